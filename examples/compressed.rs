@@ -23,7 +23,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     let output = None;
 
     let mut input_factory
-        = InputFactory::new(Some(csv_config), None, None)?;
+        = InputFactory::new(Some(csv_config), None, None, None)?;
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
     for i in inputs {
@@ -34,7 +34,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     }
 
     let csv_output_config = CSVOutputConfig::new(DELIMITER_DEFAULT, false);
-    let header_sink = factory::new_header_sink(output, Some(csv_output_config))?;
+    let header_sink = factory::new_header_sink(output, Some(csv_output_config), None)?;
     let node_output = HeaderNode::HeaderSink(header_sink);
     let chain = HeaderChain::new(vec![node_output]);
 
@@ -30,7 +30,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
 
     // source reader and headers
     let mut input_factory
-        = InputFactory::new(None, None, None)?;
+        = InputFactory::new(None, None, None, None)?;
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
     for i in inputs {
@@ -58,7 +58,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     let num_of_header_to_process = merge.num_of_header_to_process();
     let node_merge_sink = HeaderNode::MergeHeaderSink(merge, num_of_header_to_process);
     let csv_output_config = CSVOutputConfig::new(DELIMITER_DEFAULT, true);
-    let header_sink = factory::new_header_sink(output, Some(csv_output_config))?;
+    let header_sink = factory::new_header_sink(output, Some(csv_output_config), None)?;
     let node_output = HeaderNode::HeaderSink(header_sink);
     let chain_3 = HeaderChain::new(vec![node_merge_sink, node_output]);
 
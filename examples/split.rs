@@ -24,7 +24,7 @@ fn setup_test_graph() -> CliResult<Box<dyn ChopperDriver>> {
 
     // source reader and headers
     let mut input_factory
-        = InputFactory::new(None, None, None)?;
+        = InputFactory::new(None, None, None, None)?;
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
     for i in inputs {
@@ -41,12 +41,12 @@ fn setup_test_graph() -> CliResult<Box<dyn ChopperDriver>> {
     let chain_1 = HeaderChain::new(vec![node_split_sink]);
 
     // sink chain 2
-    let header_sink_1 = factory::new_header_sink(output_1, None)?;
+    let header_sink_1 = factory::new_header_sink(output_1, None, None)?;
     let node_output_1 = HeaderNode::HeaderSink(header_sink_1);
     let chain_2 = HeaderChain::new(vec![node_output_1]);
 
     // sink chain 3
-    let header_sink_2 = factory::new_header_sink(output_2, None)?;
+    let header_sink_2 = factory::new_header_sink(output_2, None, None)?;
     let node_output_2 = HeaderNode::HeaderSink(header_sink_2);
     let chain_3 = HeaderChain::new(vec![node_output_2]);
 
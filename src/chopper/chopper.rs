@@ -1,17 +1,50 @@
 use std::fmt;
 
 use crate::chopper::header_graph::{ChainId, NumOfHeaderToProcess, PinId};
-use crate::chopper::types::{Header, Row};
+use crate::chopper::types::{Header, Nanos, Row};
 use crate::error::CliResult;
 
 pub trait ChopperDriver {
     fn drive(&mut self) -> CliResult<()>;
+
+    /// Warnings of the sources read by `drive`.
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub trait Source {
     fn header(&self) -> &Header;
     fn next_row(&mut self) -> CliResult<Option<Row>>;
     fn has_native_timestamp_column(&self) -> bool;
+
+    /// Skips ahead towards the first row with timestamp >= `begin`.
+    /// Rows before `begin` may still be returned; sources that cannot seek do nothing.
+    fn seek(&mut self, _begin: Nanos) -> CliResult<()> {
+        Ok(())
+    }
+
+    /// A lower bound of the row timestamps known without reading the rows, e.g. from
+    /// the file name; lets the driver put off opening the input until its rows are due.
+    fn first_timestamp_hint(&self) -> Option<Nanos> {
+        None
+    }
+
+    /// Opaque metadata stored by the input format, e.g. the DC user header.
+    fn user_header(&self) -> Option<&[u8]> {
+        self.header().user_header()
+    }
+
+    /// Format specific details as name/value pairs, used for schema inspection.
+    fn properties(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Problems worked around while reading, e.g. skipped bad rows, for the caller
+    /// to report once the source ends.
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 //TODO better debug format?
@@ -36,8 +69,15 @@ pub trait DataSink {
 
     fn flush(&mut self) -> CliResult<()>;
 
-    fn finish(self: Box<Self>) -> CliResult<()> {
-        Ok(())
+    /// True once the sink drops every further row, e.g. a head filter that has passed its
+    /// rows; the driver then stops reading the sources that feed it.
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    /// Called once at the end of the stream; returned rows are passed on to the next node.
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        Ok(Vec::new())
     }
 
     fn boxed(self) -> Box<dyn DataSink>;
@@ -4,7 +4,7 @@ use std::fmt;
 use chrono_tz::Tz;
 
 use crate::error::{CliResult, Error};
-use crate::util::timestamp_util;
+use crate::util::timestamp_util::{self, DailyWindow, DstPolicy, TimeBound};
 
 pub type Nanos = u64;
 
@@ -12,28 +12,81 @@ pub type Nanos = u64;
 pub struct TimestampRange {
     pub begin: Option<Nanos>,
     pub end: Option<Nanos>,
+    // bounds given as offsets from the first row, resolved by the driver
+    pub begin_after_first: Option<Nanos>,
+    pub end_after_first: Option<Nanos>,
+    // only rows within this time of day, every day
+    pub daily_window: Option<DailyWindow>,
 }
 
-pub static TIMESTAMP_RANGE_DEFAULT: TimestampRange = TimestampRange { begin: None, end: None };
+pub static TIMESTAMP_RANGE_DEFAULT: TimestampRange = TimestampRange {
+    begin: None, end: None, begin_after_first: None, end_after_first: None, daily_window: None };
 
 impl TimestampRange {
-    pub fn new(begin: Option<&str>, end: Option<&str>, timezone: Tz) -> CliResult<Self> {
-        let begin = match begin {
-            Some(t) => Some(timestamp_util::parse_timestamp_range(t.to_string(), timezone)?),
-            None => None
-        };
-        let end = match end {
-            Some(t) => Some(timestamp_util::parse_timestamp_range(t.to_string(), timezone)?),
-            None => None
-        };
-        Ok(TimestampRange { begin, end })
+    pub fn new(begin: Option<&str>, end: Option<&str>, timezone: Tz, dst_policy: DstPolicy) -> CliResult<Self> {
+        let now = chrono::Utc::now();
+        let mut range = TIMESTAMP_RANGE_DEFAULT;
+        if let Some(t) = begin {
+            match timestamp_util::parse_time_bound(t, timezone, dst_policy, now)? {
+                TimeBound::Absolute(b) => range.begin = Some(b),
+                TimeBound::AfterFirstRow(b) => range.begin_after_first = Some(b),
+            }
+        }
+        if let Some(t) = end {
+            match timestamp_util::parse_time_bound(t, timezone, dst_policy, now)? {
+                TimeBound::Absolute(e) => range.end = Some(e),
+                TimeBound::AfterFirstRow(e) => range.end_after_first = Some(e),
+            }
+        }
+        Ok(range)
+    }
+
+    pub fn set_daily_window(&mut self, daily_window: Option<DailyWindow>) {
+        self.daily_window = daily_window
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.begin_after_first.is_some() || self.end_after_first.is_some()
+    }
+
+    /// Turns the bounds relative to the first row into absolute ones.
+    pub fn resolve(&mut self, first_timestamp: Nanos) {
+        if let Some(offset) = self.begin_after_first.take() {
+            self.begin = Some(first_timestamp + offset);
+        }
+        if let Some(offset) = self.end_after_first.take() {
+            self.end = Some(first_timestamp + offset);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayHint {
+    Timestamp,
+    None,
+}
+
+/// Per-field details beyond name and type, carried along with the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldMetadata {
+    pub display_hint: DisplayHint,
+    // other format specific key/value pairs, kept so they survive a round trip
+    pub properties: Vec<(String, String)>,
+}
+
+impl Default for FieldMetadata {
+    fn default() -> Self {
+        FieldMetadata { display_hint: DisplayHint::None, properties: Vec::new() }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Header {
     field_names: Vec<String>,
     field_types: Vec<FieldType>,
+    field_metadata: Vec<FieldMetadata>,
+    // opaque format specific metadata, e.g. the dc user header
+    user_header: Option<Vec<u8>>,
 }
 
 impl PartialEq for Header {
@@ -45,7 +98,8 @@ impl PartialEq for Header {
 
 impl Header {
     pub fn new(field_names: Vec<String>, field_types: Vec<FieldType>) -> Self {
-        Header { field_names, field_types }
+        let field_metadata = vec![FieldMetadata::default(); field_names.len()];
+        Header { field_names, field_types, field_metadata, user_header: None }
     }
 
     pub fn field_names(&self) -> &Vec<String> {
@@ -63,9 +117,25 @@ impl Header {
     pub fn field_types_mut(&mut self) -> &mut Vec<FieldType> {
         &mut self.field_types
     }
+
+    pub fn field_metadata(&self) -> &Vec<FieldMetadata> {
+        &self.field_metadata
+    }
+
+    pub fn field_metadata_mut(&mut self) -> &mut Vec<FieldMetadata> {
+        &mut self.field_metadata
+    }
+
+    pub fn user_header(&self) -> Option<&[u8]> {
+        self.user_header.as_deref()
+    }
+
+    pub fn set_user_header(&mut self, user_header: Option<Vec<u8>>) {
+        self.user_header = user_header
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum FieldValue {
     Boolean(bool),
     Byte(u8),
@@ -162,7 +232,7 @@ pub enum FieldType {
     String,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Row {
     pub timestamp: Nanos,
     pub field_values: Vec<FieldValue>,
@@ -1,19 +1,33 @@
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
 
 use chrono_tz::Tz;
 use clap::ArgMatches;
 
-use crate::chopper::chopper::{ChopperDriver, Source};
+use crate::chopper::chopper::{ChopperDriver, HeaderSink, Source};
 use crate::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
-use crate::chopper::types::{Header, TimestampRange};
+use crate::chopper::types::{self, Header, TimestampRange};
 use crate::cli_app::CliApp;
-use crate::driver::{driver::Driver, merge_join::MergeJoin};
-use crate::error::{self, CliResult};
+use crate::driver::{driver::{DriveLimit, Driver, Playback}, merge_join::MergeJoin};
+use crate::error::{self, CliResult, Error};
+use crate::filter::{row_filter_head::RowFilterHead, row_filter_tail::RowFilterTail};
+use crate::filter::row_filter_sample::{RowFilterSample, SampleMode};
+use crate::filter::row_filter_session::{self, RowFilterSession};
+use crate::filter::timestamp_adjust::{TimestampAdjustConfig, UnorderedPolicy};
+use crate::input::concat_source::ConcatSource;
 use crate::input::input_factory::InputFactory;
-use crate::source::csv_configs::{CSVInputConfig, CSVOutputConfig, TimestampConfig, TimestampCol};
+use crate::inspect::schema::SchemaInspector;
+use crate::inspect::stats::{self, StatsConfig, StatsSink};
+use crate::source::csv_configs::{BadRowPolicy, CSVInputConfig, CSVOutputConfig, EscapeStyle, LineTerminator, QuoteStyle,
+                                 TimestampConfig, TimestampCol, TrimMode};
+use crate::source::dc_configs::{DCInputConfig, DCOutputConfig, InvalidUtf8};
 use crate::source::source_factory::SourceFactory;
+use crate::transport::file::FollowConfig;
+use crate::transport::http::HttpConfig;
 use crate::transport::transport_factory::TransportFactory;
-use crate::util::{csv_util, timestamp_util};
+use crate::util::{csv_util, dc_util, timestamp_util};
+use crate::util::timestamp_util::DstPolicy;
 use crate::write::factory;
 
 pub fn chopper_cli(transport_factories: Option<Vec<Box<dyn TransportFactory>>>,
@@ -21,7 +35,11 @@ pub fn chopper_cli(transport_factories: Option<Vec<Box<dyn TransportFactory>>>,
                    timezone_map: Option<HashMap<&str, Tz>>) -> CliResult<()>
 {
     let mut driver = parse_cli_args(transport_factories, source_factories, timezone_map)?;
-    driver.drive()
+    driver.drive()?;
+    for warning in driver.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
 }
 
 pub fn parse_cli_args(transport_factories: Option<Vec<Box<dyn TransportFactory>>>,
@@ -33,20 +51,25 @@ pub fn parse_cli_args(transport_factories: Option<Vec<Box<dyn TransportFactory>>
         error::turn_on_backtrace()
     }
 
-    let timezone: Tz = match matches.value_of("timezone") {
-        None => timestamp_util::DEFAULT_ZONE,
-        Some(t) =>  match timezone_map {
-            None => t.parse().unwrap(),
-            Some(map) => match map.get(t) {
-                None => t.parse().unwrap(),
-                Some(tz) => *tz
-            }
-        }
+    let timezone: Tz = match (matches.value_of("timezone"), timezone_map) {
+        (Some(t), Some(map)) if map.contains_key(t) => map[t],
+        (t, _) => timestamp_util::parse_time_zone(t)?
     };
-    let timestamp_range= TimestampRange::new(
+    let dst_policy = DstPolicy::parse(matches.value_of("dst").unwrap())?;
+    let mut timestamp_range= TimestampRange::new(
         matches.value_of("begin"),
         matches.value_of("end"),
-        timezone)?;
+        timezone,
+        dst_policy)?;
+    if let Some(window) = matches.value_of("between") {
+        timestamp_range.set_daily_window(Some(timestamp_util::DailyWindow::parse(window, timezone)?));
+    }
+    let limit = parse_drive_limit(&matches)?;
+    let playback = parse_playback(&matches)?;
+    let follow = parse_follow(&matches)?;
+    let timestamp_adjust = parse_timestamp_adjust(&matches)?;
+    let row_filters = parse_row_filters(&matches, timezone)?;
+    let stats = parse_stats_config(&matches)?;
 
     let inputs = match matches.values_of("input") {
         None => None,
@@ -64,66 +87,136 @@ pub fn parse_cli_args(transport_factories: Option<Vec<Box<dyn TransportFactory>>
     };
 
     // csv only
-    let csv_input_config = parse_csv_config(&matches, timezone)?;
-    let output_delimiter = matches.value_of("csv_output_delimiter").unwrap();
-    let print_timestamp = match matches.value_of("csv_print_ts").unwrap() {
-        "auto" => None,
-        "true" => Some(true),
-        "false" => Some(false),
-        _ => unreachable!()
+    let csv_input_config = parse_csv_config(&matches, timezone, dst_policy)?;
+    let dc_input_config = parse_dc_input_config(&matches)?;
+    let mut input_factory
+        = InputFactory::new(
+        Some(csv_input_config),
+        Some(dc_input_config),
+        source_factories,
+        transport_factories)?;
+    input_factory.set_follow(follow);
+    // --duration also ends inputs that stall
+    input_factory.set_deadline(limit.duration.map(|d| Instant::now() + d));
+    input_factory.set_http(parse_http_config(&matches)?);
+
+    if matches.is_present("schema") {
+        return setup_schema(inputs, timezone, input_factory, matches.is_present("json"))
+    }
+    let input = InputOptions { inputs, input_factory, timestamp_range, timezone, concat: matches.is_present("concat") };
+    let output = OutputOptions {
+        output: outputs,
+        csv_delimiter: matches.value_of("csv_output_delimiter").unwrap(),
+        csv_hint_format: match matches.value_of("csv_output_hint_fmt") {
+            None => Some(timestamp_util::DEFAULT_OUTPUT_FORMAT),
+            Some("raw") => None,
+            Some(f) => Some(f)
+        },
+        csv_quote_style: QuoteStyle::parse(matches.value_of("csv_output_quote").unwrap())?,
+        csv_line_terminator: LineTerminator::parse(matches.value_of("csv_output_terminator").unwrap())?,
+        csv_null: matches.value_of("csv_output_null").unwrap_or(""),
+        csv_print_timestamp: match matches.value_of("csv_print_ts").unwrap() {
+            "auto" => None,
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => unreachable!()
+        },
+        dc_config: parse_dc_output_config(&matches)?,
+        stats
     };
+    let drive = DriveOptions { limit, playback, follow };
+    setup_graph(input, timestamp_adjust, row_filters, output, drive)
+}
+
+// the inputs and how to open them
+struct InputOptions<'a> {
+    inputs: Option<Vec<&'a str>>,
+    input_factory: InputFactory,
+    timestamp_range: TimestampRange,
+    timezone: Tz,
+    // read the inputs one after another rather than merged
+    concat: bool,
+}
+
+// where the rows go and how they are written
+struct OutputOptions<'a> {
+    output: Option<&'a str>,
+    csv_delimiter: &'a str,
+    csv_hint_format: Option<&'a str>,
+    csv_quote_style: QuoteStyle,
+    csv_line_terminator: LineTerminator,
+    csv_null: &'a str,
+    // None to print the timestamp only if all inputs have a native timestamp column
+    csv_print_timestamp: Option<bool>,
+    dc_config: DCOutputConfig,
+    // write statistics rather than the rows
+    stats: Option<StatsConfig>,
+}
 
-    setup_graph(inputs,
-                outputs,
-                transport_factories,
-                source_factories,
-                timestamp_range,
-                csv_input_config,
-                output_delimiter,
-                print_timestamp)
-}
-
-fn setup_graph(inputs: Option<Vec<&str>>,
-               output: Option<&str>,
-               transport_factories: Option<Vec<Box<dyn TransportFactory>>>,
-               source_factories: Option<Vec<Box<dyn SourceFactory>>>,
-               timestamp_range: TimestampRange,
-               csv_input_config: CSVInputConfig,
-               csv_output_delimiter: &str,
-               csv_output_print_timestamp: Option<bool>) -> CliResult<Box<dyn ChopperDriver>>
+// how the driver runs
+struct DriveOptions {
+    limit: DriveLimit,
+    playback: Option<Playback>,
+    follow: Option<FollowConfig>,
+}
+
+fn setup_graph(input: InputOptions,
+               mut timestamp_adjust: TimestampAdjustConfig,
+               row_filters: Vec<Box<dyn HeaderSink>>,
+               output: OutputOptions,
+               drive: DriveOptions) -> CliResult<Box<dyn ChopperDriver>>
 {
+    let InputOptions { inputs, mut input_factory, timestamp_range, timezone, concat } = input;
     // get sources and headers
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
-    let mut input_factory
-        = InputFactory::new(
-        Some(csv_input_config),
-        source_factories,
-        transport_factories)?;
 
-    let csv_output_config = match csv_output_print_timestamp {
-        Some(b) => CSVOutputConfig::new(csv_output_delimiter, b),
-        None => csv_util::create_csv_output_config_from_source(&mut sources, csv_output_delimiter)
+    let mut csv_output_config = match output.csv_print_timestamp {
+        Some(b) => CSVOutputConfig::new(output.csv_delimiter, b),
+        None => csv_util::create_csv_output_config_from_source(&mut sources, output.csv_delimiter)
     };
+    csv_output_config.set_hint_timestamp_format(output.csv_hint_format.map(|f| f.to_string()));
+    csv_output_config.set_quote_style(output.csv_quote_style);
+    csv_output_config.set_line_terminator(output.csv_line_terminator);
+    csv_output_config.set_null_value(output.csv_null.to_string());
 
     let mut header_nodes: Vec<HeaderNode> = Vec::new();
     let mut chains: Vec<HeaderChain> = Vec::new();
     match inputs {
         Some(inputs) => {
-            for i in 0..inputs.len() {
-                let source = input_factory.create_source_from_path(inputs.get(i).unwrap())?;
+            for input in inputs {
+                // globs and directories expand to files opened as their rows are due
+                match input_factory.expand_input(input, &timestamp_range, timezone)? {
+                    Some(expanded) => for e in expanded {
+                        let hint = e.time_span.map(|s| s.begin);
+                        sources.push(input_factory.create_lazy_source_from_path(&e.path, hint)?);
+                    },
+                    // concatenated files are opened one at a time; a lazy source opens its input
+                    // twice, so streams and urls are opened once, up front
+                    None if concat && Path::new(input).is_file() =>
+                        sources.push(input_factory.create_lazy_source_from_path(input, None)?),
+                    None => sources.push(input_factory.create_source_from_path(input)?)
+                }
+            }
+            if sources.is_empty() {
+                return Err(Error::from("No input files with rows in the time range"))
+            }
+            if concat && sources.len() > 1 {
+                sources = vec![Box::new(ConcatSource::new(sources)?)];
+            }
+            let source_count = sources.len();
+            for (i, source) in sources.iter().enumerate() {
                 headers.push(source.header().clone());
-                sources.push(source);
                 // add Merge to chains if multiple input files
-                if inputs.len() > 1 {
-                    let merge = HeaderNode::Merge(inputs.len(), i);
+                if source_count > 1 {
+                    let merge = HeaderNode::Merge(source_count, i);
                     let chain = HeaderChain::new(vec![merge]);
                     chains.push(chain);
                 }
             }
             // add MergeHeaderSink as first header node if multiple input files
-            if inputs.len() > 1 {
-                let merge = MergeJoin::new(inputs.len())?;
+            if source_count > 1 {
+                let merge = MergeJoin::new(source_count)?;
                 let num_of_header_to_process = merge.num_of_header_to_process();
                 let node_merge_sink = HeaderNode::MergeHeaderSink(merge, num_of_header_to_process);
                 header_nodes.push(node_merge_sink);
@@ -137,16 +230,223 @@ fn setup_graph(inputs: Option<Vec<&str>>,
             sources.push(source);
         }
     }
-    let header_sink = factory::new_header_sink(output, Some(csv_output_config))?;
+    if timestamp_adjust.is_active() {
+        // the shift is in seconds; dc timestamps are nanos
+        timestamp_adjust.set_nanos(sources.iter().all(|s| s.has_native_timestamp_column()));
+        header_nodes.push(HeaderNode::HeaderSink(timestamp_adjust.boxed()));
+    }
+    for row_filter in row_filters {
+        header_nodes.push(HeaderNode::HeaderSink(row_filter));
+    }
+    let header_sink: Box<dyn HeaderSink> = match output.stats {
+        Some(config) => Box::new(StatsSink::new(&output.output.map(|o| o.to_string()), config)?),
+        None => factory::new_header_sink(output.output, Some(csv_output_config), Some(output.dc_config))?
+    };
     let node_hs = HeaderNode::HeaderSink(header_sink);
     header_nodes.push(node_hs);
     chains.push(HeaderChain::new(header_nodes));
     let graph = HeaderGraph::new(chains);
 
-    Ok(Box::new(Driver::new(sources, graph, timestamp_range, headers)?))
+    let mut driver = Driver::new(sources, graph, timestamp_range, headers)?;
+    driver.set_limit(drive.limit);
+    driver.set_playback(drive.playback);
+    driver.set_flush_every_row(drive.follow.is_some());
+    Ok(Box::new(driver))
 }
 
-fn parse_csv_config(matches: &ArgMatches, timezone: Tz) -> CliResult<CSVInputConfig> {
+fn setup_schema(inputs: Option<Vec<&str>>,
+                timezone: Tz,
+                mut input_factory: InputFactory,
+                json: bool) -> CliResult<Box<dyn ChopperDriver>>
+{
+    let mut sources: Vec<(String, Box<dyn Source>)> = Vec::new();
+    match inputs {
+        Some(inputs) => {
+            for input in inputs {
+                // all the files, whatever their time range
+                match input_factory.expand_input(input, &types::TIMESTAMP_RANGE_DEFAULT, timezone)? {
+                    Some(expanded) => for e in expanded {
+                        let source = input_factory.create_lazy_source_from_path(&e.path, None)?;
+                        sources.push((e.path, source));
+                    },
+                    None => sources.push((input.to_string(), input_factory.create_source_from_path(input)?))
+                }
+            }
+        }
+        None => sources.push(("stdin".to_string(), input_factory.create_source_from_stdin("csv")?))
+    }
+    Ok(Box::new(SchemaInspector::new(sources, json)))
+}
+
+fn parse_drive_limit(matches: &ArgMatches) -> CliResult<DriveLimit> {
+    let rows = parse_number::<u64>(matches, "limit")?;
+    let duration = match matches.value_of("duration") {
+        None => None,
+        Some(d) => Some(timestamp_util::parse_duration(d)?)
+    };
+    Ok(DriveLimit { rows, duration })
+}
+
+fn parse_playback(matches: &ArgMatches) -> CliResult<Option<Playback>> {
+    if !matches.is_present("replay") {
+        return Ok(None)
+    }
+    let speed = parse_number::<f64>(matches, "replay")?.unwrap_or(1.0);
+    let max_gap = match matches.value_of("replay_max_gap") {
+        None => None,
+        Some(d) => Some(timestamp_util::parse_duration(d)?)
+    };
+    let nanos_per_unit = match matches.value_of("replay_unit").unwrap() {
+        "s" => 1_000_000_000,
+        "ms" => 1_000_000,
+        "us" => 1_000,
+        "ns" => 1,
+        _ => unreachable!()
+    };
+    Ok(Some(Playback::new(speed, max_gap, nanos_per_unit)?))
+}
+
+fn parse_follow(matches: &ArgMatches) -> CliResult<Option<FollowConfig>> {
+    if !matches.is_present("follow") {
+        return Ok(None)
+    }
+    let mut follow = FollowConfig::default();
+    if let Some(d) = matches.value_of("follow_poll") {
+        follow.poll_interval = timestamp_util::parse_duration(d)?;
+    }
+    if let Some(d) = matches.value_of("follow_idle") {
+        follow.idle_timeout = Some(timestamp_util::parse_duration(d)?);
+    }
+    Ok(Some(follow))
+}
+
+fn parse_http_config(matches: &ArgMatches) -> CliResult<HttpConfig> {
+    let mut config = HttpConfig::new_default();
+    if let Some(path) = matches.value_of("http_header_file") {
+        config.read_header_file(path)?;
+    }
+    if let Some(headers) = matches.values_of("http_header") {
+        for header in headers {
+            config.add_header_line(header)?;
+        }
+    }
+    if let Some(var) = matches.value_of("http_bearer_env") {
+        match std::env::var(var) {
+            Ok(token) => config.set_bearer_token(&token)?,
+            Err(_) => return Err(Error::from(format!("Http -- environment variable {} is not set", var)))
+        }
+    }
+    if let Some(d) = matches.value_of("http_connect_timeout") {
+        config.set_connect_timeout(timestamp_util::parse_duration(d)?);
+    }
+    if let Some(d) = matches.value_of("http_timeout") {
+        config.set_read_timeout(timestamp_util::parse_duration(d)?);
+    }
+    if let Some(retries) = parse_number(matches, "http_retries")? {
+        config.set_retries(retries);
+    }
+    Ok(config)
+}
+
+fn parse_stats_config(matches: &ArgMatches) -> CliResult<Option<StatsConfig>> {
+    if !matches.is_present("stats") {
+        return Ok(None)
+    }
+    let gap_threshold = parse_number::<u64>(matches, "stats_gap")?;
+    let top_k = parse_number::<usize>(matches, "stats_top_k")?.unwrap_or(stats::TOP_K_DEFAULT);
+    let json = matches.is_present("json");
+    Ok(Some(StatsConfig { gap_threshold, top_k, json }))
+}
+
+// row filters in the order they are applied after the timestamp adjust: session, sample, head, tail
+fn parse_row_filters(matches: &ArgMatches, timezone: Tz) -> CliResult<Vec<Box<dyn HeaderSink>>> {
+    let mut row_filters: Vec<Box<dyn HeaderSink>> = Vec::new();
+    // session first, so that sampling and head/tail count rows in session only
+    // the --between window is applied to the time range; here it only tells which day
+    // a session past midnight belongs to
+    let window = match matches.value_of("between") {
+        Some(w) => Some(timestamp_util::DailyWindow::parse(w, timezone)?),
+        None => None
+    };
+    let days = match matches.value_of("days") {
+        Some(d) => Some(row_filter_session::parse_days(d)?),
+        None if matches.is_present("weekdays") => Some(row_filter_session::WEEKDAYS.to_vec()),
+        None => None
+    };
+    let holidays = match matches.value_of("holidays") {
+        Some(path) => row_filter_session::read_holidays(path)?,
+        None => Vec::new()
+    };
+    if days.is_some() || !holidays.is_empty() {
+        row_filters.push(RowFilterSession::new(window, days, holidays, timezone));
+    }
+    let sample_mode = parse_number::<u64>(matches, "sample_every")?.map(SampleMode::Every)
+        .or(parse_number::<f64>(matches, "sample_prob")?.map(SampleMode::Probability))
+        .or(parse_number::<usize>(matches, "sample_reservoir")?.map(SampleMode::Reservoir));
+    if let Some(mode) = sample_mode {
+        let seed = parse_number::<u64>(matches, "sample_seed")?;
+        row_filters.push(RowFilterSample::new(mode, seed)?);
+    }
+    if let Some(n) = parse_number::<u64>(matches, "head")? {
+        row_filters.push(RowFilterHead::new(n));
+    }
+    if let Some(n) = parse_number::<usize>(matches, "tail")? {
+        row_filters.push(RowFilterTail::new(n));
+    }
+    Ok(row_filters)
+}
+
+fn parse_timestamp_adjust(matches: &ArgMatches) -> CliResult<TimestampAdjustConfig> {
+    let mut config = TimestampAdjustConfig::new();
+    config.set_from_column(matches.value_of("ts_from").map(|c| c.to_string()));
+    if let Some(shift) = matches.value_of("ts_shift") {
+        let (sign, duration) = match shift.strip_prefix('-') {
+            Some(d) => (-1, d),
+            None => (1, shift.strip_prefix('+').unwrap_or(shift))
+        };
+        let duration = timestamp_util::parse_duration(duration)?;
+        if duration.subsec_nanos() != 0 {
+            return Err(Error::from(format!("Cannot shift timestamps by a fraction of a second: {}", shift)))
+        }
+        config.set_shift(sign * duration.as_secs() as i64);
+    }
+    config.set_rebase(matches.is_present("ts_rebase"));
+    config.set_keep_original(matches.value_of("ts_keep_original").map(|c| c.to_string()));
+    config.set_unordered_policy(UnorderedPolicy::parse(matches.value_of("ts_unordered").unwrap())?);
+    Ok(config)
+}
+
+fn parse_number<T: std::str::FromStr>(matches: &ArgMatches, name: &str) -> CliResult<Option<T>> {
+    match matches.value_of(name) {
+        None => Ok(None),
+        Some(v) => match v.parse::<T>() {
+            Ok(n) => Ok(Some(n)),
+            Err(_) => Err(Error::from(format!("Cannot parse {}: {}", name, v)))
+        }
+    }
+}
+
+fn parse_dc_input_config(matches: &ArgMatches) -> CliResult<DCInputConfig> {
+    let lenient = matches.is_present("lenient");
+    let invalid_utf8 = InvalidUtf8::parse(matches.value_of("dc_invalid_utf8").unwrap())?;
+    Ok(DCInputConfig::new(lenient, invalid_utf8))
+}
+
+fn parse_dc_output_config(matches: &ArgMatches) -> CliResult<DCOutputConfig> {
+    let user_header = match (matches.value_of("dc_user_header"), matches.value_of("dc_user_header_file")) {
+        (Some(text), _) => Some(text.as_bytes().to_vec()),
+        (None, Some(file)) => Some(std::fs::read(file)?),
+        (None, None) => None
+    };
+    let mut dc_output_config = DCOutputConfig::new(user_header);
+    let version = parse_number::<u16>(matches, "dc_version")?.unwrap();
+    dc_output_config.set_version(version)?;
+    dc_output_config.set_timestamp_unit(dc_util::TimestampUnit::parse(matches.value_of("dc_ts_unit").unwrap())?);
+    dc_output_config.set_compression(dc_util::BlockCompression::parse(matches.value_of("dc_compression").unwrap())?);
+    Ok(dc_output_config)
+}
+
+fn parse_csv_config(matches: &ArgMatches, timezone: Tz, dst_policy: DstPolicy) -> CliResult<CSVInputConfig> {
     let input_delimiter = matches.value_of("csv_input_delimiter").unwrap();
     let has_header = matches.is_present("csv_has_header");
 
@@ -160,8 +460,7 @@ fn parse_csv_config(matches: &ArgMatches, timezone: Tz) -> CliResult<CSVInputCon
                 Some(fmt) => ts_fmt = Some(fmt.to_string())
             }
             // col
-            let ts = matches.value_of("csv_ts_col").unwrap();
-            TimestampCol::Timestamp(ts.parse::<usize>().unwrap())
+            TimestampCol::parse(matches.value_of("csv_ts_col").unwrap())
         },
         Some(d) => {
             // format
@@ -173,15 +472,40 @@ fn parse_csv_config(matches: &ArgMatches, timezone: Tz) -> CliResult<CSVInputCon
                 }
             };
             // col
-            let date = d.parse::<usize>().unwrap();
             let time = match matches.value_of("csv_ts_col_time") {
-                Some(t) => t.parse::<usize>().unwrap(),
+                Some(t) => t,
                 None => unreachable!()
             };
-            TimestampCol::DateAndTime(date, time)
+            TimestampCol::parse_date_and_time(d, time)
         }
     };
-    let ts_config = TimestampConfig::new(ts_col, ts_fmt, timezone);
+    let mut ts_config = TimestampConfig::new(ts_col, ts_fmt, timezone);
+    ts_config.set_drop_columns(matches.is_present("csv_drop_ts"));
+    ts_config.set_dst_policy(dst_policy);
 
-    CSVInputConfig::new(input_delimiter, has_header, ts_config)
+    let mut csv_config = CSVInputConfig::new(input_delimiter, has_header, ts_config)?;
+
+    // dialect
+    let quote = match matches.value_of("csv_quote").unwrap() {
+        "none" => None,
+        q => Some(csv_util::parse_into_delimiter(q)?)
+    };
+    csv_config.set_quote(quote);
+    csv_config.set_escape(EscapeStyle::parse(matches.value_of("csv_escape").unwrap())?);
+    if let Some(c) = matches.value_of("csv_comment") {
+        csv_config.set_comment(Some(csv_util::parse_into_delimiter(c)?));
+    }
+    csv_config.set_trim(TrimMode::parse(matches.value_of("csv_trim").unwrap())?);
+    csv_config.set_flexible(matches.is_present("csv_flexible"));
+    csv_config.set_bad_row_policy(BadRowPolicy::parse(matches.value_of("csv_bad_rows").unwrap())?);
+    if let Some(path) = matches.value_of("csv_rejected") {
+        csv_config.set_rejected_rows(Some(csv_util::RejectedRows::new(path)?));
+    }
+    if let Some(n) = parse_number::<usize>(matches, "csv_skip_lines")? {
+        csv_config.set_skip_lines(n);
+    }
+    if let Some(values) = matches.values_of("csv_null") {
+        csv_config.set_null_values(values.map(|v| v.to_string()).collect());
+    }
+    Ok(csv_config)
 }
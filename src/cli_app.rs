@@ -9,36 +9,294 @@ impl CliApp {
             .version(crate_version!())
             .about("chopper is a simple streaming time series tool")
             .arg(Arg::with_name("input")
-                .help("sets the input files to use; \nif missing, stdin will be used")
+                .help("sets the input files to use; \nif missing, stdin will be used; \
+                        \nglobs and directories read all matching files, skipping those whose \
+                        \nname date or dc footer is outside --begin/--end; e.g. 'data/2019/06/*/*.dc.gz'; \
+                        \nlocal streams are read from tcp://host:port or unix:///path.sock, \
+                        \nwith ?listen to wait for the producer to connect and &format=csv|dc")
                 .multiple(true))
+            .arg(Arg::with_name("concat")
+                .long("concat")
+                .help("read the inputs one after another instead of merging them, for time-adjacent \
+                        \ninputs of one feed such as consecutive days; each input must start no \
+                        \nearlier than the previous one ends"))
             .arg(Arg::with_name("output")
                 .long("output")
                 .short("o")
-                .help("output to a file")
+                .help("output to a file, or to a local stream: tcp://host:port or unix:///path.sock, \
+                        \nwith ?listen to wait for the consumer to connect and &format=csv|dc")
                 .takes_value(true)
                 .value_name("FILE"))
             .arg(Arg::with_name("timezone")
                 .long("timezone")
-                .help("specify time zone for timestamp.")
+                .visible_alias("tz")
+                .help("specify time zone for timestamps without an offset, e.g. America/New_York [default: UTC]")
                 .takes_value(true)
                 .case_insensitive(true)
                 .value_name("ARG"))
+            .arg(Arg::with_name("dst")
+                .long("dst")
+                .help("how local times that are repeated or skipped by a DST transition \
+                        \nare read: the earliest or latest instant, or an error")
+                .takes_value(true)
+                .default_value("error")
+                .possible_values(&["earliest", "latest", "error"])
+                .value_name("ARG"))
             .arg(Arg::with_name("begin")
                 .short("b")
                 .long("begin")
-                .help("set begin timestamp (inclusive); \ndate: yyyymmdd, time: hh:mm:ss")
+                .help("set begin timestamp (inclusive); \ndate: yyyymmdd, time: hh:mm:ss, or ISO 8601; \
+                        \nalso now, -DURATION before now, +DURATION after the first row, \
+                        \nor today/yesterday/tomorrow with an optional hh:mm[:ss]")
                 .takes_value(true)
+                .allow_hyphen_values(true)
                 .value_name("TIMESTAMP"))
             .arg(Arg::with_name("end")
                 .short("e")
                 .long("end")
-                .help("set end timestamp (exclusive); \ndate: yyyymmdd, time: hh:mm:ss")
+                .help("set end timestamp (exclusive); same forms as --begin")
                 .takes_value(true)
+                .allow_hyphen_values(true)
                 .value_name("TIMESTAMP"))
+            .arg(Arg::with_name("between")
+                .long("between")
+                .visible_alias("session")
+                .help("output only rows within a time of day window every day, in the time zone; \
+                        \ne.g. 09:30-16:00; with --weekdays, --days or --holidays, a window past \
+                        \nmidnight belongs to the day it starts on")
+                .takes_value(true)
+                .value_name("HH:MM-HH:MM"))
+            .arg(Arg::with_name("weekdays")
+                .long("weekdays")
+                .help("output only rows on monday to friday, in the time zone")
+                .conflicts_with("days"))
+            .arg(Arg::with_name("days")
+                .long("days")
+                .help("output only rows on these days of the week, in the time zone; \
+                        \ne.g. mon-fri or mon,wed,fri")
+                .takes_value(true)
+                .value_name("DAYS"))
+            .arg(Arg::with_name("holidays")
+                .long("holidays")
+                .help("drop rows on the dates listed in a file, one yyyy-mm-dd per line")
+                .takes_value(true)
+                .value_name("FILE"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .help("stop after writing N rows")
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("duration")
+                .long("duration")
+                .help("stop after running for a wall-clock duration; \ne.g. 500ms, 30s, 5m, 2h")
+                .takes_value(true)
+                .value_name("DURATION"))
+            .arg(Arg::with_name("follow")
+                .short("f")
+                .long("follow")
+                .help("keep reading input files as they grow, like tail -f, \
+                        \nreopening files that are rotated or truncated"))
+            .arg(Arg::with_name("follow_poll")
+                .long("follow-poll")
+                .help("follow only: wait between checks for new data [default: 200ms]")
+                .takes_value(true)
+                .value_name("DURATION")
+                .requires("follow"))
+            .arg(Arg::with_name("follow_idle")
+                .long("follow-idle")
+                .help("follow only: stop once the inputs have not grown for this long")
+                .takes_value(true)
+                .value_name("DURATION")
+                .requires("follow"))
+            .arg(Arg::with_name("http_header")
+                .long("http-header")
+                .help("send a header with http(s) requests; e.g. 'X-Api-Key: abc'")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("NAME: VALUE"))
+            .arg(Arg::with_name("http_header_file")
+                .long("http-header-file")
+                .help("send the headers in a file with http(s) requests, one 'Name: value' per line")
+                .takes_value(true)
+                .value_name("FILE"))
+            .arg(Arg::with_name("http_bearer_env")
+                .long("http-bearer-env")
+                .help("send the token in an environment variable as bearer auth with http(s) requests")
+                .takes_value(true)
+                .value_name("VAR"))
+            .arg(Arg::with_name("http_connect_timeout")
+                .long("http-connect-timeout")
+                .help("give up connecting to an http(s) server after this long [default: 10s]")
+                .takes_value(true)
+                .value_name("DURATION"))
+            .arg(Arg::with_name("http_timeout")
+                .long("http-timeout")
+                .help("give up waiting for data from an http(s) server after this long [default: 30s]")
+                .takes_value(true)
+                .value_name("DURATION"))
+            .arg(Arg::with_name("http_retries")
+                .long("http-retries")
+                .help("retry failed http(s) requests and resume cut off downloads up to N times, \
+                        \nwaiting twice as long each time from 500ms [default: 3]")
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("replay")
+                .long("replay")
+                .help("emit rows at wall-clock intervals matching their timestamps, \
+                        \noptionally faster or slower by a speed multiplier; e.g. --replay=10")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("SPEED"))
+            .arg(Arg::with_name("replay_max_gap")
+                .long("replay-max-gap")
+                .help("replay only: longest wall-clock wait between two rows; e.g. 500ms, 5s")
+                .takes_value(true)
+                .value_name("DURATION")
+                .requires("replay"))
+            .arg(Arg::with_name("replay_unit")
+                .long("replay-unit")
+                .help("replay only: unit of the row timestamps")
+                .takes_value(true)
+                .default_value("s")
+                .possible_values(&["s", "ms", "us", "ns"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("head")
+                .long("head")
+                .help("output only the first N rows")
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("tail")
+                .long("tail")
+                .help("output only the last N rows")
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("sample_every")
+                .long("sample-every")
+                .help("output every Nth row")
+                .takes_value(true)
+                .value_name("N")
+                .conflicts_with_all(&["sample_prob", "sample_reservoir"]))
+            .arg(Arg::with_name("sample_prob")
+                .long("sample-prob")
+                .help("output each row with probability P")
+                .takes_value(true)
+                .value_name("P")
+                .conflicts_with_all(&["sample_every", "sample_reservoir"]))
+            .arg(Arg::with_name("sample_reservoir")
+                .long("sample-reservoir")
+                .help("output a uniform random sample of K rows")
+                .takes_value(true)
+                .value_name("K")
+                .conflicts_with_all(&["sample_every", "sample_prob"]))
+            .arg(Arg::with_name("sample_seed")
+                .long("sample-seed")
+                .help("seed for random sampling")
+                .takes_value(true)
+                .value_name("SEED"))
+            .arg(Arg::with_name("ts_from")
+                .long("ts-from")
+                .help("take row timestamps from this column instead, e.g. exchange time \
+                        \ninstead of receive time")
+                .takes_value(true)
+                .value_name("COLUMN"))
+            .arg(Arg::with_name("ts_shift")
+                .long("ts-shift")
+                .help("shift row timestamps by a signed duration in whole seconds; \
+                        \ne.g. -5s or +1h")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("DURATION"))
+            .arg(Arg::with_name("ts_rebase")
+                .long("ts-rebase")
+                .help("shift row timestamps so that the first row is at zero"))
+            .arg(Arg::with_name("ts_keep_original")
+                .long("ts-keep-original")
+                .help("copy the original row timestamp into a new column with this name \
+                        \nbefore adjusting it")
+                .takes_value(true)
+                .value_name("NAME"))
+            .arg(Arg::with_name("ts_unordered")
+                .long("ts-unordered")
+                .help("what to do with rows whose adjusted timestamp goes backwards")
+                .takes_value(true)
+                .default_value("error")
+                .possible_values(&["error", "drop"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("schema")
+                .long("schema")
+                .help("print field names, types and format details of the inputs instead of the data"))
+            .arg(Arg::with_name("stats")
+                .long("stats")
+                .help("print per-column and timestamp statistics of the stream instead of the data")
+                .conflicts_with("schema"))
+            .arg(Arg::with_name("stats_gap")
+                .long("stats-gap")
+                .help("stats only: report gaps between consecutive timestamps larger than N")
+                .takes_value(true)
+                .value_name("N")
+                .requires("stats"))
+            .arg(Arg::with_name("stats_top_k")
+                .long("stats-top-k")
+                .help("stats only: number of most frequent values reported for string columns [default: 5]")
+                .takes_value(true)
+                .value_name("K")
+                .requires("stats"))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("print inspection output as json"))
             .arg(Arg::with_name("backtrace")
                 .long("backtrace")
                 .help("print backtrace"))
 
+            //  below are dc only
+            .arg(Arg::with_name("lenient")
+                .long("lenient")
+                .help("dc only: on a corrupted or truncated input, report the byte offset \
+                        \nand stop reading that input instead of failing"))
+            .arg(Arg::with_name("dc_invalid_utf8")
+                .long("dc-invalid-utf8")
+                .help("dc only: how to read string values that are not valid utf-8")
+                .takes_value(true)
+                .default_value("error")
+                .possible_values(&["error", "lossy"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("dc_user_header")
+                .long("dc-user-header")
+                .help("dc only: user header to write to the output, as text; \nby default it is copied from a dc input")
+                .takes_value(true)
+                .value_name("TEXT")
+                .conflicts_with("dc_user_header_file"))
+            .arg(Arg::with_name("dc_user_header_file")
+                .long("dc-user-header-file")
+                .help("dc only: user header to write to the output, read from a file")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("dc_user_header"))
+
+            .arg(Arg::with_name("dc_version")
+                .long("dc-version")
+                .help("dc only: format version of the output")
+                .takes_value(true)
+                .default_value("2")
+                .possible_values(&["2", "3"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("dc_ts_unit")
+                .long("dc-ts-unit")
+                .help("dc v3 only: timestamp unit of the output")
+                .takes_value(true)
+                .default_value("ns")
+                .possible_values(&["ns", "us"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("dc_compression")
+                .long("dc-compression")
+                .help("dc v3 only: compression of the output blocks")
+                .takes_value(true)
+                .default_value("deflate")
+                .possible_values(&["none", "deflate"])
+                .value_name("ARG"))
+
             //  below are csv only
             .arg(Arg::with_name("csv_input_delimiter")
                 .long("csv-in-delimiter")
@@ -48,10 +306,92 @@ impl CliApp {
                 .value_name("ARG"))
             .arg(Arg::with_name("csv_output_delimiter")
                 .long("csv-out-delimiter")
-                .help("csv only: output field/column delimiter")
+                .help("csv only: output field/column delimiter, a single character")
                 .takes_value(true)
                 .default_value(",")
                 .value_name("ARG"))
+            .arg(Arg::with_name("csv_quote")
+                .long("csv-quote")
+                .help("csv only: input quote character; 'none' disables quoting")
+                .takes_value(true)
+                .default_value("\"")
+                .value_name("CHAR"))
+            .arg(Arg::with_name("csv_escape")
+                .long("csv-escape")
+                .help("csv only: how quotes are escaped inside quoted input fields")
+                .takes_value(true)
+                .default_value("double")
+                .possible_values(&["double", "backslash"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("csv_comment")
+                .long("csv-comment")
+                .help("csv only: skip input lines starting with this character")
+                .takes_value(true)
+                .value_name("CHAR"))
+            .arg(Arg::with_name("csv_trim")
+                .long("csv-trim")
+                .help("csv only: trim whitespace around input headers and/or fields")
+                .takes_value(true)
+                .default_value("all")
+                .possible_values(&["none", "headers", "fields", "all"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("csv_flexible")
+                .long("csv-flexible")
+                .help("csv only: allow input rows with a different number of fields; \
+                        \nmissing fields are read as nulls, extra fields are dropped")
+                .conflicts_with("csv_bad_rows"))
+            .arg(Arg::with_name("csv_bad_rows")
+                .long("csv-bad-rows")
+                .help("csv only: what to do with input rows whose number of fields differs from the header; \
+                        \npad fills short rows with nulls, truncate drops extra fields of long rows")
+                .takes_value(true)
+                .default_value("error")
+                .possible_values(&["error", "skip", "pad", "truncate"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("csv_rejected")
+                .long("csv-rejected")
+                .help("csv only: write rejected input rows with line number and reason to a csv file")
+                .takes_value(true)
+                .value_name("FILE"))
+            .arg(Arg::with_name("csv_skip_lines")
+                .long("csv-skip-lines")
+                .help("csv only: skip N lines at the start of each input, before the header")
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("csv_null")
+                .long("csv-null")
+                .help("csv only: input value read as null, e.g. NA or \\N; \
+                        \nmay be given multiple times, '' matches empty fields")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("VALUE"))
+            .arg(Arg::with_name("csv_output_quote")
+                .long("csv-out-quote")
+                .help("csv only: which output fields are quoted")
+                .takes_value(true)
+                .default_value("necessary")
+                .possible_values(&["necessary", "always", "non-numeric", "never"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("csv_output_terminator")
+                .long("csv-out-terminator")
+                .help("csv only: output line terminator")
+                .takes_value(true)
+                .default_value("lf")
+                .possible_values(&["lf", "crlf", "cr"])
+                .value_name("ARG"))
+            .arg(Arg::with_name("csv_output_null")
+                .long("csv-out-null")
+                .help("csv only: output value written for nulls [default: empty]")
+                .takes_value(true)
+                .value_name("VALUE"))
+
+            .arg(Arg::with_name("csv_output_hint_fmt")
+                .long("csv-out-hint-fmt")
+                .help("csv only: output format of columns hinted as timestamps (nanos, printed in UTC); \
+                        \n'raw' prints the value as is [default: %Y-%m-%d %H:%M:%S%.9f]")
+                .takes_value(true)
+                .value_name("ARG"))
 
             // has header
             .arg(Arg::with_name("csv_has_header")
@@ -71,7 +411,9 @@ impl CliApp {
             // timestamp column
             .arg(Arg::with_name("csv_ts_col")
                 .long("csv-ts")
-                .help("csv only: specify the timestamp column index")
+                .help("csv only: specify the timestamp column by index or header name; \
+                        \n'auto' picks a column named like ts, time or timestamp, \
+                        \nor else the first column holding a date time")
                 .takes_value(true)
                 .default_value("0")
                 .value_name("ARG")
@@ -79,7 +421,7 @@ impl CliApp {
             // timestamp column date
             .arg(Arg::with_name("csv_ts_col_date")
                 .long("csv-ts-date")
-                .help("csv only: specify the timestamp date column index. \
+                .help("csv only: specify the timestamp date column by index or header name. \
                         \nused when date and time are in separate columns")
                 .takes_value(true)
                 .value_name("ARG")
@@ -88,12 +430,15 @@ impl CliApp {
             // timestamp column time
             .arg(Arg::with_name("csv_ts_col_time")
                 .long("csv-ts-time")
-                .help("csv only: specify the timestamp time column index. \
+                .help("csv only: specify the timestamp time column by index or header name. \
                         \nused when date and time are in separate columns")
                 .takes_value(true)
                 .value_name("ARG")
                 .requires("csv_ts_col_date")
                 .conflicts_with("csv_ts_col"))
+            .arg(Arg::with_name("csv_drop_ts")
+                .long("csv-drop-ts")
+                .help("csv only: drop the timestamp column(s) from the output fields"))
 
             // timestamp format
             .arg(Arg::with_name("csv_ts_fmt")
@@ -1,3 +1,6 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
 use crate::chopper::chopper::{ChopperDriver, Source};
 use crate::chopper::data_graph::{DataGraph, DataNode};
 use crate::chopper::header_graph::{ChainId, HeaderGraph, NodeId, PinId};
@@ -5,10 +8,59 @@ use crate::chopper::types::{Header, Row, TimestampRange};
 use crate::driver::source_row_buffer::SourceRowBuffer;
 use crate::error::{CliResult, Error};
 
+/// Stops the whole pipeline after a number of output rows or a wall-clock duration.
+/// The duration is checked between rows; a source blocked waiting for data is ended
+/// at the deadline by its transport, see `InputFactory::set_deadline`.
+#[derive(Copy, Clone, Default)]
+pub struct DriveLimit {
+    pub rows: Option<u64>,
+    pub duration: Option<Duration>,
+}
+
+/// Paces output rows so that they are emitted at wall-clock intervals matching
+/// the gaps between their timestamps, divided by the speed.
+#[derive(Copy, Clone, Debug)]
+pub struct Playback {
+    speed: f64,
+    // longest wall-clock wait between two rows, if any
+    max_gap: Option<Duration>,
+    // row timestamp unit, in nanoseconds
+    nanos_per_unit: u64,
+}
+
+impl Playback {
+    pub fn new(speed: f64, max_gap: Option<Duration>, nanos_per_unit: u64) -> CliResult<Self> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(Error::from(format!("Driver -- replay speed must be positive - {}", speed)))
+        }
+        Ok(Playback { speed, max_gap, nanos_per_unit })
+    }
+
+    /// Wall-clock time to wait between rows with these timestamps.
+    pub fn delay(&self, previous: u64, timestamp: u64) -> Duration {
+        let nanos = timestamp.saturating_sub(previous) as f64 * self.nanos_per_unit as f64 / self.speed;
+        let delay = Duration::from_nanos(nanos.min(u64::MAX as f64) as u64);
+        match self.max_gap {
+            Some(max_gap) => delay.min(max_gap),
+            None => delay
+        }
+    }
+}
+
 pub struct Driver {
     sources: Vec<Box<dyn Source>>,
     data_graph: DataGraph,
     timestamp_range: TimestampRange,
+    limit: DriveLimit,
+    // rows that got through the whole graph, counted against the limit
+    row_count: u64,
+    playback: Option<Playback>,
+    // flush the sinks after each row, e.g. when following live inputs
+    flush_every_row: bool,
+    // number of merge pins still open, per chain
+    open_pins: Vec<usize>,
+    finished_chains: Vec<bool>,
+    warnings: Vec<String>,
 }
 
 impl Driver {
@@ -22,67 +74,261 @@ impl Driver {
                 "Driver -- not enough header chains for sources. \
                 each source should have at least one header chain."));
         }
-        let data_graph = header_graph.process_header(headers)?;
-        Ok(Driver { sources, data_graph, timestamp_range })
+        let mut data_graph = header_graph.process_header(headers)?;
+        let open_pins = Self::count_merge_pins(&mut data_graph);
+        let finished_chains = vec![false; data_graph.len()];
+        Ok(Driver {
+            sources,
+            data_graph,
+            timestamp_range,
+            limit: DriveLimit::default(),
+            row_count: 0,
+            playback: None,
+            flush_every_row: false,
+            open_pins,
+            finished_chains,
+            warnings: Vec::new()
+        })
+    }
+
+    pub fn set_limit(&mut self, limit: DriveLimit) {
+        self.limit = limit;
+    }
+
+    pub fn set_playback(&mut self, playback: Option<Playback>) {
+        self.playback = playback;
+    }
+
+    pub fn set_flush_every_row(&mut self, flush_every_row: bool) {
+        self.flush_every_row = flush_every_row;
+    }
+
+    fn count_merge_pins(data_graph: &mut DataGraph) -> Vec<usize> {
+        let mut open_pins = vec![0; data_graph.len()];
+        for chain_id in 0..data_graph.len() {
+            for node in data_graph.get_mut_chain(chain_id).nodes() {
+                if let DataNode::Merge(merge_chain_id, _) = node {
+                    open_pins[*merge_chain_id] += 1;
+                }
+            }
+        }
+        open_pins
     }
 
     fn drive(&mut self) -> CliResult<()> {
+        let start = Instant::now();
         let mut row_buffers = self.get_row_buffers()?;
+        // timestamp and wall-clock time of the last row played back
+        let mut played: Option<(u64, Instant)> = None;
 
         // sort and output
         // all the sources are processed at the same time, but a row with min timestamp is output first
-        let mut buffer_len = row_buffers.len();
-        while buffer_len > 0 {
+        while !row_buffers.is_empty() && !self.is_limit_reached(start) {
             // get the row with min timestamp and write
             let buffer_index = Self::get_next_buffer_index(&row_buffers);
+            // open a deferred source once its rows may be next
+            if !row_buffers[buffer_index].is_primed() {
+                if !row_buffers[buffer_index].prime(&self.timestamp_range)? {
+                    self.end_source(row_buffers.remove(buffer_index))?;
+                }
+                continue;
+            }
             let next_row_buffer = &mut row_buffers[buffer_index];
             let row = next_row_buffer.row().clone().unwrap();
             let chain_id = next_row_buffer.chain_id();
-            Self::process_row(&mut self.data_graph, chain_id, 0, 0, row)?;
+            if let Some(playback) = self.playback {
+                played = Some(Self::wait_for(&playback, played, row.timestamp));
+            }
+            if Self::process_row(&mut self.data_graph, chain_id, 0, 0, row)? {
+                self.row_count += 1;
+            }
+            if self.playback.is_some() || self.flush_every_row {
+                self.flush(chain_id)?;
+            }
+
+            // no more rows can get through, so the source need not be read any further
+            if self.is_chain_done(chain_id) {
+                self.end_source(row_buffers.remove(buffer_index))?;
+                continue;
+            }
 
             // remove the row buffer if it reaches the end of the file
-            loop {
-                if !row_buffers[buffer_index].has_next(&self.timestamp_range)? {
-                    self.flush(chain_id, 0)?;
-                    row_buffers.remove(buffer_index);
-                }
-                break;
+            if !row_buffers[buffer_index].has_next(&self.timestamp_range)? {
+                self.end_source(row_buffers.remove(buffer_index))?;
+            }
+        }
+
+        // the limit stopped the pipeline early; drop the remaining sources
+        // and end their streams
+        for row_buffer in row_buffers {
+            self.end_source(row_buffer)?;
+        }
+        // chains not fed by any source
+        for chain_id in 0..self.data_graph.len() {
+            self.end_of_stream(chain_id)?;
+        }
+        Ok(())
+    }
+
+    // keeps the warnings of a source that is done and ends its stream
+    fn end_source(&mut self, mut row_buffer: SourceRowBuffer) -> CliResult<()> {
+        self.warnings.extend(row_buffer.warnings());
+        self.end_of_stream(row_buffer.chain_id())
+    }
+
+    // finish the sinks of the chain in order, passing rows they emit on to the rest of the chain,
+    // then end the stream of the chains fed by it. a merged chain ends once all its pins end.
+    fn end_of_stream(&mut self, chain_id: ChainId) -> CliResult<()> {
+        if self.finished_chains[chain_id] {
+            return Ok(())
+        }
+        self.finished_chains[chain_id] = true;
+
+        let node_count = self.data_graph.get_mut_chain(chain_id).nodes().len();
+        for node_id in 0..node_count {
+            match self.data_graph.get_mut_chain(chain_id).node(node_id) {
+                DataNode::DataSink(sink) => {
+                    let rows = sink.finish()?;
+                    // rows held back until the end, e.g. by a tail, count against the limit too
+                    for row in rows {
+                        if self.is_row_limit_reached() {
+                            break
+                        }
+                        if Self::process_row(&mut self.data_graph, chain_id, node_id + 1, 0, row)? {
+                            self.row_count += 1;
+                        }
+                    }
+                },
+                DataNode::Merge(merge_chain_id, _pin_id) => {
+                    let merge_chain_id = *merge_chain_id;
+                    self.open_pins[merge_chain_id] -= 1;
+                    if self.open_pins[merge_chain_id] == 0 {
+                        self.end_of_stream(merge_chain_id)?;
+                    }
+                    break
+                },
+                DataNode::Split(chain_ids) => {
+                    for split_chain_id in chain_ids.clone() {
+                        self.end_of_stream(split_chain_id)?;
+                    }
+                    break
+                },
             }
-            // update row buffer length
-            buffer_len = row_buffers.len();
-         }
+        }
         Ok(())
     }
 
+    // true if a sink in the chain, or in every chain it feeds, drops all further rows
+    fn is_chain_done(&mut self, chain_id: ChainId) -> bool {
+        let node_count = self.data_graph.get_mut_chain(chain_id).nodes().len();
+        for node_id in 0..node_count {
+            match self.data_graph.get_mut_chain(chain_id).node(node_id) {
+                DataNode::DataSink(sink) => if sink.is_done() {
+                    return true
+                },
+                DataNode::Merge(merge_chain_id, _pin_id) => {
+                    let merge_chain_id = *merge_chain_id;
+                    return self.is_chain_done(merge_chain_id)
+                },
+                DataNode::Split(chain_ids) => {
+                    let chain_ids = chain_ids.clone();
+                    return chain_ids.into_iter().all(|id| self.is_chain_done(id))
+                },
+            }
+        }
+        false
+    }
+
+    // sleeps until the row is due; a row that is late is played right away
+    // and the following rows are paced from it, rather than rushed to catch up
+    fn wait_for(playback: &Playback, played: Option<(u64, Instant)>, timestamp: u64) -> (u64, Instant) {
+        let now = Instant::now();
+        let due = match played {
+            Some((previous, played_at)) => played_at + playback.delay(previous, timestamp),
+            None => now
+        };
+        if due > now {
+            thread::sleep(due - now);
+        }
+        (timestamp, due.max(now))
+    }
+
+    fn is_limit_reached(&self, start: Instant) -> bool {
+        if self.is_row_limit_reached() {
+            return true
+        }
+        match self.limit.duration {
+            Some(duration) => start.elapsed() >= duration,
+            None => false
+        }
+    }
+
+    fn is_row_limit_reached(&self) -> bool {
+        self.limit.rows.is_some_and(|rows| self.row_count >= rows)
+    }
+
     fn get_row_buffers(&mut self) -> CliResult<Vec<SourceRowBuffer>> {
         let mut row_buffers: Vec<SourceRowBuffer> = Vec::with_capacity(self.sources.len());
-        for i in 0..self.sources.len() {
-            let source = self.sources.pop().unwrap();
-            row_buffers.push(SourceRowBuffer::new(source, i, &self.timestamp_range)?);
+        let sources: Vec<Box<dyn Source>> = self.sources.drain(..).collect();
+        for (i, source) in sources.into_iter().enumerate() {
+            let mut row_buffer = SourceRowBuffer::new(source, i, &self.timestamp_range)?;
+            // relative bounds need the actual first rows
+            if self.timestamp_range.is_relative() && !row_buffer.is_primed() {
+                row_buffer.prime(&self.timestamp_range)?;
+            }
+            // sources with no rows in range are done already
+            if row_buffer.is_primed() && row_buffer.row().is_none() {
+                self.end_source(row_buffer)?;
+                continue;
+            }
+            row_buffers.push(row_buffer);
+        }
+
+        // bounds relative to the first row of all sources
+        if self.timestamp_range.is_relative() {
+            match row_buffers.iter().map(|b| b.timestamp()).min() {
+                Some(first_timestamp) => self.timestamp_range.resolve(first_timestamp),
+                None => return Ok(row_buffers)
+            }
+            let mut in_range: Vec<SourceRowBuffer> = Vec::with_capacity(row_buffers.len());
+            for mut row_buffer in row_buffers {
+                if row_buffer.apply_range(&self.timestamp_range)? {
+                    in_range.push(row_buffer);
+                } else {
+                    self.end_source(row_buffer)?;
+                }
+            }
+            return Ok(in_range)
         }
         Ok(row_buffers)
     }
 
-    // index of the row buffer that has a row with min timestamp
+    // index of the row buffer that has a row with min timestamp; on a tie, a buffer
+    // not primed yet goes first, so rows come out as if all sources were read from the start
     fn get_next_buffer_index(row_buffers: &Vec<SourceRowBuffer>) -> usize {
         let min = row_buffers
             .iter()
             .enumerate()
-            .min_by(|&(_, i1), &(_, i2)|
-                i1.timestamp().cmp(&i2.timestamp())).unwrap();
+            .min_by_key(|&(_, b)| (b.timestamp(), b.is_primed())).unwrap();
         min.0
     }
 
+    // returns true if the row made it to the last node of at least one chain
     fn process_row(data_graph: &mut DataGraph,
                    mut chain_id: ChainId,
                    mut node_id: NodeId,
                    mut pin_id: PinId,
-                   mut row: Row) -> CliResult<()>
+                   mut row: Row) -> CliResult<bool>
     {
+        let mut is_output = false;
         let chain = data_graph.get_mut_chain(chain_id);
-        while node_id < chain.nodes().len() {
+        let node_count = chain.nodes().len();
+        while node_id < node_count {
             match chain.node(node_id) {
                 DataNode::DataSink(sink) => {
+                    if node_id + 1 == node_count {
+                        is_output = true;
+                    }
                     match sink.write_row_to_pin(pin_id, row)? {
                         Some(r) => {
                             row = r;
@@ -95,24 +341,21 @@ impl Driver {
                     chain_id = *new_chain_id;
                     node_id = 0;
                     pin_id = *new_pin_id;
-                    Self::process_row(data_graph, chain_id, node_id, pin_id, row)?;
+                    is_output = Self::process_row(data_graph, chain_id, node_id, pin_id, row)?;
                     break
                 }
                 DataNode::Split(chain_ids) => {
-                    if pin_id < chain_ids.len() {
-                        let new_chain_id = chain_ids[pin_id];
-                        pin_id += 1;
-                        Self::process_row(data_graph, new_chain_id, 0, pin_id, row.clone())?;
-                        Self::process_row(data_graph, chain_id, 0, pin_id, row.clone())?;
+                    for split_chain_id in chain_ids.clone() {
+                        is_output |= Self::process_row(data_graph, split_chain_id, 0, 0, row.clone())?;
                     }
                     break
                 },
             }
         }
-        Ok(())
+        Ok(is_output)
     }
 
-    pub fn flush(&mut self, mut chain_id: ChainId, mut pin_id: PinId) -> CliResult<()> {
+    pub fn flush(&mut self, mut chain_id: ChainId) -> CliResult<()> {
         let mut node_id = 0;
         let chain = self.data_graph.get_mut_chain(chain_id);
         while chain.nodes().len() > node_id {
@@ -123,15 +366,12 @@ impl Driver {
                 },
                 DataNode::Merge(new_chain_id, _pin_id) => {
                     chain_id = *new_chain_id;
-                    self.flush(chain_id, 0)?;
+                    self.flush(chain_id)?;
                     break
                 },
                 DataNode::Split(chain_ids) => {
-                    if pin_id < chain_ids.len() {
-                        let new_chain_id = chain_ids[pin_id];
-                        pin_id += 1;
-                        self.flush(new_chain_id,0)?;
-                        self.flush(chain_id,pin_id)?;
+                    for split_chain_id in chain_ids.clone() {
+                        self.flush(split_chain_id)?;
                     }
                     break
                 },
@@ -145,4 +385,8 @@ impl ChopperDriver for Driver {
     fn drive(&mut self) -> CliResult<()> {
         self.drive()
     }
+
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
 }
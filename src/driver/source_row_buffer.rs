@@ -4,20 +4,42 @@ use crate::chopper::types::{Nanos, Row, TimestampRange};
 use crate::error::CliResult;
 
 pub struct SourceRowBuffer {
-    source: Box<dyn Source+'static>,
+    source: Option<Box<dyn Source+'static>>,
     chain_id: ChainId,
     timestamp: Nanos,
     row: Option<Row>,
+    // false until the first row is read; the timestamp is a lower bound till then
+    primed: bool,
+    // of the source once released
+    warnings: Vec<String>,
 }
 
 impl SourceRowBuffer {
-    pub fn new(mut source: Box<dyn Source>, chain_id: ChainId, timestamp_range: &TimestampRange) -> CliResult<Self> {
-        let mut row = match_next_row(&mut source, timestamp_range)?;
-        let timestamp = match &mut row {
-            Some(r) => r.timestamp,
-            None => 0 as Nanos
-        };
-        Ok(SourceRowBuffer { source, chain_id, timestamp, row })
+    /// Reads the first row in range, unless the source gives a lower bound of its
+    /// timestamps; such a source is primed later, once its rows may be due.
+    pub fn new(source: Box<dyn Source>, chain_id: ChainId, timestamp_range: &TimestampRange) -> CliResult<Self> {
+        let hint = source.first_timestamp_hint();
+        let mut row_buffer = SourceRowBuffer { source: Some(source), chain_id, timestamp: 0, row: None, primed: false, warnings: Vec::new() };
+        match hint {
+            Some(timestamp) => row_buffer.timestamp = timestamp.max(timestamp_range.begin.unwrap_or(0)),
+            None => {
+                row_buffer.prime(timestamp_range)?;
+            }
+        }
+        Ok(row_buffer)
+    }
+
+    /// Reads the first row in range; returns false if there is none.
+    pub fn prime(&mut self, timestamp_range: &TimestampRange) -> CliResult<bool> {
+        self.primed = true;
+        if let (Some(source), Some(begin)) = (&mut self.source, timestamp_range.begin) {
+            source.seek(begin)?;
+        }
+        self.has_next(timestamp_range)
+    }
+
+    pub fn is_primed(&self) -> bool {
+        self.primed
     }
 
     pub fn timestamp(&self) -> u64 {
@@ -32,19 +54,48 @@ impl SourceRowBuffer {
         self.chain_id
     }
 
+    pub fn warnings(&self) -> Vec<String> {
+        match &self.source {
+            Some(source) => source.warnings(),
+            None => self.warnings.clone()
+        }
+    }
+
     fn update_record(&mut self, next_row: Row) {
         self.timestamp = next_row.timestamp;
         self.row = Some(next_row);
     }
 
+    /// Re-checks the buffered row against a narrowed range, moving on to the next
+    /// row in range if needed; returns false if the source has none left.
+    pub fn apply_range(&mut self, timestamp_range: &TimestampRange) -> CliResult<bool> {
+        if !self.primed {
+            return self.prime(timestamp_range)
+        }
+        match &self.row {
+            Some(r) if filter_data_range(timestamp_range, r.timestamp) == Action::Write => Ok(true),
+            Some(_) => self.has_next(timestamp_range),
+            None => Ok(false)
+        }
+    }
+
     pub fn has_next(&mut self, timestamp_range: &TimestampRange) -> CliResult<bool> {
-        let next_row = match_next_row(&mut self.source, &timestamp_range)?;
+        let next_row = match &mut self.source {
+            Some(source) => match_next_row(source, timestamp_range)?,
+            None => None
+        };
         match next_row {
             Some(r) => {
                 self.update_record(r);
                 Ok(true)
             }
             None => {
+                // release the source and its transport as soon as it is done,
+                // e.g. to close http connections once past the end timestamp
+                if let Some(source) = self.source.take() {
+                    self.warnings = source.warnings();
+                }
+                self.row = None;
                 Ok(false)
             }
         }
@@ -62,6 +113,11 @@ fn filter_data_range(timestamp_range: &TimestampRange, timestamp: Nanos) -> Acti
     if timestamp_range.end.is_some() && timestamp >= timestamp_range.end.unwrap() {
         return Action::Stop;
     }
+    if let Some(window) = &timestamp_range.daily_window {
+        if !window.contains(timestamp) {
+            return Action::Skip;
+        }
+    }
     match timestamp_range.begin.is_none() || timestamp >= timestamp_range.begin.unwrap() {
         true => Action::Write,
         false => Action::Skip,
@@ -28,6 +28,7 @@ impl HeaderSink for ColumnFilterDeleteConfig {
                 // remove column
                 header.field_names_mut().remove(i);
                 header.field_types_mut().remove(i);
+                header.field_metadata_mut().remove(i);
                 // return data filter with the column index
                 let data_sink = ColumnFilterDelete { column_index: i };
                 return Ok(data_sink.boxed())
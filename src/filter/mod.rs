@@ -4,3 +4,8 @@ pub mod column_filter_delete_col;
 pub mod row_filter_equal_value;
 #[allow(dead_code)]
 pub mod row_filter_greater_value;
+pub mod row_filter_head;
+pub mod row_filter_sample;
+pub mod row_filter_session;
+pub mod row_filter_tail;
+pub mod timestamp_adjust;
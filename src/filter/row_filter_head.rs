@@ -0,0 +1,49 @@
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{Header, Row};
+use crate::error::CliResult;
+
+/// Passes through the first `row_count` rows and drops the rest; once they are passed,
+/// the driver stops reading the inputs.
+pub struct RowFilterHead {
+    row_count: u64,
+    rows_seen: u64,
+}
+
+impl RowFilterHead {
+    pub fn new(row_count: u64) -> Box<dyn HeaderSink> {
+        Box::new(RowFilterHead { row_count, rows_seen: 0 }) as Box<dyn HeaderSink>
+    }
+}
+
+impl HeaderSink for RowFilterHead {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for RowFilterHead {
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        if self.rows_seen >= self.row_count {
+            return Ok(None)
+        }
+        self.rows_seen += 1;
+        Ok(Some(row))
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn is_done(&self) -> bool {
+        self.rows_seen >= self.row_count
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
@@ -0,0 +1,129 @@
+use rand::{FromEntropy, Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{Header, Row};
+use crate::error::{CliResult, Error};
+
+#[derive(Clone, Copy)]
+pub enum SampleMode {
+    /// every Nth row, starting with the first one
+    Every(u64),
+    /// each row independently with the given probability
+    Probability(f64),
+    /// uniform sample of K rows, passed on in stream order when the stream finishes
+    Reservoir(usize),
+}
+
+pub struct RowFilterSample {
+    mode: SampleMode,
+    rng: StdRng,
+    rows_seen: u64,
+    reservoir: Vec<(u64, Row)>,
+}
+
+impl RowFilterSample {
+    pub fn new(mode: SampleMode, seed: Option<u64>) -> CliResult<Box<dyn HeaderSink>> {
+        match mode {
+            SampleMode::Every(0) =>
+                return Err(Error::from("RowFilterSample -- sampling interval must be at least 1")),
+            SampleMode::Probability(p) if !(0.0..=1.0).contains(&p) =>
+                return Err(Error::from("RowFilterSample -- sampling probability must be between 0 and 1")),
+            _ => ()
+        }
+        let rng = match seed {
+            Some(s) => StdRng::seed_from_u64(s),
+            None => StdRng::from_entropy()
+        };
+        let sample = RowFilterSample { mode, rng, rows_seen: 0, reservoir: Vec::new() };
+        Ok(Box::new(sample) as Box<dyn HeaderSink>)
+    }
+}
+
+impl HeaderSink for RowFilterSample {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for RowFilterSample {
+    // is_multiple_of would need rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        let index = self.rows_seen;
+        self.rows_seen += 1;
+        match self.mode {
+            SampleMode::Every(n) => {
+                if index % n == 0 {
+                    return Ok(Some(row))
+                }
+            }
+            SampleMode::Probability(p) => {
+                if self.rng.gen::<f64>() < p {
+                    return Ok(Some(row))
+                }
+            }
+            SampleMode::Reservoir(k) => {
+                // algorithm R
+                if self.reservoir.len() < k {
+                    self.reservoir.push((index, row));
+                } else {
+                    let j = self.rng.gen_range(0, index + 1) as usize;
+                    if j < k {
+                        self.reservoir[j] = (index, row);
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        let mut reservoir: Vec<(u64, Row)> = self.reservoir.drain(..).collect();
+        reservoir.sort_by_key(|(index, _)| *index);
+        Ok(reservoir.into_iter().map(|(_, row)| row).collect())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample(mode: SampleMode) -> RowFilterSample {
+        RowFilterSample { mode, rng: StdRng::seed_from_u64(1), rows_seen: 0, reservoir: Vec::new() }
+    }
+
+    #[test]
+    fn test_sample_every() {
+        let mut sample = create_sample(SampleMode::Every(3));
+        let passed: Vec<u64> = (0..10)
+            .filter_map(|timestamp| sample.write_row(Row { timestamp, field_values: Vec::new() }).unwrap())
+            .map(|r| r.timestamp)
+            .collect();
+        assert_eq!(passed, vec![0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn test_sample_reservoir() {
+        let mut sample = create_sample(SampleMode::Reservoir(4));
+        for timestamp in 0..100 {
+            assert!(sample.write_row(Row { timestamp, field_values: Vec::new() }).unwrap().is_none());
+        }
+        let timestamps: Vec<u64> = sample.finish().unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps.len(), 4);
+        assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+    }
+}
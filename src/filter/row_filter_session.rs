@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+use std::fs;
+
+use chrono::{Datelike, NaiveDate, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{Header, Row};
+use crate::error::{CliResult, Error};
+use crate::util::timestamp_util::DailyWindow;
+
+pub static WEEKDAYS: [Weekday; 5] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+
+/// Passes rows within a trading session: a time of day window, days of the week
+/// and a holiday calendar, all evaluated in the time zone.
+pub struct RowFilterSession {
+    window: Option<DailyWindow>,
+    // all days if None
+    days: Option<Vec<Weekday>>,
+    holidays: HashSet<NaiveDate>,
+    timezone: Tz,
+}
+
+impl RowFilterSession {
+    pub fn new(window: Option<DailyWindow>,
+               days: Option<Vec<Weekday>>,
+               holidays: Vec<NaiveDate>,
+               timezone: Tz) -> Box<dyn HeaderSink>
+    {
+        let holidays = holidays.into_iter().collect();
+        Box::new(RowFilterSession { window, days, holidays, timezone }) as Box<dyn HeaderSink>
+    }
+
+    fn in_session(&self, timestamp: u64) -> bool {
+        if let Some(window) = &self.window {
+            if !window.contains(timestamp) {
+                return false
+            }
+        }
+        // a session past midnight belongs to the day it starts on
+        let date = match &self.window {
+            Some(window) => window.start_date(timestamp),
+            None => self.timezone.timestamp_opt(timestamp as i64, 0).single().map(|dt| dt.date_naive())
+        };
+        let date = match date {
+            Some(d) => d,
+            None => return false
+        };
+        if let Some(days) = &self.days {
+            if !days.contains(&date.weekday()) {
+                return false
+            }
+        }
+        !self.holidays.contains(&date)
+    }
+}
+
+/// Parses days of the week such as "mon-fri" or "mon,wed,fri".
+pub fn parse_days(days: &str) -> CliResult<Vec<Weekday>> {
+    let parse_day = |day: &str| match day.trim().parse::<Weekday>() {
+        Ok(d) => Ok(d),
+        Err(_) => Err(Error::from(format!("RowFilterSession -- cannot parse day of week: {}", day)))
+    };
+    let mut parsed: Vec<Weekday> = Vec::new();
+    for part in days.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let mut day = parse_day(first)?;
+                let last = parse_day(last)?;
+                while day != last {
+                    parsed.push(day);
+                    day = day.succ();
+                }
+                parsed.push(last);
+            }
+            None => parsed.push(parse_day(part)?)
+        }
+    }
+    Ok(parsed)
+}
+
+/// Reads a holiday calendar: one date per line as yyyy-mm-dd or yyyymmdd;
+/// blank lines and lines starting with # are ignored.
+pub fn read_holidays(path: &str) -> CliResult<Vec<NaiveDate>> {
+    let mut holidays: Vec<NaiveDate> = Vec::new();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(line, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(line, "%Y%m%d"));
+        match date {
+            Ok(d) => holidays.push(d),
+            Err(_) => return Err(Error::from(format!(
+                "RowFilterSession -- cannot parse holiday at line {} of {}: {}", i + 1, path, line)))
+        }
+    }
+    Ok(holidays)
+}
+
+impl HeaderSink for RowFilterSession {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for RowFilterSession {
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        match self.in_session(row.timestamp) {
+            true => Ok(Some(row)),
+            false => Ok(None)
+        }
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::New_York;
+
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let window = DailyWindow::parse("09:30-16:00", New_York).unwrap();
+        let holidays = vec![NaiveDate::from_ymd_opt(2019, 7, 4).unwrap()];
+        let mut session = RowFilterSession {
+            window: Some(window),
+            days: Some(WEEKDAYS.to_vec()),
+            holidays: holidays.into_iter().collect(),
+            timezone: New_York
+        };
+        let passed: Vec<u64> = vec![
+            1562160600, // Wed 2019-07-03 09:30
+            1562158800, // Wed 2019-07-03 09:00
+            1562252400, // Thu 2019-07-04 11:00, holiday
+            1562338800, // Fri 2019-07-05 11:00
+            1562425200, // Sat 2019-07-06 11:00
+        ].into_iter()
+            .filter_map(|timestamp| session.write_row(Row { timestamp, field_values: Vec::new() }).unwrap())
+            .map(|r| r.timestamp)
+            .collect();
+        assert_eq!(passed, vec![1562160600, 1562338800]);
+
+        // overnight sessions, on the weekday they start
+        session.window = Some(DailyWindow::parse("18:00-02:00", New_York).unwrap());
+        let passed: Vec<u64> = vec![
+            1562277600, // Thu 2019-07-04 18:00, holiday
+            1562302800, // Fri 2019-07-05 01:00, in Thursday's session
+            1562367600, // Fri 2019-07-05 19:00
+            1562389200, // Sat 2019-07-06 01:00, in Friday's session
+            1562454000, // Sat 2019-07-06 19:00
+            1562648400, // Tue 2019-07-09 01:00, in Monday's session
+        ].into_iter()
+            .filter_map(|timestamp| session.write_row(Row { timestamp, field_values: Vec::new() }).unwrap())
+            .map(|r| r.timestamp)
+            .collect();
+        assert_eq!(passed, vec![1562367600, 1562389200, 1562648400]);
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("mon-fri").unwrap(), WEEKDAYS.to_vec());
+        assert_eq!(parse_days("sat,sun").unwrap(), vec![Weekday::Sat, Weekday::Sun]);
+        assert_eq!(parse_days("fri-mon").unwrap(), vec![Weekday::Fri, Weekday::Sat, Weekday::Sun, Weekday::Mon]);
+        assert!(parse_days("someday").is_err());
+    }
+}
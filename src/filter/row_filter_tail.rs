@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{Header, Row};
+use crate::error::CliResult;
+
+/// Keeps only the last `row_count` rows in memory and passes them on when the stream finishes.
+pub struct RowFilterTail {
+    row_count: usize,
+    rows: VecDeque<Row>,
+}
+
+impl RowFilterTail {
+    pub fn new(row_count: usize) -> Box<dyn HeaderSink> {
+        let rows = VecDeque::with_capacity(row_count);
+        Box::new(RowFilterTail { row_count, rows }) as Box<dyn HeaderSink>
+    }
+}
+
+impl HeaderSink for RowFilterTail {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for RowFilterTail {
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        if self.row_count == 0 {
+            return Ok(None)
+        }
+        if self.rows.len() == self.row_count {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        Ok(None)
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        Ok(self.rows.drain(..).collect())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail() {
+        let mut tail = RowFilterTail { row_count: 2, rows: VecDeque::new() };
+        for timestamp in 0..5 {
+            assert!(tail.write_row(Row { timestamp, field_values: Vec::new() }).unwrap().is_none());
+        }
+        let timestamps: Vec<u64> = tail.finish().unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, vec![3, 4]);
+        assert!(tail.finish().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{FieldMetadata, FieldType, FieldValue, Header, Nanos, Row};
+use crate::error::{CliResult, Error};
+
+/// What to do with a row whose adjusted timestamp is earlier than the previous one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnorderedPolicy {
+    Error,
+    Drop,
+}
+
+impl UnorderedPolicy {
+    pub fn parse(policy: &str) -> CliResult<Self> {
+        match policy {
+            "error" => Ok(UnorderedPolicy::Error),
+            "drop" => Ok(UnorderedPolicy::Drop),
+            _ => Err(Error::from(format!("TimestampAdjust -- unknown unordered policy: {}", policy)))
+        }
+    }
+}
+
+pub struct TimestampAdjustConfig {
+    // take the timestamp from this column
+    from_column: Option<String>,
+    // signed offset in seconds added to every timestamp
+    shift: i64,
+    // the timestamps are nanos, as read from dc, rather than seconds
+    nanos: bool,
+    // subtract the first timestamp, so the stream starts at zero
+    rebase: bool,
+    // copy the original timestamp into a new column with this name
+    keep_original: Option<String>,
+    unordered_policy: UnorderedPolicy,
+}
+
+impl TimestampAdjustConfig {
+    pub fn new() -> Self {
+        TimestampAdjustConfig {
+            from_column: None,
+            shift: 0,
+            nanos: false,
+            rebase: false,
+            keep_original: None,
+            unordered_policy: UnorderedPolicy::Error
+        }
+    }
+
+    pub fn from_column(&self) -> Option<&String> {
+        self.from_column.as_ref()
+    }
+
+    pub fn set_from_column(&mut self, from_column: Option<String>) {
+        self.from_column = from_column
+    }
+
+    pub fn shift(&self) -> i64 {
+        self.shift
+    }
+
+    pub fn set_shift(&mut self, shift: i64) {
+        self.shift = shift
+    }
+
+    pub fn nanos(&self) -> bool {
+        self.nanos
+    }
+
+    pub fn set_nanos(&mut self, nanos: bool) {
+        self.nanos = nanos
+    }
+
+    pub fn rebase(&self) -> bool {
+        self.rebase
+    }
+
+    pub fn set_rebase(&mut self, rebase: bool) {
+        self.rebase = rebase
+    }
+
+    pub fn keep_original(&self) -> Option<&String> {
+        self.keep_original.as_ref()
+    }
+
+    pub fn set_keep_original(&mut self, keep_original: Option<String>) {
+        self.keep_original = keep_original
+    }
+
+    pub fn unordered_policy(&self) -> UnorderedPolicy {
+        self.unordered_policy
+    }
+
+    pub fn set_unordered_policy(&mut self, unordered_policy: UnorderedPolicy) {
+        self.unordered_policy = unordered_policy
+    }
+
+    /// True if the config changes anything at all.
+    pub fn is_active(&self) -> bool {
+        self.from_column.is_some() || self.shift != 0 || self.rebase || self.keep_original.is_some()
+    }
+
+    pub fn boxed(self) -> Box<dyn HeaderSink> {
+        Box::new(self) as Box<dyn HeaderSink>
+    }
+}
+
+impl Default for TimestampAdjustConfig {
+    fn default() -> Self {
+        TimestampAdjustConfig::new()
+    }
+}
+
+/// Replaces, shifts and rebases row timestamps, in that order, and checks that
+/// the adjusted timestamps still do not go backwards.
+pub struct TimestampAdjust {
+    from_index: Option<usize>,
+    from_column: String,
+    shift: i64,
+    rebase: bool,
+    keep_original: bool,
+    unordered_policy: UnorderedPolicy,
+    first_timestamp: Option<i64>,
+    last_timestamp: Option<Nanos>,
+    row_count: u64,
+}
+
+impl TimestampAdjust {
+    fn column_timestamp(&self, value: &FieldValue) -> CliResult<i64> {
+        let timestamp = match value {
+            FieldValue::Byte(x) => Some(*x as i64),
+            FieldValue::Short(x) => Some(*x as i64),
+            FieldValue::Int(x) => Some(*x as i64),
+            FieldValue::Long(x) => Some(*x),
+            FieldValue::String(x) => x.trim().parse::<i64>().ok(),
+            _ => None
+        };
+        match timestamp {
+            Some(t) => Ok(t),
+            None => Err(Error::from(format!(
+                "TimestampAdjust -- cannot use value {:?} of column {} as a timestamp at row {}",
+                value, self.from_column, self.row_count + 1)))
+        }
+    }
+
+    fn adjust(&mut self, row: &Row) -> CliResult<i64> {
+        let mut timestamp = match self.from_index {
+            Some(i) => self.column_timestamp(&row.field_values[i])?,
+            None => row.timestamp as i64
+        };
+        timestamp += self.shift;
+        if self.rebase {
+            let first = *self.first_timestamp.get_or_insert(timestamp);
+            timestamp -= first;
+        }
+        if timestamp < 0 {
+            return Err(Error::from(format!(
+                "TimestampAdjust -- timestamp {} at row {} is before the epoch after adjusting",
+                timestamp, self.row_count + 1)))
+        }
+        Ok(timestamp)
+    }
+}
+
+impl HeaderSink for TimestampAdjustConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        let from_index = match &self.from_column {
+            Some(name) => match header.field_names().iter().position(|n| n.eq_ignore_ascii_case(name)) {
+                Some(i) => Some(i),
+                None => return Err(Error::from(format!(
+                    "TimestampAdjustConfig -- field name [{}] not found", name)))
+            },
+            None => None
+        };
+        if let Some(name) = &self.keep_original {
+            if header.field_names().iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Err(Error::from(format!(
+                    "TimestampAdjustConfig -- field name [{}] already exists", name)))
+            }
+            header.field_names_mut().push(name.clone());
+            header.field_types_mut().push(FieldType::Long);
+            // no timestamp hint: the unit of the timestamps, seconds or nanos, depends on the input
+            header.field_metadata_mut().push(FieldMetadata::default());
+        }
+        let shift = match self.nanos {
+            true => match self.shift.checked_mul(1_000_000_000) {
+                Some(s) => s,
+                None => return Err(Error::from(format!(
+                    "TimestampAdjustConfig -- shift of {} seconds is out of range", self.shift)))
+            },
+            false => self.shift
+        };
+        let data_sink = TimestampAdjust {
+            from_index,
+            from_column: self.from_column.unwrap_or_default(),
+            shift,
+            rebase: self.rebase,
+            keep_original: self.keep_original.is_some(),
+            unordered_policy: self.unordered_policy,
+            first_timestamp: None,
+            last_timestamp: None,
+            row_count: 0
+        };
+        Ok(data_sink.boxed())
+    }
+}
+
+impl DataSink for TimestampAdjust {
+    fn write_row(&mut self, mut row: Row) -> CliResult<Option<Row>> {
+        let timestamp = self.adjust(&row)? as Nanos;
+        self.row_count += 1;
+        if let Some(last) = self.last_timestamp {
+            if timestamp < last {
+                match self.unordered_policy {
+                    UnorderedPolicy::Drop => return Ok(None),
+                    UnorderedPolicy::Error => return Err(Error::from(format!(
+                        "TimestampAdjust -- timestamps go backwards at row {}: {} after {}",
+                        self.row_count, timestamp, last)))
+                }
+            }
+        }
+        self.last_timestamp = Some(timestamp);
+        if self.keep_original {
+            row.field_values.push(FieldValue::Long(row.timestamp as i64));
+        }
+        row.timestamp = timestamp;
+        Ok(Some(row))
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adjust(config: TimestampAdjustConfig, rows: Vec<Row>) -> CliResult<(Header, Vec<Row>)> {
+        let mut header = Header::new(vec!["exchange_time".to_string()], vec![FieldType::Long]);
+        let mut data_sink = config.boxed().process_header(&mut header)?;
+        let mut adjusted = Vec::new();
+        for row in rows {
+            if let Some(r) = data_sink.write_row(row)? {
+                adjusted.push(r);
+            }
+        }
+        Ok((header, adjusted))
+    }
+
+    fn row(timestamp: Nanos, exchange_time: i64) -> Row {
+        Row { timestamp, field_values: vec![FieldValue::Long(exchange_time)] }
+    }
+
+    fn timestamps(rows: &[Row]) -> Vec<Nanos> {
+        rows.iter().map(|r| r.timestamp).collect()
+    }
+
+    #[test]
+    fn test_shift_and_rebase() {
+        let mut config = TimestampAdjustConfig::new();
+        config.set_shift(-100);
+        let (_, rows) = adjust(config, vec![row(1000, 0), row(1005, 0)]).unwrap();
+        assert_eq!(timestamps(&rows), vec![900, 905]);
+
+        let mut config = TimestampAdjustConfig::new();
+        config.set_rebase(true);
+        config.set_keep_original(Some("receive_time".to_string()));
+        let (header, rows) = adjust(config, vec![row(1000, 0), row(1005, 0)]).unwrap();
+        assert_eq!(timestamps(&rows), vec![0, 5]);
+        assert_eq!(header.field_names()[1], "receive_time");
+        assert!(rows[1].field_values[1] == FieldValue::Long(1005));
+
+        let mut config = TimestampAdjustConfig::new();
+        config.set_shift(-2000);
+        assert!(adjust(config, vec![row(1000, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_from_column() {
+        let rows = vec![row(1000, 990), row(1001, 995), row(1002, 993), row(1003, 999)];
+        let mut config = TimestampAdjustConfig::new();
+        config.set_from_column(Some("exchange_time".to_string()));
+        assert!(adjust(config, rows.clone()).is_err());
+
+        let mut config = TimestampAdjustConfig::new();
+        config.set_from_column(Some("exchange_time".to_string()));
+        config.set_unordered_policy(UnorderedPolicy::Drop);
+        let (_, adjusted) = adjust(config, rows).unwrap();
+        assert_eq!(timestamps(&adjusted), vec![990, 995, 999]);
+
+        let mut config = TimestampAdjustConfig::new();
+        config.set_from_column(Some("missing".to_string()));
+        assert!(adjust(config, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+
+use crate::chopper::chopper::Source;
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::error::{CliResult, Error};
+
+/// Reads time-adjacent inputs of the same feed one after another, e.g. consecutive days,
+/// checking that each input starts no earlier than the previous one ended. Given lazy
+/// sources, only the input being read is open.
+pub struct ConcatSource {
+    sources: VecDeque<Box<dyn Source>>,
+    header: Header,
+    has_native_timestamp_column: bool,
+    properties: Vec<(String, String)>,
+    // input being read, counted from 1 for messages
+    input_number: usize,
+    // read any row from the current input yet
+    started: bool,
+    last_timestamp: Option<Nanos>,
+    // of the inputs read to the end
+    warnings: Vec<String>,
+}
+
+impl ConcatSource {
+    pub fn new(sources: Vec<Box<dyn Source>>) -> CliResult<Self> {
+        let first = match sources.first() {
+            Some(s) => s,
+            None => return Err(Error::from("ConcatSource -- no inputs"))
+        };
+        let header = first.header().clone();
+        for (i, source) in sources.iter().enumerate().skip(1) {
+            if source.header() != &header {
+                return Err(Error::from(format!(
+                    "ConcatSource -- fields of input {} differ from the first input: {:?} vs {:?}",
+                    i + 1, source.header().field_names(), header.field_names())))
+            }
+        }
+        let has_native_timestamp_column = first.has_native_timestamp_column();
+        let properties = first.properties();
+        Ok(ConcatSource {
+            sources: sources.into_iter().collect(),
+            header,
+            has_native_timestamp_column,
+            properties,
+            input_number: 1,
+            started: false,
+            last_timestamp: None,
+            warnings: Vec::new()
+        })
+    }
+}
+
+impl Source for ConcatSource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        loop {
+            let row = match self.sources.front_mut() {
+                Some(source) => source.next_row()?,
+                None => return Ok(None)
+            };
+            match row {
+                Some(r) => {
+                    if !self.started {
+                        if let Some(last) = self.last_timestamp {
+                            if r.timestamp < last {
+                                return Err(Error::from(format!(
+                                    "ConcatSource -- input {} starts at {}, before input {} ends at {}",
+                                    self.input_number, r.timestamp, self.input_number - 1, last)))
+                            }
+                        }
+                        self.started = true;
+                    }
+                    self.last_timestamp = Some(r.timestamp);
+                    return Ok(Some(r))
+                }
+                None => {
+                    // done with this input; dropping it closes it
+                    let source = self.sources.pop_front().unwrap();
+                    self.warnings.extend(source.warnings());
+                    self.input_number += 1;
+                    self.started = false;
+                }
+            }
+        }
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        self.has_native_timestamp_column
+    }
+
+    fn seek(&mut self, begin: Nanos) -> CliResult<()> {
+        for source in self.sources.iter_mut() {
+            source.seek(begin)?;
+        }
+        Ok(())
+    }
+
+    fn first_timestamp_hint(&self) -> Option<Nanos> {
+        match self.last_timestamp {
+            Some(_) => None,
+            None => self.sources.front().and_then(|s| s.first_timestamp_hint())
+        }
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        self.properties.clone()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = self.warnings.clone();
+        if let Some(source) = self.sources.front() {
+            warnings.extend(source.warnings());
+        }
+        warnings
+    }
+}
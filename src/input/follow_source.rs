@@ -0,0 +1,80 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::chopper::chopper::Source;
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::error::{CliResult, Error};
+
+/// Opens the followed file again: a new source, and the flag its reader sets when
+/// the file is rotated or truncated.
+pub type OpenFollowed = Box<dyn FnMut() -> CliResult<(Box<dyn Source>, Arc<AtomicBool>)>>;
+
+/// Reads a followed file across rotations and truncations. When the file is replaced
+/// or shrinks, the reader ends the input and the file is opened again with a new source,
+/// so a header at the start of the new file is read as a header, not as rows.
+pub struct FollowSource {
+    open: OpenFollowed,
+    source: Box<dyn Source>,
+    reopen: Arc<AtomicBool>,
+    header: Header,
+    // of the sources of files before a rotation or truncation
+    warnings: Vec<String>,
+}
+
+impl FollowSource {
+    pub fn new(mut open: OpenFollowed) -> CliResult<Self> {
+        let (source, reopen) = open()?;
+        let header = source.header().clone();
+        Ok(FollowSource { open, source, reopen, header, warnings: Vec::new() })
+    }
+}
+
+impl Source for FollowSource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        loop {
+            if let Some(row) = self.source.next_row()? {
+                return Ok(Some(row))
+            }
+            if !self.reopen.load(Ordering::SeqCst) {
+                return Ok(None)
+            }
+            let (source, reopen) = match (self.open)() {
+                Ok(s) => s,
+                // the new file is still empty at the idle timeout
+                Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e)
+            };
+            if source.header() != &self.header {
+                return Err(Error::from(format!(
+                    "FollowSource -- fields of the file changed after it was rotated or truncated: {:?} vs {:?}",
+                    source.header().field_names(), self.header.field_names())))
+            }
+            self.warnings.extend(self.source.warnings());
+            self.source = source;
+            self.reopen = reopen;
+        }
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        self.source.has_native_timestamp_column()
+    }
+
+    fn seek(&mut self, begin: Nanos) -> CliResult<()> {
+        self.source.seek(begin)
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        self.source.properties()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = self.warnings.clone();
+        warnings.extend(self.source.warnings());
+        warnings
+    }
+}
@@ -1,20 +1,44 @@
+use std::cell::RefCell;
+use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+use chrono_tz::Tz;
 
 use crate::chopper::chopper::Source;
+use crate::chopper::types::{Nanos, TimestampRange};
 use crate::error::{CliResult, Error};
+use crate::input::follow_source::FollowSource;
+use crate::input::lazy_source::LazySource;
+use crate::input::time_span::TimeSpan;
 use crate::source::{csv_factory::CSVFactory, dc_factory::DCFactory, source_factory::SourceFactory};
 use crate::source::csv_configs::CSVInputConfig;
+use crate::source::dc_configs::DCInputConfig;
 use crate::source::decompress;
-use crate::transport::{file::FileInput, http::Http, transport_factory::TransportFactory};
+use crate::transport::{deadline::DeadlineReader, file::{FileInput, FollowConfig, FollowReader}, http::{Http, HttpConfig}, socket::Socket, transport_factory::{SeekableRead, TransportFactory}};
+
+/// An input file found by expanding a glob or a directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpandedInput {
+    pub path: String,
+    pub time_span: Option<TimeSpan>,
+}
 
+// clones share the source factories
+#[derive(Clone)]
 pub struct InputFactory {
     transport_factories: Vec<Box<dyn TransportFactory>>,
-    source_factories: Vec<Box<dyn SourceFactory>>
+    source_factories: Rc<RefCell<Vec<Box<dyn SourceFactory>>>>,
+    // end streamed inputs at this time, even if blocked waiting for data
+    deadline: Option<Instant>,
+    follow: Option<FollowConfig>,
 }
 
 impl InputFactory {
     pub fn new(csv_input_config: Option<CSVInputConfig>,
+               dc_input_config: Option<DCInputConfig>,
                user_source_factories: Option<Vec<Box<dyn SourceFactory>>>,
                user_transport_factories: Option<Vec<Box<dyn TransportFactory>>>) -> CliResult<Self>
     {
@@ -33,7 +57,11 @@ impl InputFactory {
             Some(c) => c,
             None => CSVInputConfig::new_default()?
         };
-        let mut default_source_factories = create_default_source_factories(csv_input_config);
+        let dc_input_config = match dc_input_config {
+            Some(c) => c,
+            None => DCInputConfig::new_default()
+        };
+        let mut default_source_factories = create_default_source_factories(csv_input_config, dc_input_config);
         let source_factories = match user_source_factories {
             Some(mut s) => {
                 s.append(&mut default_source_factories);
@@ -42,7 +70,29 @@ impl InputFactory {
             None => default_source_factories
         };
 
-        Ok(InputFactory { transport_factories, source_factories })
+        Ok(InputFactory { transport_factories, source_factories: Rc::new(RefCell::new(source_factories)), deadline: None, follow: None })
+    }
+
+    /// Follows local files as they grow instead of stopping at their end; see `FollowSource`.
+    pub fn set_follow(&mut self, follow: Option<FollowConfig>) {
+        self.follow = follow;
+    }
+
+    /// Ends inputs read as streams, such as stdin, sockets, http or followed files, at the
+    /// deadline, including while a read waits for data. Local files that are not followed,
+    /// compressed or not, are not limited: they cannot stall.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Sends the headers and uses the timeouts and retries for http(s) inputs.
+    pub fn set_http(&mut self, config: HttpConfig) {
+        let http = Http::new(config);
+        for factory in self.transport_factories.iter_mut() {
+            if factory.factory_name() == "http" {
+                *factory = Box::new(http.clone());
+            }
+        }
     }
 }
 
@@ -58,32 +108,143 @@ impl InputFactory {
     }
 
     pub fn create_source_from_stdin(&mut self, file_type: &str) -> CliResult<Box<dyn Source>> {
-        self.create_source_from_reader(Box::new(io::stdin()), file_type)
+        let reader = self.with_deadline(Box::new(io::stdin()));
+        self.create_source_from_reader(reader, file_type)
+    }
+
+    /// Opens the input only when its rows are first read; see `LazySource`.
+    pub fn create_lazy_source_from_path(&mut self,
+                                        path: &str,
+                                        first_timestamp_hint: Option<Nanos>) -> CliResult<Box<dyn Source>>
+    {
+        let mut input_factory = self.clone();
+        let path = path.to_string();
+        let open = move || input_factory.create_source_from_path(&path);
+        Ok(Box::new(LazySource::new(Box::new(open), first_timestamp_hint)?))
+    }
+
+    /// Expands a glob or a directory into the input files it holds, in path order, leaving out
+    /// files whose name date or dc footer shows they have no rows in the range. Directories are
+    /// searched recursively for files of known types. None if the input is a single path.
+    pub fn expand_input(&self,
+                        input: &str,
+                        timestamp_range: &TimestampRange,
+                        timezone: Tz) -> CliResult<Option<Vec<ExpandedInput>>>
+    {
+        let path = Path::new(input);
+        let mut paths: Vec<PathBuf> = if input.contains("://") || path.is_file() {
+            return Ok(None)
+        } else if path.is_dir() {
+            let mut paths = Vec::new();
+            self.find_files(path, &mut paths)?;
+            paths
+        } else if input.contains(['*', '?', '[']) {
+            let matches = match glob::glob(input) {
+                Ok(m) => m,
+                Err(e) => return Err(Error::from(format!("Cannot parse input pattern {}: {}", input, e)))
+            };
+            let mut paths = Vec::new();
+            for m in matches {
+                match m {
+                    Ok(p) if p.is_file() => paths.push(p),
+                    Ok(_) => continue,
+                    Err(e) => return Err(Error::from(io::Error::from(e)))
+                }
+            }
+            if paths.is_empty() {
+                return Err(Error::from(format!("Input pattern matches no files: {}", input)))
+            }
+            paths
+        } else {
+            return Ok(None)
+        };
+        paths.sort();
+
+        let mut inputs: Vec<ExpandedInput> = Vec::with_capacity(paths.len());
+        for path in paths {
+            let time_span = match TimeSpan::from_dc_footer(&path)? {
+                Some(span) => Some(span),
+                None => TimeSpan::from_path_date(&path, timezone)
+            };
+            if time_span.is_some_and(|s| !s.overlaps(timestamp_range)) {
+                continue;
+            }
+            let path = match path.to_str() {
+                Some(p) => p.to_string(),
+                None => return Err(Error::from(format!("Input path is not valid utf-8: {:?}", path)))
+            };
+            inputs.push(ExpandedInput { path, time_span });
+        }
+        Ok(Some(inputs))
+    }
+
+    // files under the directory that a source factory can read
+    fn find_files(&self, dir: &Path, paths: &mut Vec<PathBuf>) -> CliResult<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.find_files(&path, paths)?;
+            } else if let Some(extension) = file_extension(&path) {
+                let mut type_path = Path::new("dummy").with_extension(&extension);
+                if decompress::is_compressed(&type_path) {
+                    type_path = PathBuf::from(type_path.file_stem().unwrap());
+                }
+                if type_path.extension().is_some()
+                    && self.source_factories.borrow().iter().any(|sf| sf.can_create_from(&type_path)) {
+                    paths.push(path);
+                }
+            }
+        }
+        Ok(())
     }
 
     fn create_source(&mut self, path: &str, file_extension_override: Option<&str>) -> CliResult<Box<dyn Source>> {
-        let mut path = Path::new(path);
-        let reader = self.create_io_reader(path)?;
+        let path = Path::new(path);
         let file_extension = match file_extension_override {
             Some(x) => x.to_string(),
-            None => {
-                if path.extension().is_none() {
-                    return Err(Error::from(format!("Cannot find file type for [{:?}]. \
+            None => match self.transport_file_type(path).or_else(|| file_extension(path)) {
+                Some(x) => x,
+                None => return Err(Error::from(format!("Cannot find file type for [{:?}]. \
                     Please specify file type as file extension or use parameter file_type.", path)))
-                };
-
-                let mut extension: String = "".to_string();
-                while path.extension().is_some() {
-                    let string = path.extension().unwrap().to_str().unwrap();
-                    extension = format!(".{}{}", string, extension);
-                    path = Path::new(path.file_stem().unwrap());
-                }
-                extension
             }
         };
+
+        if let Some(follow) = self.follow {
+            if self.transport_name(path) == Some("file") {
+                return self.create_follow_source(path, &file_extension, follow)
+            }
+        }
+
+        // uncompressed inputs with random access can seek to the begin timestamp
+        let type_path = Path::new("dummy").with_extension(&file_extension);
+        if !decompress::is_compressed(&type_path) {
+            if let Some(reader) = self.create_seekable_io_reader(path)? {
+                return self.with_source_factory(&type_path, |sf| sf.create_seekable_source(reader))
+            }
+        }
+
+        let reader = self.create_io_reader(path)?;
         self.create_source_from_reader(Box::new(reader), &file_extension)
     }
 
+    // opened again from the start after the file is rotated or truncated
+    fn create_follow_source(&mut self,
+                            path: &Path,
+                            file_extension: &str,
+                            follow: FollowConfig) -> CliResult<Box<dyn Source>>
+    {
+        let mut input_factory = self.clone();
+        let path = path.to_path_buf();
+        let file_extension = file_extension.to_string();
+        let open = move || {
+            let reader = FollowReader::new(&path, follow)?;
+            let reopen = reader.reopen_flag();
+            let reader = input_factory.with_deadline(Box::new(reader));
+            Ok((input_factory.create_source_from_reader(reader, &file_extension)?, reopen))
+        };
+        Ok(Box::new(FollowSource::new(Box::new(open))?))
+    }
+
     fn create_source_from_reader(&mut self,
                                  mut reader: Box<dyn io::Read>,
                                  file_extension: &str) -> CliResult<Box<dyn Source>>
@@ -99,16 +260,39 @@ impl InputFactory {
             path = Path::new(path.file_stem().unwrap());
         }
         // get source from matching source factory
-        for sf in &mut self.source_factories {
-            if sf.can_create_from(&path) {
-                return sf.create_source(reader)
+        self.with_source_factory(path, |sf| sf.create_source(reader))
+    }
+
+    fn with_source_factory<T>(&self,
+                              path: &Path,
+                              f: impl FnOnce(&mut Box<dyn SourceFactory>) -> CliResult<T>) -> CliResult<T>
+    {
+        for sf in self.source_factories.borrow_mut().iter_mut() {
+            if sf.can_create_from(path) {
+                return f(sf)
             }
         }
         Err(Error::from(format!("Cannot find source factory for file - {:?}", path)))
     }
 
+    fn transport_name(&self, path: &Path) -> Option<&str> {
+        Some(self.transport_factories.iter().rev().find(|f| f.can_open(path))?.factory_name())
+    }
+
+    fn transport_file_type(&self, path: &Path) -> Option<String> {
+        self.transport_factories.iter().rev().find(|f| f.can_open(path))?.file_type(path)
+    }
+
+    fn create_seekable_io_reader(&mut self, path: &Path) -> CliResult<Option<Box<dyn SeekableRead>>> {
+        // same factory as create_io_reader would pick
+        match self.transport_factories.iter().rev().find(|f| f.can_open(path)) {
+            Some(factory) => Ok(factory.open_seekable(path)?),
+            None => Ok(None)
+        }
+    }
+
     fn create_io_reader(&mut self, path: &Path) -> CliResult<Box<dyn io::Read>> {
-        let mut io_reader: Option<Box<dyn io::Read>> = None;
+        let mut io_reader: Option<Box<dyn io::Read + Send>> = None;
         for _factory in &mut self.transport_factories.iter() {
             match _factory.can_open(path) {
                 false => continue,
@@ -124,19 +308,41 @@ impl InputFactory {
                 let err = io::Error::new(io::ErrorKind::Other, msg);
                 Err(Error::Io(err))
             }
-            Some(r) => Ok(Box::new(r))
+            // local files do not wait for data
+            Some(r) if self.transport_name(path) == Some("file") => Ok(r),
+            Some(r) => Ok(self.with_deadline(r))
+        }
+    }
+
+    fn with_deadline(&self, reader: Box<dyn io::Read + Send>) -> Box<dyn io::Read> {
+        match self.deadline {
+            Some(deadline) => Box::new(DeadlineReader::new(reader, deadline)),
+            None => reader
         }
     }
 }
 
-pub fn create_default_source_factories(csv_input_config: CSVInputConfig) -> Vec<Box<dyn SourceFactory>> {
+// all extensions of the file name, e.g. ".csv.gz"
+fn file_extension(path: &Path) -> Option<String> {
+    path.extension()?;
+    let mut stem = path;
+    let mut extension: String = "".to_string();
+    while let Some(e) = stem.extension() {
+        extension = format!(".{}{}", e.to_str()?, extension);
+        stem = Path::new(stem.file_stem()?);
+    }
+    Some(extension)
+}
+
+pub fn create_default_source_factories(csv_input_config: CSVInputConfig,
+                                       dc_input_config: DCInputConfig) -> Vec<Box<dyn SourceFactory>> {
     let source_factories: Vec<Box<dyn SourceFactory>>
-        = vec![Box::new(CSVFactory::new(csv_input_config)), Box::new(DCFactory)];
+        = vec![Box::new(CSVFactory::new(csv_input_config)), Box::new(DCFactory::new(dc_input_config))];
     source_factories
 }
 
 pub fn create_default_transport_factories() -> Vec<Box<dyn TransportFactory>> {
     let transport_factories: Vec<Box<dyn TransportFactory>>
-        = vec![Box::new(FileInput), Box::new(Http)];
+        = vec![Box::new(FileInput::new()), Box::new(Http::default()), Box::new(Socket)];
     transport_factories
 }
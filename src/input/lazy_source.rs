@@ -0,0 +1,102 @@
+use crate::chopper::chopper::Source;
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::error::CliResult;
+
+pub type OpenSource = Box<dyn FnMut() -> CliResult<Box<dyn Source>>>;
+
+/// Opens its input only when rows are first read, and closes it at the end,
+/// so many inputs do not hold open files all at once. The input is opened once
+/// up front to read the header, so inputs must be files that can be read twice.
+pub struct LazySource {
+    open: OpenSource,
+    source: Option<Box<dyn Source>>,
+    header: Header,
+    has_native_timestamp_column: bool,
+    properties: Vec<(String, String)>,
+    first_timestamp_hint: Option<Nanos>,
+    seek_to: Option<Nanos>,
+    done: bool,
+    // of the input once closed
+    warnings: Vec<String>,
+}
+
+impl LazySource {
+    pub fn new(mut open: OpenSource, first_timestamp_hint: Option<Nanos>) -> CliResult<Self> {
+        let source = open()?;
+        let header = source.header().clone();
+        let has_native_timestamp_column = source.has_native_timestamp_column();
+        let properties = source.properties();
+        Ok(LazySource {
+            open,
+            source: None,
+            header,
+            has_native_timestamp_column,
+            properties,
+            first_timestamp_hint,
+            seek_to: None,
+            done: false,
+            warnings: Vec::new()
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.source.is_some()
+    }
+}
+
+impl Source for LazySource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        if self.done {
+            return Ok(None)
+        }
+        if self.source.is_none() {
+            let mut source = (self.open)()?;
+            if let Some(begin) = self.seek_to {
+                source.seek(begin)?;
+            }
+            self.source = Some(source);
+        }
+        let row = self.source.as_mut().unwrap().next_row()?;
+        if row.is_none() {
+            self.warnings = self.source.take().unwrap().warnings();
+            self.done = true;
+        }
+        Ok(row)
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        self.has_native_timestamp_column
+    }
+
+    fn seek(&mut self, begin: Nanos) -> CliResult<()> {
+        match &mut self.source {
+            Some(source) => source.seek(begin),
+            None => {
+                self.seek_to = Some(begin);
+                Ok(())
+            }
+        }
+    }
+
+    fn first_timestamp_hint(&self) -> Option<Nanos> {
+        match self.source {
+            Some(_) => None,
+            None => self.first_timestamp_hint
+        }
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        self.properties.clone()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        match &self.source {
+            Some(source) => source.warnings(),
+            None => self.warnings.clone()
+        }
+    }
+}
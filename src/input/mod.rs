@@ -1 +1,5 @@
+pub mod concat_source;
+pub mod follow_source;
 pub mod input_factory;
+pub mod lazy_source;
+pub mod time_span;
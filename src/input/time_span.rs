@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use chrono::{Days, NaiveDate};
+use chrono_tz::Tz;
+
+use crate::chopper::types::{Nanos, TimestampRange};
+use crate::error::{CliResult, Error};
+use crate::util::dc_util;
+use crate::util::timestamp_util::{self, DstPolicy};
+
+/// The timestamps an input file may hold, as far as can be told without reading its rows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeSpan {
+    pub begin: Nanos,
+    // exclusive
+    pub end: Nanos,
+}
+
+impl TimeSpan {
+    /// False if none of the timestamps can be within the range.
+    pub fn overlaps(&self, range: &TimestampRange) -> bool {
+        range.begin.is_none_or(|b| self.end > b) && range.end.is_none_or(|e| self.begin < e)
+    }
+
+    /// The local day of a date in the path: YYYY/MM/DD directories, or YYYY-MM-DD
+    /// or YYYYMMDD in the file name.
+    pub fn from_path_date(path: &Path, timezone: Tz) -> Option<TimeSpan> {
+        let date = date_from_dirs(path).or_else(|| date_from_file_name(path))?;
+        let begin = timestamp_util::localize(
+            &date.and_hms_opt(0, 0, 0)?, timezone, DstPolicy::Earliest).ok()?;
+        let end = timestamp_util::localize(
+            &date.checked_add_days(Days::new(1))?.and_hms_opt(0, 0, 0)?, timezone, DstPolicy::Earliest).ok()?;
+        if begin < 0 {
+            return None
+        }
+        Some(TimeSpan { begin: begin as Nanos, end: end as Nanos })
+    }
+
+    /// The min and max timestamps in the footer of an uncompressed dc v3 file.
+    /// None for other files or if the footer is not written.
+    pub fn from_dc_footer(path: &Path) -> CliResult<Option<TimeSpan>> {
+        if path.extension().is_none_or(|e| e != "dc") {
+            return Ok(None)
+        }
+        let footer = match File::open(path).map_err(Error::from)
+            .and_then(|mut file| dc_util::read_footer_from_end(&mut file)) {
+            Ok(footer) => footer,
+            // shorter than a dc header, e.g. empty or still being written
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(Error::from(format!(
+                "TimeSpan -- cannot read dc footer of {}: {}", path.display(), e)))
+        };
+        Ok(footer.map(|f| match f.row_count {
+            0 => TimeSpan { begin: 0, end: 0 },
+            _ => TimeSpan { begin: f.min_timestamp, end: f.max_timestamp + 1 }
+        }))
+    }
+}
+
+fn parse_date(text: &str, format: &str) -> Option<NaiveDate> {
+    match NaiveDate::parse_from_str(text, format) {
+        Ok(date) if (1970..2200).contains(&chrono::Datelike::year(&date)) => Some(date),
+        _ => None
+    }
+}
+
+// the last YYYY/MM/DD run of directories
+fn date_from_dirs(path: &Path) -> Option<NaiveDate> {
+    let dirs: Vec<&str> = path.parent()?.iter().filter_map(|c| c.to_str()).collect();
+    dirs.windows(3).rev()
+        .filter(|w| w[0].len() == 4 && w[1].len() == 2 && w[2].len() == 2)
+        .find_map(|w| parse_date(&w.concat(), "%Y%m%d"))
+}
+
+// YYYY-MM-DD or YYYYMMDD in the file name, not part of a longer number
+fn date_from_file_name(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?.as_bytes();
+    let is_digit = |i: usize| i < name.len() && name[i].is_ascii_digit();
+    for start in 0..name.len() {
+        if !is_digit(start) || (start > 0 && is_digit(start - 1)) {
+            continue;
+        }
+        let digits = (start..name.len()).take_while(|&i| is_digit(i)).count();
+        let text = std::str::from_utf8(&name[start..]).ok()?;
+        let date = match digits {
+            8 => parse_date(&text[..8], "%Y%m%d"),
+            4 if !is_digit(start + 10) => text.get(..10).and_then(|t| parse_date(t, "%Y-%m-%d")),
+            _ => None
+        };
+        if date.is_some() {
+            return date
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::{America::New_York, UTC};
+
+    use crate::chopper::types::TIMESTAMP_RANGE_DEFAULT;
+
+    use super::*;
+
+    #[test]
+    fn test_from_path_date() {
+        let day = TimeSpan { begin: 1559520000, end: 1559606400 };
+        assert_eq!(TimeSpan::from_path_date(Path::new("data/2019/06/03/a.dc.gz"), UTC), Some(day));
+        assert_eq!(TimeSpan::from_path_date(Path::new("data/feed_20190603.csv"), UTC), Some(day));
+        assert_eq!(TimeSpan::from_path_date(Path::new("feed.2019-06-03.csv"), UTC), Some(day));
+        assert_eq!(TimeSpan::from_path_date(Path::new("2019-06-03.csv"), New_York),
+                   Some(TimeSpan { begin: 1559534400, end: 1559620800 }));
+        assert_eq!(TimeSpan::from_path_date(Path::new("data/1559520000.csv"), UTC), None);
+        assert_eq!(TimeSpan::from_path_date(Path::new("data/feed_2019.csv"), UTC), None);
+        assert_eq!(TimeSpan::from_path_date(Path::new("data/20191340.csv"), UTC), None);
+
+        let range = TimestampRange { begin: Some(1559606400), ..TIMESTAMP_RANGE_DEFAULT };
+        assert!(!day.overlaps(&range));
+        let range = TimestampRange { end: Some(1559520001), ..TIMESTAMP_RANGE_DEFAULT };
+        assert!(day.overlaps(&range));
+    }
+}
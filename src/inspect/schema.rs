@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::chopper::chopper::{ChopperDriver, Source};
+use crate::error::CliResult;
+use crate::source::decompress;
+
+/// Prints field names, types and format details of each input without driving any rows.
+pub struct SchemaInspector {
+    inputs: Vec<(String, Box<dyn Source>)>,
+    json: bool,
+}
+
+impl SchemaInspector {
+    pub fn new(inputs: Vec<(String, Box<dyn Source>)>, json: bool) -> Self {
+        SchemaInspector { inputs, json }
+    }
+
+    fn compression(input: &str) -> Option<String> {
+        let path = Path::new(input);
+        match path.extension() {
+            Some(e) if decompress::is_compressed(path) => Some(e.to_string_lossy().to_string()),
+            _ => None
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut inputs: Vec<Value> = Vec::with_capacity(self.inputs.len());
+        for (input, source) in &self.inputs {
+            let header = source.header();
+            let fields: Vec<Value> = header.field_names().iter()
+                .zip(header.field_types())
+                .map(|(name, field_type)| json!({ "name": name, "type": format!("{:?}", field_type) }))
+                .collect();
+            let mut properties = serde_json::Map::new();
+            for (name, value) in source.properties() {
+                properties.insert(name, Value::String(value));
+            }
+            inputs.push(json!({
+                "input": input,
+                "compression": Self::compression(input),
+                "fields": fields,
+                "properties": properties,
+            }));
+        }
+        Value::Array(inputs)
+    }
+
+    fn write_human<W: Write>(&self, writer: &mut W) -> CliResult<()> {
+        for (input, source) in &self.inputs {
+            let header = source.header();
+            let compression = Self::compression(input).unwrap_or_else(|| "none".to_string());
+            writeln!(writer, "input: {}", input)?;
+            writeln!(writer, "compression: {}", compression)?;
+            writeln!(writer, "fields:")?;
+            let name_width = header.field_names().iter().map(|n| n.len()).max().unwrap_or(0);
+            for (name, field_type) in header.field_names().iter().zip(header.field_types()) {
+                writeln!(writer, "    {:width$}  {:?}", name, field_type, width = name_width)?;
+            }
+            let properties = source.properties();
+            if !properties.is_empty() {
+                writeln!(writer, "properties:")?;
+                for (name, value) in properties {
+                    writeln!(writer, "    {}: {}", name, value)?;
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChopperDriver for SchemaInspector {
+    fn drive(&mut self) -> CliResult<()> {
+        let stdout = io::stdout();
+        let mut writer = stdout.lock();
+        if self.json {
+            writeln!(writer, "{:#}", self.to_json())?;
+        } else {
+            self.write_human(&mut writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::csv_configs::{CSVInputConfig, TimestampConfig};
+    use crate::source::csv_source::CSVSource;
+
+    #[test]
+    fn test_schema_json() {
+        let data = "time,city\n1500000000,London\n".as_bytes();
+        let config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+        let source: Box<dyn Source> = Box::new(CSVSource::new(data, &config).unwrap());
+        let inspector = SchemaInspector::new(vec![("cities.csv.gz".to_string(), source)], true);
+
+        let json = inspector.to_json();
+        assert_eq!(json[0]["compression"], "gz");
+        assert_eq!(json[0]["fields"][1]["name"], "city");
+        assert_eq!(json[0]["fields"][1]["type"], "String");
+        assert_eq!(json[0]["properties"]["timestamp_column"], "0 (time)");
+    }
+}
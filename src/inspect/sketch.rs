@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+const HLL_PRECISION: u32 = 12;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// Distinct count estimate in fixed memory (4096 one-byte registers, ~1.6% standard error).
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        HyperLogLog { registers: vec![0; HLL_REGISTERS] }
+    }
+
+    pub fn insert<T: Hash + ?Sized>(&mut self, value: &T) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // position of the first set bit in the remaining bits
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for register in &self.registers {
+            sum += 1.0 / (1u64 << register) as f64;
+            if *register == 0 {
+                zeros += 1;
+            }
+        }
+        let estimate = alpha * m * m / sum;
+        // small range correction
+        if estimate <= 2.5 * m && zeros > 0 {
+            return (m * (m / zeros as f64).ln()).round() as u64
+        }
+        estimate.round() as u64
+    }
+}
+
+/// Most frequent values in bounded memory (space-saving algorithm).
+/// Counts are exact while fewer than `capacity` distinct values have been seen,
+/// upper bounds afterwards.
+pub struct TopK {
+    capacity: usize,
+    counts: HashMap<String, u64>,
+}
+
+impl TopK {
+    pub fn new(capacity: usize) -> Self {
+        TopK { capacity, counts: HashMap::with_capacity(capacity) }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        if let Some(count) = self.counts.get_mut(value) {
+            *count += 1;
+            return
+        }
+        if self.counts.len() < self.capacity {
+            self.counts.insert(value.to_string(), 1);
+            return
+        }
+        // replace the least frequent value, inheriting its count
+        let (min_value, min_count) = match self.counts.iter().min_by_key(|(_, c)| **c) {
+            Some((v, c)) => (v.clone(), *c),
+            None => return
+        };
+        self.counts.remove(&min_value);
+        self.counts.insert(value.to_string(), min_count + 1);
+    }
+
+    pub fn top(&self, k: usize) -> Vec<(String, u64)> {
+        let mut top: Vec<(String, u64)> = self.counts.iter().map(|(v, c)| (v.clone(), *c)).collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(k);
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyper_log_log() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..100_000 {
+            hll.insert(&i);
+            hll.insert(&i);
+        }
+        let estimate = hll.estimate() as f64;
+        assert!((estimate - 100_000.0).abs() / 100_000.0 < 0.05);
+
+        let mut hll = HyperLogLog::new();
+        for i in 0..10 {
+            hll.insert(&i);
+        }
+        assert_eq!(hll.estimate(), 10);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let mut top_k = TopK::new(10);
+        for i in 0..1000 {
+            top_k.insert(if i % 2 == 0 { "even" } else { "odd" });
+            top_k.insert(format!("unique_{}", i).as_str());
+        }
+        let top = top_k.top(2);
+        assert_eq!(top[0].0, "even");
+        assert_eq!(top[1].0, "odd");
+        assert!(top[0].1 >= 500);
+    }
+}
@@ -0,0 +1,394 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::chopper::chopper::{DataSink, HeaderSink};
+use crate::chopper::header_graph::PinId;
+use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+use crate::error::CliResult;
+use crate::inspect::sketch::{HyperLogLog, TopK};
+
+pub static TOP_K_DEFAULT: usize = 5;
+// number of gaps listed in the report; all of them are counted
+static GAPS_LISTED: usize = 10;
+
+#[derive(Clone, Copy)]
+pub struct StatsConfig {
+    /// report gaps between consecutive timestamps larger than this, in timestamp units
+    pub gap_threshold: Option<Nanos>,
+    pub top_k: usize,
+    pub json: bool,
+}
+
+struct ColumnStats {
+    name: String,
+    field_type: FieldType,
+    count: u64,
+    null_count: u64,
+    // numeric values, incl. numeric strings
+    numeric_count: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+    // non-numeric strings
+    min_string: Option<String>,
+    max_string: Option<String>,
+    distinct: HyperLogLog,
+    top_k: Option<TopK>,
+}
+
+impl ColumnStats {
+    fn new(name: &str, field_type: &FieldType, top_k: usize) -> Self {
+        let top_k = match field_type {
+            FieldType::String => Some(TopK::new(top_k * 10)),
+            _ => None
+        };
+        ColumnStats {
+            name: name.to_string(),
+            field_type: field_type.clone(),
+            count: 0,
+            null_count: 0,
+            numeric_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+            min_string: None,
+            max_string: None,
+            distinct: HyperLogLog::new(),
+            top_k,
+        }
+    }
+
+    fn add(&mut self, value: &FieldValue) {
+        let number = match value {
+            FieldValue::None => {
+                self.null_count += 1;
+                return
+            }
+            FieldValue::Boolean(x) => { self.distinct.insert(x); None },
+            FieldValue::Byte(x) => Some(*x as f64),
+            FieldValue::ByteBuf(x) => { self.distinct.insert(x); None },
+            FieldValue::Char(x) => Some(*x as f64),
+            FieldValue::Double(x) => Some(*x),
+            FieldValue::Float(x) => Some(*x as f64),
+            FieldValue::Int(x) => Some(*x as f64),
+            FieldValue::Long(x) => Some(*x as f64),
+            FieldValue::Short(x) => Some(*x as f64),
+            FieldValue::String(x) => {
+                self.distinct.insert(x.as_str());
+                if let Some(top_k) = &mut self.top_k {
+                    top_k.insert(x);
+                }
+                match x.parse::<f64>() {
+                    Ok(n) => Some(n),
+                    Err(_) => {
+                        self.add_string(x);
+                        None
+                    }
+                }
+            }
+        };
+        self.count += 1;
+        if let Some(n) = number {
+            if let FieldValue::String(_) = value {} else {
+                self.distinct.insert(&n.to_bits());
+            }
+            self.add_number(n);
+        }
+    }
+
+    fn add_number(&mut self, n: f64) {
+        // Welford's online mean and variance
+        self.numeric_count += 1;
+        let delta = n - self.mean;
+        self.mean += delta / self.numeric_count as f64;
+        self.m2 += delta * (n - self.mean);
+        self.min = self.min.min(n);
+        self.max = self.max.max(n);
+    }
+
+    fn add_string(&mut self, s: &str) {
+        match &self.min_string {
+            Some(min) if min.as_str() <= s => (),
+            _ => self.min_string = Some(s.to_string())
+        }
+        match &self.max_string {
+            Some(max) if max.as_str() >= s => (),
+            _ => self.max_string = Some(s.to_string())
+        }
+    }
+
+    // numeric summary only if every non-null value was numeric
+    fn is_numeric(&self) -> bool {
+        self.count > 0 && self.numeric_count == self.count
+    }
+
+    fn min(&self) -> Option<String> {
+        if self.is_numeric() { Some(self.min.to_string()) } else { self.min_string.clone() }
+    }
+
+    fn max(&self) -> Option<String> {
+        if self.is_numeric() { Some(self.max.to_string()) } else { self.max_string.clone() }
+    }
+
+    fn stddev(&self) -> Option<f64> {
+        if self.is_numeric() && self.numeric_count > 1 {
+            Some((self.m2 / (self.numeric_count - 1) as f64).sqrt())
+        } else {
+            None
+        }
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.is_numeric() { Some(self.mean) } else { None }
+    }
+}
+
+/// Collects statistics over the stream and writes a report when the stream finishes.
+pub struct StatsSink {
+    writer: BufWriter<Box<dyn io::Write+'static>>,
+    config: StatsConfig,
+    columns: Vec<ColumnStats>,
+    row_count: u64,
+    first_timestamp: Option<Nanos>,
+    last_timestamp: Option<Nanos>,
+    out_of_order_count: u64,
+    gap_count: u64,
+    largest_gap: Option<Nanos>,
+    gaps: Vec<(Nanos, Nanos)>,
+}
+
+impl StatsSink {
+    pub fn new(path: &Option<String>, config: StatsConfig) -> CliResult<Self> {
+        let writer = BufWriter::new(StatsSink::into_writer(path)?);
+        Ok(StatsSink {
+            writer,
+            config,
+            columns: Vec::new(),
+            row_count: 0,
+            first_timestamp: None,
+            last_timestamp: None,
+            out_of_order_count: 0,
+            gap_count: 0,
+            largest_gap: None,
+            gaps: Vec::new(),
+        })
+    }
+
+    fn into_writer(path: &Option<String>) -> io::Result<Box<dyn io::Write>> {
+        match path {
+            None => {
+                Ok(Box::new(io::stdout()))
+            }
+            Some(p) => {
+                let path = PathBuf::from(p);
+                let file = File::create(path)?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+
+    fn add_timestamp(&mut self, timestamp: Nanos) {
+        if let Some(last) = self.last_timestamp {
+            if timestamp < last {
+                self.out_of_order_count += 1;
+            } else if let Some(threshold) = self.config.gap_threshold {
+                let gap = timestamp - last;
+                if gap > threshold {
+                    self.gap_count += 1;
+                    if self.gaps.len() < GAPS_LISTED {
+                        self.gaps.push((last, timestamp));
+                    }
+                }
+            }
+            if timestamp >= last {
+                let gap = timestamp - last;
+                if self.largest_gap.is_none_or(|g| gap > g) {
+                    self.largest_gap = Some(gap);
+                }
+            }
+        } else {
+            self.first_timestamp = Some(timestamp);
+        }
+        self.last_timestamp = Some(timestamp);
+    }
+
+    fn to_json(&self) -> Value {
+        let columns: Vec<Value> = self.columns.iter().map(|c| {
+            let top: Option<Vec<Value>> = c.top_k.as_ref().map(|t| t.top(self.config.top_k).into_iter()
+                .map(|(value, count)| json!({ "value": value, "count": count }))
+                .collect());
+            json!({
+                "name": c.name,
+                "type": format!("{:?}", c.field_type),
+                "count": c.count,
+                "null_count": c.null_count,
+                "min": c.min(),
+                "max": c.max(),
+                "mean": c.mean(),
+                "stddev": c.stddev(),
+                "distinct_estimate": c.distinct.estimate(),
+                "top": top,
+            })
+        }).collect();
+        let gaps: Vec<Value> = self.gaps.iter().map(|(from, to)| json!({ "from": from, "to": to })).collect();
+        json!({
+            "row_count": self.row_count,
+            "first_timestamp": self.first_timestamp,
+            "last_timestamp": self.last_timestamp,
+            "out_of_order_count": self.out_of_order_count,
+            "largest_gap": self.largest_gap,
+            "gap_threshold": self.config.gap_threshold,
+            "gap_count": self.gap_count,
+            "gaps": gaps,
+            "columns": columns,
+        })
+    }
+
+    fn write_table(&mut self) -> CliResult<()> {
+        let format_option = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
+        let mut rows: Vec<Vec<String>> = vec![
+            ["column", "type", "count", "nulls", "min", "max", "mean", "stddev", "distinct", "top"]
+                .iter().map(|s| s.to_string()).collect()];
+        for c in &self.columns {
+            let top = match &c.top_k {
+                Some(t) => t.top(self.config.top_k).iter()
+                    .map(|(value, count)| format!("{}({})", value, count))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                None => "-".to_string()
+            };
+            rows.push(vec![
+                c.name.clone(),
+                format!("{:?}", c.field_type),
+                c.count.to_string(),
+                c.null_count.to_string(),
+                format_option(c.min()),
+                format_option(c.max()),
+                format_option(c.mean().map(|m| format!("{:.6}", m))),
+                format_option(c.stddev().map(|s| format!("{:.6}", s))),
+                c.distinct.estimate().to_string(),
+                top]);
+        }
+
+        let timestamp = |t: Option<Nanos>| t.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string());
+        writeln!(self.writer, "rows: {}", self.row_count)?;
+        writeln!(self.writer, "first timestamp: {}", timestamp(self.first_timestamp))?;
+        writeln!(self.writer, "last timestamp: {}", timestamp(self.last_timestamp))?;
+        writeln!(self.writer, "out of order: {}", self.out_of_order_count)?;
+        writeln!(self.writer, "largest gap: {}", timestamp(self.largest_gap))?;
+        if let Some(threshold) = self.config.gap_threshold {
+            writeln!(self.writer, "gaps > {}: {}", threshold, self.gap_count)?;
+            for (from, to) in &self.gaps {
+                writeln!(self.writer, "    {} - {}", from, to)?;
+            }
+        }
+        writeln!(self.writer)?;
+
+        let mut widths: Vec<usize> = vec![0; rows[0].len()];
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+        for row in &rows {
+            let cells: Vec<String> = row.iter().enumerate()
+                .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+                .collect();
+            writeln!(self.writer, "{}", cells.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl HeaderSink for StatsSink {
+    fn process_header(mut self: Box<Self>, header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        let top_k = self.config.top_k;
+        self.columns = header.field_names().iter()
+            .zip(header.field_types())
+            .map(|(name, field_type)| ColumnStats::new(name, field_type, top_k))
+            .collect();
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for StatsSink {
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        self.row_count += 1;
+        self.add_timestamp(row.timestamp);
+        for (column, value) in self.columns.iter_mut().zip(&row.field_values) {
+            column.add(value);
+        }
+        Ok(None)
+    }
+
+    fn write_row_to_pin(&mut self, _pin_id: PinId, row: Row) -> CliResult<Option<Row>> {
+        self.write_row(row)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        if self.config.json {
+            let json = self.to_json();
+            writeln!(self.writer, "{:#}", json)?;
+        } else {
+            self.write_table()?;
+        }
+        self.flush()?;
+        Ok(Vec::new())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let config = StatsConfig { gap_threshold: Some(5), top_k: 1, json: true };
+        let mut sink = StatsSink::new(&None, config).unwrap();
+        sink.columns = vec![ColumnStats::new("a", &FieldType::Int, 1),
+                            ColumnStats::new("b", &FieldType::String, 1)];
+        let rows = vec![(10, 1, "x"), (12, 2, "y"), (11, 3, "x"), (20, 6, "x")];
+        for (timestamp, a, b) in rows {
+            let field_values = vec![FieldValue::Int(a), FieldValue::String(b.to_string())];
+            sink.write_row(Row { timestamp, field_values }).unwrap();
+        }
+        sink.write_row(Row { timestamp: 21, field_values: vec![FieldValue::None, FieldValue::None] }).unwrap();
+
+        let json = sink.to_json();
+        assert_eq!(json["row_count"], 5);
+        assert_eq!(json["first_timestamp"], 10);
+        assert_eq!(json["last_timestamp"], 21);
+        assert_eq!(json["out_of_order_count"], 1);
+        assert_eq!(json["gap_count"], 1);
+        assert_eq!(json["gaps"][0], json!({ "from": 11, "to": 20 }));
+
+        let a = &json["columns"][0];
+        assert_eq!(a["count"], 4);
+        assert_eq!(a["null_count"], 1);
+        assert_eq!(a["min"], "1");
+        assert_eq!(a["max"], "6");
+        assert_eq!(a["mean"], 3.0);
+        assert_eq!(a["distinct_estimate"], 4);
+
+        let b = &json["columns"][1];
+        assert_eq!(b["min"], "x");
+        assert_eq!(b["max"], "y");
+        assert_eq!(b["mean"], Value::Null);
+        assert_eq!(b["distinct_estimate"], 2);
+        assert_eq!(b["top"], json!([{ "value": "x", "count": 3 }]));
+    }
+}
@@ -10,6 +10,7 @@ pub mod cli_app;
 pub mod driver;
 pub mod filter;
 pub mod input;
+pub mod inspect;
 pub mod source;
 pub mod transport;
 pub mod util;
@@ -2,8 +2,10 @@ use std::fmt;
 
 use chrono_tz::Tz;
 
-use crate::error::CliResult;
+use crate::error::{CliResult, Error};
 use crate::util::{csv_util, timestamp_util};
+use crate::util::csv_util::RejectedRows;
+use crate::util::timestamp_util::DstPolicy;
 
 pub static DELIMITER_DEFAULT: &str = ",";
 pub static TIMESTAMP_COL_DATE_DEFAULT: usize = 0;
@@ -11,10 +13,39 @@ pub static TIMESTAMP_COL_DATE_DEFAULT: usize = 0;
 pub type DateCol = usize;
 pub type TimeCol = usize;
 
-#[derive(Clone)]
+// candidate names of a timestamp column for auto-detection, most likely first
+pub static TIMESTAMP_COL_NAMES: [&str; 8]
+    = ["timestamp", "ts", "time", "datetime", "date_time", "epoch", "time_stamp", "t"];
+
+/// Timestamp column(s) of a csv input; names and auto-detection are resolved
+/// to indexes when the input is opened.
+#[derive(Clone, Debug)]
 pub enum TimestampCol {
     Timestamp(usize),
     DateAndTime(DateCol, TimeCol),
+    TimestampName(String),
+    DateAndTimeNames(String, String),
+    Auto,
+}
+
+impl TimestampCol {
+    /// Parses a column given by index or by header name.
+    pub fn parse(col: &str) -> Self {
+        match col {
+            "auto" => TimestampCol::Auto,
+            _ => match col.parse::<usize>() {
+                Ok(i) => TimestampCol::Timestamp(i),
+                Err(_) => TimestampCol::TimestampName(col.to_string())
+            }
+        }
+    }
+
+    pub fn parse_date_and_time(date: &str, time: &str) -> Self {
+        match (date.parse::<usize>(), time.parse::<usize>()) {
+            (Ok(d), Ok(t)) => TimestampCol::DateAndTime(d, t),
+            _ => TimestampCol::DateAndTimeNames(date.to_string(), time.to_string())
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -22,17 +53,24 @@ pub struct TimestampConfig {
     timestamp_col: TimestampCol,
     timestamp_fmt: Option<String>,
     timezone: Tz,
+    // drop the timestamp source columns from the output, the value stays in Row::timestamp
+    drop_columns: bool,
+    dst_policy: DstPolicy,
 }
 
 impl TimestampConfig {
     pub fn new(timestamp_col: TimestampCol, timestamp_fmt: Option<String>, timezone: Tz) -> Self {
-        TimestampConfig { timestamp_col, timestamp_fmt, timezone }
+        TimestampConfig { timestamp_col, timestamp_fmt, timezone, drop_columns: false, dst_policy: DstPolicy::Error }
     }
 
     pub fn default() -> Self {
         let timestamp_col = TimestampCol::Timestamp(0);
         let timezone = timestamp_util::DEFAULT_ZONE;
-        TimestampConfig { timestamp_col, timestamp_fmt: None, timezone }
+        TimestampConfig { timestamp_col, timestamp_fmt: None, timezone, drop_columns: false, dst_policy: DstPolicy::Error }
+    }
+
+    pub fn set_timestamp_col(&mut self, timestamp_col: TimestampCol) {
+        self.timestamp_col = timestamp_col
     }
 
     pub fn timestamp_col(&mut self) -> &mut TimestampCol {
@@ -50,19 +88,157 @@ impl TimestampConfig {
     pub fn timezone(&self) -> Tz {
         self.timezone
     }
+
+    pub fn dst_policy(&self) -> DstPolicy {
+        self.dst_policy
+    }
+
+    pub fn set_dst_policy(&mut self, dst_policy: DstPolicy) {
+        self.dst_policy = dst_policy
+    }
+
+    pub fn drop_columns(&self) -> bool {
+        self.drop_columns
+    }
+
+    pub fn set_drop_columns(&mut self, drop_columns: bool) {
+        self.drop_columns = drop_columns
+    }
+}
+
+// multi-byte delimiters are translated to this byte before parsing
+pub const MULTI_BYTE_DELIMITER_PLACEHOLDER: u8 = 0x1f;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EscapeStyle {
+    /// a quote inside a quoted field is written as two quotes
+    Double,
+    /// a quote inside a quoted field is preceded by a backslash
+    Backslash,
+}
+
+impl EscapeStyle {
+    pub fn parse(escape: &str) -> CliResult<Self> {
+        match escape {
+            "double" => Ok(EscapeStyle::Double),
+            "backslash" => Ok(EscapeStyle::Backslash),
+            _ => Err(Error::from(format!("CSV -- unknown escape style - {}", escape)))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrimMode {
+    None,
+    Headers,
+    Fields,
+    All,
+}
+
+impl TrimMode {
+    pub fn parse(trim: &str) -> CliResult<Self> {
+        match trim {
+            "none" => Ok(TrimMode::None),
+            "headers" => Ok(TrimMode::Headers),
+            "fields" => Ok(TrimMode::Fields),
+            "all" => Ok(TrimMode::All),
+            _ => Err(Error::from(format!("CSV -- unknown trim mode - {}", trim)))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuoteStyle {
+    /// quote fields that contain a delimiter, quote or line break
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl QuoteStyle {
+    pub fn parse(style: &str) -> CliResult<Self> {
+        match style {
+            "necessary" => Ok(QuoteStyle::Necessary),
+            "always" => Ok(QuoteStyle::Always),
+            "non-numeric" => Ok(QuoteStyle::NonNumeric),
+            "never" => Ok(QuoteStyle::Never),
+            _ => Err(Error::from(format!("CSV -- unknown quote style - {}", style)))
+        }
+    }
+}
+
+/// What to do with an input row whose field count differs from the header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BadRowPolicy {
+    /// fail, reporting the line number
+    Error,
+    /// drop the row and count it
+    Skip,
+    /// fill missing fields of short rows with nulls; long rows fail
+    Pad,
+    /// drop extra fields of long rows; short rows fail
+    Truncate,
+}
+
+impl BadRowPolicy {
+    pub fn parse(policy: &str) -> CliResult<Self> {
+        match policy {
+            "error" => Ok(BadRowPolicy::Error),
+            "skip" => Ok(BadRowPolicy::Skip),
+            "pad" => Ok(BadRowPolicy::Pad),
+            "truncate" => Ok(BadRowPolicy::Truncate),
+            _ => Err(Error::from(format!("CSV -- unknown bad row policy - {}", policy)))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineTerminator {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineTerminator {
+    pub fn parse(terminator: &str) -> CliResult<Self> {
+        match terminator {
+            "lf" => Ok(LineTerminator::Lf),
+            "crlf" => Ok(LineTerminator::CrLf),
+            "cr" => Ok(LineTerminator::Cr),
+            _ => Err(Error::from(format!("CSV -- unknown line terminator - {}", terminator)))
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct CSVInputConfig {
-    delimiter: u8,
+    delimiter: Vec<u8>,
     has_header: bool,
     timestamp_config: TimestampConfig,
+    quote: Option<u8>,
+    escape: EscapeStyle,
+    comment: Option<u8>,
+    trim: TrimMode,
+    flexible: bool,
+    skip_lines: usize,
+    // field values read as nulls, e.g. NA or \N
+    null_values: Vec<String>,
+    bad_row_policy: BadRowPolicy,
+    rejected_rows: Option<RejectedRows>,
 }
 
 #[derive(Clone)]
 pub struct CSVOutputConfig {
     delimiter: String,
-    print_timestamp: bool
+    print_timestamp: bool,
+    // format of timestamp-hinted columns; None prints the raw value
+    hint_timestamp_format: Option<String>,
+    quote: u8,
+    quote_style: QuoteStyle,
+    line_terminator: LineTerminator,
+    // written for null values
+    null_value: String,
 }
 
 impl CSVInputConfig {
@@ -70,22 +246,117 @@ impl CSVInputConfig {
                has_header: bool,
                timestamp_config: TimestampConfig) -> CliResult<Self>
     {
-        let delimiter = csv_util::parse_into_delimiter(delimiter)?;
-        Ok(CSVInputConfig { delimiter, has_header, timestamp_config })
+        let delimiter = csv_util::parse_delimiter(delimiter)?;
+        Ok(CSVInputConfig {
+            delimiter,
+            has_header,
+            timestamp_config,
+            quote: Some(b'"'),
+            escape: EscapeStyle::Double,
+            comment: None,
+            trim: TrimMode::All,
+            flexible: false,
+            skip_lines: 0,
+            null_values: Vec::new(),
+            bad_row_policy: BadRowPolicy::Error,
+            rejected_rows: None
+        })
     }
 
     pub fn new_default() -> CliResult<Self> {
-        let delimiter = csv_util::parse_into_delimiter(DELIMITER_DEFAULT)?;
-        let timestamp_config = TimestampConfig::default();
-        Ok(CSVInputConfig { delimiter, has_header: false, timestamp_config })
+        CSVInputConfig::new(DELIMITER_DEFAULT, false, TimestampConfig::default())
     }
 
     pub fn has_header(&self) -> bool {
         self.has_header
     }
 
+    /// Delimiter byte the csv parser splits on.
     pub fn delimiter(&self) -> u8 {
-        self.delimiter
+        match self.delimiter.len() {
+            1 => self.delimiter[0],
+            _ => MULTI_BYTE_DELIMITER_PLACEHOLDER
+        }
+    }
+
+    pub fn delimiter_bytes(&self) -> &[u8] {
+        &self.delimiter
+    }
+
+    pub fn is_multi_byte_delimiter(&self) -> bool {
+        self.delimiter.len() > 1
+    }
+
+    pub fn quote(&self) -> Option<u8> {
+        self.quote
+    }
+
+    pub fn set_quote(&mut self, quote: Option<u8>) {
+        self.quote = quote
+    }
+
+    pub fn escape(&self) -> EscapeStyle {
+        self.escape
+    }
+
+    pub fn set_escape(&mut self, escape: EscapeStyle) {
+        self.escape = escape
+    }
+
+    pub fn comment(&self) -> Option<u8> {
+        self.comment
+    }
+
+    pub fn set_comment(&mut self, comment: Option<u8>) {
+        self.comment = comment
+    }
+
+    pub fn trim(&self) -> TrimMode {
+        self.trim
+    }
+
+    pub fn set_trim(&mut self, trim: TrimMode) {
+        self.trim = trim
+    }
+
+    pub fn flexible(&self) -> bool {
+        self.flexible
+    }
+
+    pub fn set_flexible(&mut self, flexible: bool) {
+        self.flexible = flexible
+    }
+
+    pub fn skip_lines(&self) -> usize {
+        self.skip_lines
+    }
+
+    pub fn set_skip_lines(&mut self, skip_lines: usize) {
+        self.skip_lines = skip_lines
+    }
+
+    pub fn null_values(&self) -> &Vec<String> {
+        &self.null_values
+    }
+
+    pub fn set_null_values(&mut self, null_values: Vec<String>) {
+        self.null_values = null_values
+    }
+
+    pub fn bad_row_policy(&self) -> BadRowPolicy {
+        self.bad_row_policy
+    }
+
+    pub fn set_bad_row_policy(&mut self, bad_row_policy: BadRowPolicy) {
+        self.bad_row_policy = bad_row_policy
+    }
+
+    pub fn rejected_rows(&self) -> &Option<RejectedRows> {
+        &self.rejected_rows
+    }
+
+    pub fn set_rejected_rows(&mut self, rejected_rows: Option<RejectedRows>) {
+        self.rejected_rows = rejected_rows
     }
 
     pub fn timestamp_config(&mut self) -> &mut TimestampConfig {
@@ -95,11 +366,20 @@ impl CSVInputConfig {
 
 impl CSVOutputConfig {
     pub fn new(delimiter: &str, print_timestamp: bool) -> Self {
-        CSVOutputConfig { delimiter: delimiter.to_string(), print_timestamp }
+        let hint_timestamp_format = Some(timestamp_util::DEFAULT_OUTPUT_FORMAT.to_string());
+        CSVOutputConfig {
+            delimiter: delimiter.to_string(),
+            print_timestamp,
+            hint_timestamp_format,
+            quote: b'"',
+            quote_style: QuoteStyle::Necessary,
+            line_terminator: LineTerminator::Lf,
+            null_value: String::new()
+        }
     }
 
     pub fn new_default() -> Self {
-        CSVOutputConfig { delimiter: DELIMITER_DEFAULT.to_string(), print_timestamp: true }
+        CSVOutputConfig::new(DELIMITER_DEFAULT, true)
     }
 
     pub fn delimiter(&self) -> &String {
@@ -109,10 +389,50 @@ impl CSVOutputConfig {
     pub fn print_timestamp(&self) -> bool {
         self.print_timestamp
     }
+
+    pub fn hint_timestamp_format(&self) -> &Option<String> {
+        &self.hint_timestamp_format
+    }
+
+    pub fn set_hint_timestamp_format(&mut self, format: Option<String>) {
+        self.hint_timestamp_format = format
+    }
+
+    pub fn quote(&self) -> u8 {
+        self.quote
+    }
+
+    pub fn set_quote(&mut self, quote: u8) {
+        self.quote = quote
+    }
+
+    pub fn quote_style(&self) -> QuoteStyle {
+        self.quote_style
+    }
+
+    pub fn set_quote_style(&mut self, quote_style: QuoteStyle) {
+        self.quote_style = quote_style
+    }
+
+    pub fn line_terminator(&self) -> LineTerminator {
+        self.line_terminator
+    }
+
+    pub fn set_line_terminator(&mut self, line_terminator: LineTerminator) {
+        self.line_terminator = line_terminator
+    }
+
+    pub fn null_value(&self) -> &String {
+        &self.null_value
+    }
+
+    pub fn set_null_value(&mut self, null_value: String) {
+        self.null_value = null_value
+    }
 }
 
 impl fmt::Debug for CSVInputConfig {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "delimiter: {:?}, has headers: {:?}", self.delimiter, self.has_header)
+        write!(f, "delimiter: {:?}, has headers: {:?}", String::from_utf8_lossy(&self.delimiter), self.has_header)
     }
 }
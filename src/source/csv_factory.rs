@@ -6,6 +6,7 @@ use crate::error::CliResult;
 use crate::source::csv_configs::CSVInputConfig;
 use crate::source::csv_source::CSVSource;
 use crate::source::source_factory::SourceFactory;
+use crate::transport::transport_factory::SeekableRead;
 
 pub struct CSVFactory {
     pub csv_input_config: CSVInputConfig
@@ -25,4 +26,8 @@ impl SourceFactory for CSVFactory {
     fn create_source(&mut self, reader: Box<dyn io::Read>) -> CliResult<Box<dyn Source>> {
         Ok(Box::new(CSVSource::new(reader, &self.csv_input_config)?))
     }
+
+    fn create_seekable_source(&mut self, reader: Box<dyn SeekableRead>) -> CliResult<Box<dyn Source>> {
+        Ok(Box::new(CSVSource::new_seekable(reader, &self.csv_input_config)?))
+    }
 }
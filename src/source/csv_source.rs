@@ -1,29 +1,53 @@
-use std::io;
+use std::io::{self, BufRead, Seek, SeekFrom};
 
-use chrono::{NaiveDateTime, TimeZone};
 use csv;
 use csv::Trim;
 
 use crate::chopper::chopper::Source;
 use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
 use crate::error::{CliResult, Error};
-use crate::source::csv_configs::{CSVInputConfig, TimestampCol};
+use crate::source::csv_configs::{self, BadRowPolicy, CSVInputConfig, EscapeStyle, TimestampCol, TrimMode};
+use crate::transport::deadline;
+use crate::util::csv_util::DialectReader;
 use crate::util::timestamp_util;
 
 pub struct CSVSource<R> {
-    reader: csv::Reader<R>,
+    reader: csv::Reader<DialectReader<R>>,
     header: Header,
     csv_config: CSVInputConfig,
     next_row: Row,
     has_next_row: bool,
+    data_offset: u64,
+    // rows dropped by the skip bad row policy
+    skipped_rows: usize,
+    // names of all fields of a record, including dropped timestamp columns
+    field_names: Vec<String>,
+    field_count: usize,
+    // indexes of record fields kept in the row
+    columns: Vec<usize>,
+}
+
+impl <R: io::Read + io::Seek> CSVSource<R> {
+    /// Over a random access input, which can skip to the begin timestamp.
+    pub fn new_seekable(reader: R, csv_config: &CSVInputConfig) -> CliResult<Self> {
+        let reader = DialectReader::new_seekable(reader, csv_config.delimiter_bytes(), csv_config.skip_lines())?;
+        CSVSource::from_dialect_reader(reader, csv_config)
+    }
 }
 
 impl <R: io::Read> CSVSource<R> {
     pub fn new(reader: R, csv_config: &CSVInputConfig) -> CliResult<Self> {
-        let mut reader = csv::ReaderBuilder::new()
-            .delimiter(csv_config.delimiter())
+        let reader = DialectReader::new(reader, csv_config.delimiter_bytes(), csv_config.skip_lines())?;
+        CSVSource::from_dialect_reader(reader, csv_config)
+    }
+
+    fn from_dialect_reader(mut reader: DialectReader<R>, csv_config: &CSVInputConfig) -> CliResult<Self> {
+        // rejected rows are reported as read
+        if csv_config.rejected_rows().is_some() {
+            reader.keep_history();
+        }
+        let mut reader = reader_builder(csv_config)
             .has_headers(csv_config.has_header())
-            .trim(Trim::All)
             .from_reader(reader);
 
         // get field names if available
@@ -31,13 +55,24 @@ impl <R: io::Read> CSVSource<R> {
         if reader.has_headers() {
             let header_record = reader.headers()?;
             for i in header_record {
-                field_names.push(i.to_string());
+                field_names.push(unmap_delimiter(i, csv_config));
             }
         }
 
         // get first row and initialize next_row
-        let first_row: csv::StringRecord = reader.records().next().unwrap()?;
-        let field_count = first_row.len();
+        let first_row: csv::StringRecord = match reader.records().next() {
+            Some(r) => r?,
+            None => return Err(Error::from("CSVReader -- input has no rows"))
+        };
+        let data_offset = match first_row.position() {
+            Some(p) => p.byte(),
+            None => 0
+        };
+        // the header, if any, sets the expected number of fields
+        let field_count = match reader.has_headers() {
+            true => field_names.len(),
+            false => first_row.len()
+        };
         if !reader.has_headers() {
             // if field name is not given, assign default name - "col_x"
             for i in 0..field_count {
@@ -45,30 +80,53 @@ impl <R: io::Read> CSVSource<R> {
             }
         }
 
+        // resolve timestamp columns given by name or auto-detected
+        let mut csv_config = csv_config.clone();
+        let timestamp_col = resolve_timestamp_col(
+            csv_config.timestamp_config().timestamp_col(), &field_names, &first_row)?;
+        let timestamp_columns = match timestamp_col {
+            TimestampCol::Timestamp(i) => vec![i],
+            TimestampCol::DateAndTime(d, t) => vec![d, t],
+            _ => unreachable!()
+        };
+        if let Some(i) = timestamp_columns.iter().find(|i| **i >= field_count) {
+            return Err(Error::from(format!(
+                "CSVReader -- timestamp column {} is out of range, the input has {} fields", i, field_count)))
+        }
+        csv_config.timestamp_config().set_timestamp_col(timestamp_col);
+
+        // columns of the record kept in the output
+        let drop_columns = csv_config.timestamp_config().drop_columns();
+        let columns: Vec<usize> = (0..field_count)
+            .filter(|i| !drop_columns || !timestamp_columns.contains(i))
+            .collect();
+
         let timestamp: Nanos = 0;
-        let field_values: Vec<FieldValue> = vec![FieldValue::None; field_count];
+        let field_values: Vec<FieldValue> = vec![FieldValue::None; columns.len()];
         let next_row = Row { timestamp, field_values };
-        let field_types: Vec<FieldType> = vec![FieldType::String; field_count];
-        let header: Header = Header::new(field_names, field_types);
-        let csv_config = csv_config.clone();
+        let field_types: Vec<FieldType> = vec![FieldType::String; columns.len()];
+        let header: Header = Header::new(
+            columns.iter().map(|i| field_names[*i].clone()).collect(), field_types);
 
-        let mut csv_reader = CSVSource { reader, header, csv_config, next_row, has_next_row: true };
+        let mut csv_reader = CSVSource {
+            reader, header, csv_config, next_row, has_next_row: true, data_offset, skipped_rows: 0,
+            field_names, field_count, columns };
+        let first_row = match csv_reader.accept(&first_row)? {
+            true => first_row,
+            false => match csv_reader.next_record()? {
+                Some(r) => r,
+                None => return Err(Error::from("CSVReader -- input has no valid rows"))
+            }
+        };
 
         // update timestamp format
-        let ts = csv_reader.get_timestamp(&first_row);
-        let timestamp = timestamp_util::complete_timestamp(ts);
-
         if csv_reader.csv_config.timestamp_config().timestamp_fmt().is_none() {
-            for fmt in timestamp_util::DATE_TIME_FORMATS.iter() {
-                if NaiveDateTime::parse_from_str(timestamp.as_ref(), fmt.as_ref()).is_ok() {
-                    csv_reader.csv_config.timestamp_config().set_timestamp_fmt(fmt.clone());
-                }
-            }
-        }
-        if csv_reader.csv_config.timestamp_config().timestamp_fmt().is_none() {
-            if timestamp.parse::<Nanos>().is_err() {
-                return Err(Error::from
-                    (format!("Cannot parse timestamp. Please provide format for parsing.")))
+            let ts = csv_reader.get_timestamp(&first_row);
+            match timestamp_format(&ts) {
+                Some(Some(fmt)) => csv_reader.csv_config.timestamp_config().set_timestamp_fmt(fmt),
+                Some(None) => (),
+                None => return Err(Error::from
+                    ("Cannot parse timestamp. Please provide format for parsing."))
             }
         }
 
@@ -78,20 +136,85 @@ impl <R: io::Read> CSVSource<R> {
         Ok(csv_reader)
     }
 
+    // reads the next record kept by the bad row policy
+    fn next_record(&mut self) -> CliResult<Option<csv::StringRecord>> {
+        loop {
+            let record = match self.reader.records().next() {
+                Some(r) => r?,
+                None => return Ok(None)
+            };
+            if self.accept(&record)? {
+                return Ok(Some(record))
+            }
+        }
+    }
+
+    // checks the record's field count against the header and applies the bad row policy;
+    // returns false if the record is skipped
+    fn accept(&mut self, record: &csv::StringRecord) -> CliResult<bool> {
+        let start = record.position().map_or(0, |p| p.byte());
+        self.reader.get_mut().discard_history(start);
+        let expected = self.field_count;
+        let found = record.len();
+        let kept = match self.csv_config.bad_row_policy() {
+            _ if found == expected || self.csv_config.flexible() => true,
+            BadRowPolicy::Pad => found < expected,
+            BadRowPolicy::Truncate => found > expected,
+            _ => false
+        };
+        if kept {
+            return Ok(true)
+        }
+
+        // csv line numbers start after the skipped lines
+        let line = record.position().map_or(0, |p| p.line()) + self.csv_config.skip_lines() as u64;
+        let reason = format!("found {} fields, expected {}", found, expected);
+        if let Some(rejected_rows) = self.csv_config.rejected_rows() {
+            // the record as read, up to the start of the next one, without its line terminator
+            let end = self.reader.position().byte();
+            let raw = self.reader.get_ref().history(start, end).unwrap_or_default();
+            let raw = String::from_utf8_lossy(raw);
+            let raw = raw.trim_end_matches(['\r', '\n']);
+            rejected_rows.write(line, &reason, &unmap_delimiter(raw, &self.csv_config))?;
+        }
+        match self.csv_config.bad_row_policy() {
+            BadRowPolicy::Skip => {
+                self.skipped_rows += 1;
+                Ok(false)
+            }
+            _ => Err(Error::from(format!("CSVReader -- bad row at line {}: {}", line, reason)))
+        }
+    }
+
+    pub fn skipped_rows(&self) -> usize {
+        self.skipped_rows
+    }
+
+    // rows shorter than the header are padded with nulls, longer ones are cut to the header
     fn update_row(&mut self, next_record: csv::StringRecord) -> CliResult<()> {
-        for i in 0..next_record.len() {
-            self.next_row.field_values[i]
-                = FieldValue::String(next_record.get(i).unwrap().to_string());
+        for (j, i) in self.columns.iter().enumerate() {
+            self.next_row.field_values[j] = match next_record.get(*i) {
+                Some(v) if !self.csv_config.null_values().iter().any(|n| n == v) =>
+                    FieldValue::String(unmap_delimiter(v, &self.csv_config)),
+                _ => FieldValue::None
+            };
         }
 
-        let ts = self.get_timestamp(&next_record);
+        self.next_row.timestamp = self.parse_timestamp(&next_record)?;
+        Ok(())
+    }
+
+    fn parse_timestamp(&mut self, record: &csv::StringRecord) -> CliResult<Nanos> {
+        let ts = self.get_timestamp(record);
         let timestamp = timestamp_util::complete_timestamp(ts);
 
         // parse timestamp into Nanos
-        self.next_row.timestamp = match self.csv_config.timestamp_config().timestamp_fmt() {
+        let nanos = match self.csv_config.timestamp_config().timestamp_fmt() {
             Some(fmt) => {
-                let naive_dt = NaiveDateTime::parse_from_str(timestamp.as_ref(), fmt.as_ref())?;
-                self.csv_config.timestamp_config().timezone().from_local_datetime(&naive_dt).unwrap().timestamp() as Nanos
+                let fmt = fmt.clone();
+                let timestamp_config = self.csv_config.timestamp_config();
+                timestamp_util::parse_datetime(
+                    &timestamp, &fmt, timestamp_config.timezone(), timestamp_config.dst_policy())? as Nanos
             },
             None => {
                 match timestamp.parse::<Nanos>() {
@@ -101,34 +224,244 @@ impl <R: io::Read> CSVSource<R> {
                 }
             }
         };
-        Ok(())
+        Ok(nanos)
     }
 
     fn get_timestamp(&mut self, record: &csv::StringRecord) -> String {
         match self.csv_config.timestamp_config().timestamp_col() {
-            TimestampCol::Timestamp(i) => record.get(*i).unwrap().to_string(),
+            TimestampCol::Timestamp(i) => record.get(*i).unwrap_or_default().to_string(),
             TimestampCol::DateAndTime(d, t) => {
-                let date = record.get(*d).unwrap();
-                let time = record.get(*t).unwrap();
+                let date = record.get(*d).unwrap_or_default();
+                let time = record.get(*t).unwrap_or_default();
                 format!("{}{}", date, time)
             }
+            // resolved when the source is created
+            _ => unreachable!()
         }
     }
 
+    fn properties(&self) -> Vec<(String, String)> {
+        let mut csv_config = self.csv_config.clone();
+        let field_names = &self.field_names;
+        let timestamp_column = match csv_config.timestamp_config().timestamp_col() {
+            TimestampCol::Timestamp(i) => format!("{} ({})", i, field_names[*i]),
+            TimestampCol::DateAndTime(d, t) =>
+                format!("{} ({}) + {} ({})", d, field_names[*d], t, field_names[*t]),
+            _ => unreachable!()
+        };
+        let timestamp_format = match csv_config.timestamp_config().timestamp_fmt() {
+            Some(fmt) => fmt.clone(),
+            None => "epoch seconds".to_string()
+        };
+        let quote = match csv_config.quote() {
+            Some(q) => format!("{:?}", q as char),
+            None => "none".to_string()
+        };
+        let mut properties = vec![
+            ("delimiter".to_string(), format!("{:?}", String::from_utf8_lossy(csv_config.delimiter_bytes()))),
+            ("has_header".to_string(), csv_config.has_header().to_string()),
+            ("quote".to_string(), quote),
+            ("escape".to_string(), format!("{:?}", csv_config.escape())),
+            ("trim".to_string(), format!("{:?}", csv_config.trim())),
+            ("timestamp_column".to_string(), timestamp_column),
+            ("timestamp_format".to_string(), timestamp_format),
+            ("timezone".to_string(), csv_config.timestamp_config().timezone().to_string())];
+        if let Some(c) = csv_config.comment() {
+            properties.push(("comment".to_string(), format!("{:?}", c as char)));
+        }
+        if csv_config.flexible() {
+            properties.push(("flexible".to_string(), true.to_string()));
+        } else {
+            properties.push(("bad_rows".to_string(), format!("{:?}", csv_config.bad_row_policy())));
+        }
+        if csv_config.skip_lines() > 0 {
+            properties.push(("skip_lines".to_string(), csv_config.skip_lines().to_string()));
+        }
+        if !csv_config.null_values().is_empty() {
+            properties.push(("null_values".to_string(), format!("{:?}", csv_config.null_values())));
+        }
+        properties
+    }
+
     fn next_row(&mut self) -> CliResult<Option<Row>> {
         if !self.has_next_row {
             return Ok(None)
         }
 
         let current_row = self.next_row.clone();
-        match self.reader.records().next() {
-            Some(r) => self.update_row(r?)?,
-            None => self.has_next_row = false,
+        match self.next_record() {
+            Ok(Some(r)) => self.update_row(r)?,
+            Ok(None) => self.has_next_row = false,
+            // cut off at the deadline, maybe within a record
+            Err(Error::Io(ref e)) if deadline::is_deadline_passed(e) => self.has_next_row = false,
+            Err(e) => return Err(e)
         }
         Ok(Some(current_row))
     }
 }
 
+impl <R: io::Read> CSVSource<R> {
+    /// Positions the reader at the first row with timestamp >= `begin` by bisecting on
+    /// byte offsets. Relies on rows being time ordered and not spanning multiple lines;
+    /// if a line met on the way is not a row, e.g. within a quoted field spanning lines,
+    /// the reader reads on from where it was instead. Does nothing unless the input is seekable.
+    pub fn seek(&mut self, begin: Nanos) -> CliResult<()> {
+        if !self.reader.get_ref().is_seekable() {
+            return Ok(())
+        }
+        if self.next_row.timestamp >= begin || !self.has_next_row {
+            return Ok(())
+        }
+        let resume = self.reader.position().clone();
+        let end = self.reader.get_mut().seek(SeekFrom::End(0))?;
+        let mut lo = self.data_offset;
+        let mut hi = end;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.read_line_from(mid) {
+                Ok(Some((_, line_end, timestamp))) if timestamp < begin => lo = line_end,
+                Ok(_) => hi = mid,
+                Err(Error::Io(e)) => return Err(Error::Io(e)),
+                // not a row; seeking only saves reading, so read on from where the reader was
+                Err(_) => return self.seek_position(resume)
+            }
+        }
+
+        // resynchronize the csv reader to the start of the row found
+        let mut position = csv::Position::new();
+        position.set_byte(lo);
+        self.seek_position(position)?;
+        match self.next_record() {
+            Ok(Some(r)) if self.parse_timestamp(&r).is_ok() => self.update_row(r)?,
+            Ok(None) => self.has_next_row = false,
+            Err(Error::Io(e)) => return Err(Error::Io(e)),
+            // not a row start after all
+            _ => self.seek_position(resume)?
+        }
+        Ok(())
+    }
+
+    fn seek_position(&mut self, position: csv::Position) -> CliResult<()> {
+        self.reader.seek_raw(SeekFrom::Start(position.byte()), position)?;
+        Ok(())
+    }
+
+    // find the first line starting at or after the offset;
+    // returns its start and end offsets and its timestamp
+    fn read_line_from(&mut self, offset: u64) -> CliResult<Option<(u64, u64, Nanos)>> {
+        let data_offset = self.data_offset;
+        let mut reader = io::BufReader::new(self.reader.get_mut());
+        let mut line_start = offset;
+        let mut line: Vec<u8> = Vec::new();
+        if offset > data_offset {
+            // skip the rest of the line the offset falls into
+            reader.seek(SeekFrom::Start(offset - 1))?;
+            line_start = offset - 1 + reader.read_until(b'\n', &mut line)? as u64;
+            line.clear();
+        } else {
+            reader.seek(SeekFrom::Start(offset))?;
+        }
+        loop {
+            let line_len = reader.read_until(b'\n', &mut line)? as u64;
+            if line_len == 0 {
+                return Ok(None)
+            }
+
+            let mut line_reader = reader_builder(&self.csv_config)
+                .has_headers(false)
+                .from_reader(line.as_slice());
+            match line_reader.records().next() {
+                Some(r) => {
+                    let timestamp = self.parse_timestamp(&r?)?;
+                    return Ok(Some((line_start, line_start + line_len, timestamp)))
+                }
+                // a comment or blank line, try the next one
+                None => {
+                    line_start += line_len;
+                    line.clear();
+                }
+            }
+        }
+    }
+}
+
+fn resolve_timestamp_col(timestamp_col: &TimestampCol,
+                         field_names: &[String],
+                         first_row: &csv::StringRecord) -> CliResult<TimestampCol> {
+    let index_of = |name: &str| match field_names.iter().position(|n| n == name) {
+        Some(i) => Ok(i),
+        None => Err(Error::from(format!("CSVReader -- no timestamp column named {}", name)))
+    };
+    match timestamp_col {
+        TimestampCol::TimestampName(name) => Ok(TimestampCol::Timestamp(index_of(name)?)),
+        TimestampCol::DateAndTimeNames(date, time) =>
+            Ok(TimestampCol::DateAndTime(index_of(date)?, index_of(time)?)),
+        TimestampCol::Auto => detect_timestamp_col(field_names, first_row),
+        col => Ok(col.clone())
+    }
+}
+
+// picks the first column with a typical timestamp name whose value parses,
+// otherwise the first column holding a date time
+fn detect_timestamp_col(field_names: &[String], first_row: &csv::StringRecord) -> CliResult<TimestampCol> {
+    for name in csv_configs::TIMESTAMP_COL_NAMES.iter() {
+        let column = field_names.iter().position(|n| n.eq_ignore_ascii_case(name));
+        if let Some(i) = column {
+            if first_row.get(i).is_some_and(|v| timestamp_format(v).is_some()) {
+                return Ok(TimestampCol::Timestamp(i))
+            }
+        }
+    }
+    for (i, value) in first_row.iter().enumerate() {
+        if let Some(Some(_)) = timestamp_format(value) {
+            return Ok(TimestampCol::Timestamp(i))
+        }
+    }
+    Err(Error::from("CSVReader -- cannot detect the timestamp column, please specify it"))
+}
+
+// the format a timestamp value parses with: Some(Some(fmt)) for a date time,
+// Some(None) for epoch seconds, None if it does not parse
+fn timestamp_format(value: &str) -> Option<Option<String>> {
+    let timestamp = timestamp_util::complete_timestamp(value.to_string());
+    match timestamp_util::detect_format(&timestamp) {
+        Some(fmt) => Some(Some(fmt.clone())),
+        None if timestamp.parse::<Nanos>().is_ok() => Some(None),
+        None => None
+    }
+}
+
+fn reader_builder(csv_config: &CSVInputConfig) -> csv::ReaderBuilder {
+    let mut builder = csv::ReaderBuilder::new();
+    builder.delimiter(csv_config.delimiter())
+        .comment(csv_config.comment())
+        // row shapes are checked against the bad row policy instead
+        .flexible(true)
+        .trim(match csv_config.trim() {
+            TrimMode::None => Trim::None,
+            TrimMode::Headers => Trim::Headers,
+            TrimMode::Fields => Trim::Fields,
+            TrimMode::All => Trim::All,
+        });
+    match csv_config.quote() {
+        Some(q) => { builder.quote(q); },
+        None => { builder.quoting(false); }
+    }
+    if csv_config.escape() == EscapeStyle::Backslash {
+        builder.escape(Some(b'\\')).double_quote(false);
+    }
+    builder
+}
+
+// restore a multi-byte delimiter inside a quoted value
+fn unmap_delimiter(value: &str, csv_config: &CSVInputConfig) -> String {
+    if !csv_config.is_multi_byte_delimiter() {
+        return value.to_string()
+    }
+    let placeholder = csv_configs::MULTI_BYTE_DELIMITER_PLACEHOLDER as char;
+    value.replace(placeholder, &String::from_utf8_lossy(csv_config.delimiter_bytes()))
+}
+
 impl <R: io::Read> Source for CSVSource<R> {
     fn header(&self) -> &Header {
         &self.header
@@ -141,4 +474,77 @@ impl <R: io::Read> Source for CSVSource<R> {
     fn has_native_timestamp_column(&self) -> bool {
         false
     }
+
+    fn seek(&mut self, begin: Nanos) -> CliResult<()> {
+        self.seek(begin)
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        self.properties()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        match self.skipped_rows {
+            0 => Vec::new(),
+            n => vec![format!("CSVReader -- skipped {} bad rows", n)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::csv_configs::TimestampConfig;
+
+    const START: Nanos = 1_500_000_000;
+
+    fn create_source(rows: usize) -> CSVSource<io::Cursor<Vec<u8>>> {
+        let mut data = "time,value\n".to_string();
+        for i in 0..rows {
+            data.push_str(format!("{},value_{}\n", START + i as Nanos * 10, i).as_str());
+        }
+        let config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+        CSVSource::new_seekable(io::Cursor::new(data.into_bytes()), &config).unwrap()
+    }
+
+    #[test]
+    fn test_seek_multi_line_fields() {
+        // every other line is within a quoted field
+        let mut data = "time,value\n".to_string();
+        for i in 0..1000 {
+            data.push_str(format!("{},\"value\n{}\"\n", START + i as Nanos * 10, i).as_str());
+        }
+        let config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+        for begin in vec![START + 5005, START + 9990, START + 10000] {
+            let mut source = CSVSource::new_seekable(io::Cursor::new(data.clone().into_bytes()), &config).unwrap();
+            source.seek(begin).unwrap();
+            let mut count = 0;
+            while let Some(row) = source.next_row().unwrap() {
+                if row.timestamp >= begin {
+                    count += 1;
+                }
+            }
+            assert_eq!(count, (0..1000).filter(|i| START + i * 10 >= begin).count());
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut source = create_source(1000);
+        source.seek(START + 5005).unwrap();
+        assert_eq!(source.next_row().unwrap().unwrap().timestamp, START + 5010);
+        assert_eq!(source.next_row().unwrap().unwrap().timestamp, START + 5020);
+
+        let mut source = create_source(1000);
+        source.seek(START + 5000).unwrap();
+        assert_eq!(source.next_row().unwrap().unwrap().timestamp, START + 5000);
+
+        let mut source = create_source(1000);
+        source.seek(START).unwrap();
+        assert_eq!(source.next_row().unwrap().unwrap().timestamp, START);
+
+        let mut source = create_source(1000);
+        source.seek(START + 10000).unwrap();
+        assert!(source.next_row().unwrap().is_none());
+    }
 }
@@ -0,0 +1,101 @@
+use crate::error::{CliResult, Error};
+use crate::util::dc_util::{self, BlockCompression, TimestampUnit};
+
+#[derive(Clone)]
+pub struct DCOutputConfig {
+    user_header: Option<Vec<u8>>,
+    version: u16,
+    // v3 only
+    timestamp_unit: TimestampUnit,
+    compression: BlockCompression,
+}
+
+impl DCOutputConfig {
+    pub fn new(user_header: Option<Vec<u8>>) -> Self {
+        DCOutputConfig {
+            user_header,
+            version: dc_util::VERSION,
+            timestamp_unit: TimestampUnit::Nanos,
+            compression: BlockCompression::Deflate
+        }
+    }
+
+    pub fn new_default() -> Self {
+        DCOutputConfig::new(None)
+    }
+
+    /// User header to write instead of the one carried by the input, if any.
+    pub fn user_header(&self) -> Option<&[u8]> {
+        self.user_header.as_deref()
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u16) -> CliResult<()> {
+        if !dc_util::SUPPORTED_VERSIONS.contains(&version) {
+            return Err(Error::from(format!("DCOutputConfig -- unsupported version - {}, supported versions are {:?}",
+                                           version, dc_util::SUPPORTED_VERSIONS)))
+        }
+        self.version = version;
+        Ok(())
+    }
+
+    pub fn timestamp_unit(&self) -> TimestampUnit {
+        self.timestamp_unit
+    }
+
+    pub fn set_timestamp_unit(&mut self, timestamp_unit: TimestampUnit) {
+        self.timestamp_unit = timestamp_unit
+    }
+
+    pub fn compression(&self) -> BlockCompression {
+        self.compression
+    }
+
+    pub fn set_compression(&mut self, compression: BlockCompression) {
+        self.compression = compression
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidUtf8 {
+    Error,
+    Lossy,
+}
+
+impl InvalidUtf8 {
+    pub fn parse(policy: &str) -> CliResult<Self> {
+        match policy {
+            "error" => Ok(InvalidUtf8::Error),
+            "lossy" => Ok(InvalidUtf8::Lossy),
+            _ => Err(Error::from(format!("DCInputConfig -- unknown invalid utf-8 policy - {}", policy)))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DCInputConfig {
+    // stop reading at the first corrupted or truncated row instead of failing
+    lenient: bool,
+    invalid_utf8: InvalidUtf8,
+}
+
+impl DCInputConfig {
+    pub fn new(lenient: bool, invalid_utf8: InvalidUtf8) -> Self {
+        DCInputConfig { lenient, invalid_utf8 }
+    }
+
+    pub fn new_default() -> Self {
+        DCInputConfig { lenient: false, invalid_utf8: InvalidUtf8::Error }
+    }
+
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub fn invalid_utf8(&self) -> InvalidUtf8 {
+        self.invalid_utf8
+    }
+}
@@ -3,9 +3,18 @@ use std::path::Path;
 
 use crate::chopper::chopper::Source;
 use crate::error::CliResult;
+use crate::source::dc_configs::DCInputConfig;
 use crate::source::{dc_source::DCSource, source_factory::SourceFactory};
 
-pub struct DCFactory;
+pub struct DCFactory {
+    pub dc_input_config: DCInputConfig
+}
+
+impl DCFactory {
+    pub fn new(dc_input_config: DCInputConfig) -> Self {
+        DCFactory { dc_input_config }
+    }
+}
 
 impl SourceFactory for DCFactory {
     fn can_create_from(&self, path: &Path) -> bool {
@@ -13,6 +22,6 @@ impl SourceFactory for DCFactory {
     }
 
     fn create_source(&mut self, reader: Box<dyn io::Read>) -> CliResult<Box<dyn Source>> {
-        Ok(Box::new(DCSource::new(reader)?))
+        Ok(Box::new(DCSource::new_with_config(reader, &self.dc_input_config)?))
     }
 }
@@ -1,13 +1,16 @@
 use std::collections::HashMap;
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read};
 use std::str;
 
 use byteorder::{BigEndian, ReadBytesExt};
+use flate2::read::DeflateDecoder;
 
 use crate::chopper::chopper::Source;
-use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+use crate::chopper::types::{DisplayHint, FieldMetadata, FieldType, FieldValue, Header, Row};
 use crate::error::{CliResult, Error};
-use crate::util::dc_util;
+use crate::source::dc_configs::{DCInputConfig, InvalidUtf8};
+use crate::transport::deadline;
+use crate::util::dc_util::{self, BlockCompression, Footer, TimestampUnit};
 
 // map for field types
 lazy_static! {
@@ -15,17 +18,45 @@ lazy_static! {
                                       = dc_util::creat_field_string_map_name();
 }
 
+// counts the bytes read from the underlying reader, to report offsets of corrupted data
+struct CountingReader<R> {
+    reader: R,
+    count: u64,
+}
+
+impl <R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, into: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(into)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
 pub struct DCSource<R> {
-    reader: io::BufReader<R>,
+    reader: io::BufReader<CountingReader<R>>,
+    dc_input_config: DCInputConfig,
+    // set once a corrupted row stops a lenient read
+    stopped: Option<String>,
     header: Header,
     field_count: usize,
     bitset_byte_count: usize,
     current_row: Row,
+    version: u16,
+    timestamp_unit: TimestampUnit,
+    compression: BlockCompression,
+    // v3 rows are read from the current block
+    block: io::Cursor<Vec<u8>>,
+    block_rows: u32,
+    footer: Option<Footer>,
 }
 
 impl <R: io::Read> DCSource<R> {
     pub fn new(reader: R) -> CliResult<Self> {
-        let mut reader = io::BufReader::new(reader);
+        DCSource::new_with_config(reader, &DCInputConfig::new_default())
+    }
+
+    pub fn new_with_config(reader: R, dc_input_config: &DCInputConfig) -> CliResult<Self> {
+        let mut reader = io::BufReader::new(CountingReader { reader, count: 0 });
 
         let magic_num = reader.read_u64::<BigEndian>()?;
         if &magic_num != &dc_util::MAGIC_NUM {
@@ -33,103 +64,289 @@ impl <R: io::Read> DCSource<R> {
         }
 
         let version = reader.read_u16::<BigEndian>()?;
-        if &version != &dc_util::VERSION {
-            return Err(Error::from(format!("DCReader -- wrong version - {}", version)))
+        if !dc_util::SUPPORTED_VERSIONS.contains(&version) {
+            return Err(Error::from(format!("DCReader -- unsupported version - {}, supported versions are {:?}",
+                                           version, dc_util::SUPPORTED_VERSIONS)))
         }
 
-        // skip user given data
+        // user given data
         let user_header_size = reader.read_u32::<BigEndian>()?;
-        for _i in 0..user_header_size as usize {
-            reader.read_u8()?;
-        }
+        let user_header = dc_util::read_bytes(&mut reader, user_header_size as u64)?;
+
+        let (timestamp_unit, compression) = match version {
+            dc_util::VERSION_3 => (TimestampUnit::from_code(reader.read_u8()?)?,
+                                   BlockCompression::from_code(reader.read_u8()?)?),
+            _ => (TimestampUnit::Nanos, BlockCompression::None)
+        };
 
         let map_field_string = &FIELD_STRING_MAP_NAME;
         let field_count = reader.read_u32::<BigEndian>()? as usize;
         let bitset_byte_count = dc_util::get_bitset_bytes(field_count);
 
-        // field descriptor (header)
-        let mut field_names: Vec<String> = Vec::with_capacity(field_count);
-        let mut field_types: Vec<FieldType> = Vec::with_capacity(field_count);
-        let mut field_values: Vec<FieldValue> = Vec::with_capacity(field_count);
+        // field descriptor (header); the vectors grow as fields are read, as the count may be corrupt
+        let mut field_names: Vec<String> = Vec::new();
+        let mut field_types: Vec<FieldType> = Vec::new();
+        let mut field_values: Vec<FieldValue> = Vec::new();
+        let mut field_metadata: Vec<FieldMetadata> = Vec::new();
         for i in 0..field_count {
-            let field_descriptor = dc_util::FieldDescriptor::new(&mut reader)?;
+            let field_descriptor = match version {
+                dc_util::VERSION_3 => dc_util::FieldDescriptor::new_v3(&mut reader)?,
+                _ => dc_util::FieldDescriptor::new(&mut reader)?
+            };
             let mut name = field_descriptor.get_name().to_string();
             // if field name is not given, assign default name - "col_x"
             if name.is_empty() {
                 name = format!("col_{}", i);
             }
+            let field_type = match map_field_string.get(field_descriptor.get_type_string()) {
+                Some(t) => t.clone(),
+                None => return Err(Error::from(format!("DCReader -- unknown field type - {} for field {}",
+                                                       field_descriptor.get_type_string(), name)))
+            };
             field_names.push(name);
-            field_types.push(map_field_string.get(field_descriptor.get_type_string()).unwrap().clone());
+            field_types.push(field_type);
             field_values.push(FieldValue::None);
+            let display_hint = *field_descriptor.get_display_hint();
+            field_metadata.push(FieldMetadata { display_hint, ..FieldMetadata::default() });
+        }
+        if version == dc_util::VERSION_3 {
+            Self::read_field_metadata(&mut reader, &mut field_metadata)?;
         }
 
         // Header
-        let header: Header = Header::new(field_names, field_types);
+        let mut header: Header = Header::new(field_names, field_types);
+        *header.field_metadata_mut() = field_metadata;
+        header.set_user_header(Some(user_header));
 
         // Row
         let timestamp = 0 as u64;
         let current_row = Row { timestamp, field_values };
 
-        Ok(DCSource { reader, header, field_count, bitset_byte_count, current_row })
+        Ok(DCSource {
+            reader,
+            dc_input_config: dc_input_config.clone(),
+            stopped: None,
+            header,
+            field_count,
+            bitset_byte_count,
+            current_row,
+            version,
+            timestamp_unit,
+            compression,
+            block: io::Cursor::new(Vec::new()),
+            block_rows: 0,
+            footer: None,
+        })
+    }
+
+    /// Footer of a v3 file, available once all rows are read.
+    pub fn footer(&self) -> Option<&Footer> {
+        self.footer.as_ref()
+    }
+
+    fn read_field_metadata<T: io::Read>(reader: &mut T, field_metadata: &mut Vec<FieldMetadata>) -> CliResult<()> {
+        // the section is read whole, so fields added after a known count can be skipped
+        let size = reader.read_u32::<BigEndian>()?;
+        let mut section = io::Cursor::new(dc_util::read_bytes(reader, size as u64)?);
+        for metadata in field_metadata.iter_mut() {
+            let entry_count = section.read_u32::<BigEndian>()?;
+            for _i in 0..entry_count {
+                let key = dc_util::read_sized_string(&mut section)?;
+                let value = dc_util::read_sized_string(&mut section)?;
+                match key.as_str() {
+                    "display_hint" if value == "timestamp" => metadata.display_hint = DisplayHint::Timestamp,
+                    "display_hint" => metadata.display_hint = DisplayHint::None,
+                    _ => metadata.properties.push((key, value))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // offset in the input of the next byte to be parsed
+    fn offset(&self) -> u64 {
+        self.reader.get_ref().count - self.reader.buffer().len() as u64
     }
 
     fn next_row(&mut self) -> CliResult<Option<Row>> {
-        match self.reader.read_u64::<BigEndian>() {
-            Ok(i) => self.current_row.timestamp = i,
-            Err(_e) => return Ok(None),
+        if self.stopped.is_some() {
+            return Ok(None)
+        }
+        let offset = self.offset();
+        let result = match self.version {
+            dc_util::VERSION_3 => self.next_row_v3(),
+            _ => self.next_row_v2()
+        };
+        match result {
+            Ok(row) => Ok(row),
+            // cut off at the deadline, maybe within a row
+            Err(Error::Io(ref e)) if deadline::is_deadline_passed(e) => Ok(None),
+            // failures of the underlying reader are not corruption
+            Err(Error::Io(ref e)) if e.kind() != io::ErrorKind::UnexpectedEof => result,
+            Err(e) => {
+                let error = Self::corruption_error(e, offset);
+                if !self.dc_input_config.lenient() {
+                    return Err(error)
+                }
+                self.stopped = Some(format!("{}; stopped reading", Self::error_message(&error)));
+                Ok(None)
+            }
+        }
+    }
+
+    fn corruption_error(error: Error, offset: u64) -> Error {
+        match error {
+            Error::Io(_) => Error::from(format!("DCReader -- truncated row at byte offset {}", offset)),
+            e => Error::from(format!("{} at byte offset {}", Self::error_message(&e), offset)),
+        }
+    }
+
+    fn error_message(error: &Error) -> String {
+        match error {
+            Error::Custom(s) => s.clone(),
+            e => e.to_string()
+        }
+    }
+
+    fn next_row_v2(&mut self) -> CliResult<Option<Row>> {
+        // nothing left at a row boundary is a clean end of file
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None)
+        }
+        self.current_row.timestamp = self.reader.read_u64::<BigEndian>()?;
+        Self::read_field_values(&mut self.reader,
+                                self.header.field_types(),
+                                self.bitset_byte_count,
+                                self.field_count,
+                                self.dc_input_config.invalid_utf8(),
+                                &mut self.current_row.field_values)?;
+        Ok(Some(self.current_row.clone()))
+    }
+
+    fn next_row_v3(&mut self) -> CliResult<Option<Row>> {
+        if self.block_rows == 0 && !self.read_block()? {
+            return Ok(None)
+        }
+        self.block_rows -= 1;
+        let timestamp = self.block.read_u64::<BigEndian>()?;
+        self.current_row.timestamp = self.timestamp_unit.to_nanos(timestamp)?;
+        Self::read_field_values(&mut self.block,
+                                self.header.field_types(),
+                                self.bitset_byte_count,
+                                self.field_count,
+                                self.dc_input_config.invalid_utf8(),
+                                &mut self.current_row.field_values)?;
+        Ok(Some(self.current_row.clone()))
+    }
+
+    // returns false once the footer is reached
+    fn read_block(&mut self) -> CliResult<bool> {
+        if self.footer.is_some() {
+            return Ok(false)
+        }
+        if self.reader.fill_buf()?.is_empty() {
+            return Err(Error::from("DCReader -- missing footer"))
+        }
+        let row_count = self.reader.read_u32::<BigEndian>()?;
+        if row_count == 0 {
+            self.footer = Some(self.read_footer()?);
+            return Ok(false)
+        }
+        let raw_size = self.reader.read_u32::<BigEndian>()? as usize;
+        let stored_size = self.reader.read_u32::<BigEndian>()? as usize;
+        let stored = dc_util::read_bytes(&mut self.reader, stored_size as u64)?;
+        let raw = match self.compression {
+            BlockCompression::None => stored,
+            BlockCompression::Deflate => {
+                // one byte past the expected size is enough to tell it is wrong
+                let mut raw: Vec<u8> = Vec::new();
+                DeflateDecoder::new(stored.as_slice()).take(raw_size as u64 + 1).read_to_end(&mut raw)?;
+                raw
+            }
         };
+        if raw.len() != raw_size {
+            return Err(Error::from(format!("DCReader -- wrong block size - {}, expected {}", raw.len(), raw_size)))
+        }
+        self.block = io::Cursor::new(raw);
+        self.block_rows = row_count;
+        Ok(true)
+    }
+
+    fn read_footer(&mut self) -> CliResult<Footer> {
+        let row_count = self.reader.read_u64::<BigEndian>()?;
+        let min_timestamp = self.timestamp_unit.to_nanos(self.reader.read_u64::<BigEndian>()?)?;
+        let max_timestamp = self.timestamp_unit.to_nanos(self.reader.read_u64::<BigEndian>()?)?;
+        let magic_num = self.reader.read_u64::<BigEndian>()?;
+        if magic_num != dc_util::FOOTER_MAGIC_NUM {
+            return Err(Error::from(format!("DCReader -- wrong footer magic number - {}", magic_num)))
+        }
+        Ok(Footer { row_count, min_timestamp, max_timestamp })
+    }
 
+    fn read_field_values<T: io::Read>(reader: &mut T,
+                                      field_types: &[FieldType],
+                                      bitset_byte_count: usize,
+                                      field_count: usize,
+                                      invalid_utf8: InvalidUtf8,
+                                      field_values: &mut [FieldValue]) -> CliResult<()>
+    {
         // bitset of null values
-        let mut bitset_bytes: Vec<u8> = vec![0 as u8; self.bitset_byte_count];
+        let mut bitset_bytes: Vec<u8> = vec![0 as u8; bitset_byte_count];
         let bitset_bytes = bitset_bytes.as_mut_slice();
-        self.reader.read_exact(bitset_bytes)?;
+        reader.read_exact(bitset_bytes)?;
 
         // get non-null fields, if null put string "null"
         let mut field_index = 0 as usize;
         for i in 0..bitset_bytes.len() {
             let mut current_bitset = bitset_bytes[i];
             for _j in 0..8 {
-                self.current_row.field_values[field_index] = {
+                field_values[field_index] = {
                     if current_bitset & 1 == 0 { // not null
-                        match self.header.field_types()[field_index] {
+                        match field_types[field_index] {
                             FieldType::Boolean =>
                                 return Err(Error::from("DCReader -- boolean field type is not supported")),
-                            FieldType::Byte => FieldValue::Byte(self.reader.read_u8()?),
+                            FieldType::Byte => FieldValue::Byte(reader.read_u8()?),
                             FieldType::ByteBuf =>
                                 return Err(Error::from("DCReader -- ByteBuffer field type is not supported")),
-                            FieldType::Char => FieldValue::Char(self.reader.read_u16::<BigEndian>()?),
-                            FieldType::Double => FieldValue::Double(self.reader.read_f64::<BigEndian>()?),
-                            FieldType::Float => FieldValue::Float(self.reader.read_f32::<BigEndian>()?),
-                            FieldType::Int => FieldValue::Int(self.reader.read_i32::<BigEndian>()?),
-                            FieldType::Long => FieldValue::Long(self.reader.read_i64::<BigEndian>()?),
-                            FieldType::Short => FieldValue::Short(self.reader.read_i16::<BigEndian>()?),
-                            FieldType::String => FieldValue::String(self.read_string()?.to_owned()),
+                            FieldType::Char => FieldValue::Char(reader.read_u16::<BigEndian>()?),
+                            FieldType::Double => FieldValue::Double(reader.read_f64::<BigEndian>()?),
+                            FieldType::Float => FieldValue::Float(reader.read_f32::<BigEndian>()?),
+                            FieldType::Int => FieldValue::Int(reader.read_i32::<BigEndian>()?),
+                            FieldType::Long => FieldValue::Long(reader.read_i64::<BigEndian>()?),
+                            FieldType::Short => FieldValue::Short(reader.read_i16::<BigEndian>()?),
+                            FieldType::String => FieldValue::String(Self::read_string(reader, invalid_utf8)?),
                         }
                     } else {
                         FieldValue::None
                     }
                 };
                 field_index += 1;
-                if field_index >= self.field_count {
+                if field_index >= field_count {
                     break;
                 }
                 current_bitset = current_bitset >> 1;
             }
         }
-        Ok(Some(self.current_row.clone()))
+        Ok(())
     }
 
-    fn read_string(&mut self) -> CliResult<String> {
-        let data_size_short = self.reader.read_i16::<BigEndian>()?;
+    fn read_string<T: io::Read>(reader: &mut T, invalid_utf8: InvalidUtf8) -> CliResult<String> {
+        let data_size_short = reader.read_i16::<BigEndian>()?;
         let data_size = match data_size_short {
-            -1 => self.reader.read_u32::<BigEndian>()?,
+            -1 => reader.read_u32::<BigEndian>()?,
+            // -1 is the only negative length written; anything else is corruption
+            n if n < -1 => return Err(Error::from(format!("DCReader -- negative string length {}", n))),
             _ => data_size_short as u32,
         };
-        let mut string: Vec<u8> = vec![0; data_size as usize];
-        let string = string.as_mut_slice();
-        self.reader.read_exact(string)?;
+        let string = dc_util::read_bytes(reader, data_size as u64)?;
 
-        Ok(str::from_utf8_mut(string).unwrap().to_string())
+        match String::from_utf8(string) {
+            Ok(s) => Ok(s),
+            Err(e) => match invalid_utf8 {
+                InvalidUtf8::Lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+                InvalidUtf8::Error => Err(Error::from("DCReader -- invalid utf-8 in string value"))
+            }
+        }
     }
 }
 
@@ -151,4 +368,28 @@ impl <R: io::Read> Source for DCSource<R> {
     fn has_native_timestamp_column(&self) -> bool {
         true
     }
+
+    fn warnings(&self) -> Vec<String> {
+        self.stopped.iter().cloned().collect()
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        let user_header = self.header.user_header().unwrap_or(&[]);
+        let mut properties = vec![
+            ("dc_version".to_string(), self.version.to_string()),
+            ("user_header".to_string(), dc_util::format_bytes(user_header))];
+        if self.version == dc_util::VERSION_3 {
+            properties.push(("timestamp_unit".to_string(), format!("{:?}", self.timestamp_unit)));
+            properties.push(("compression".to_string(), format!("{:?}", self.compression)));
+        }
+        for (name, metadata) in self.header.field_names().iter().zip(self.header.field_metadata()) {
+            if metadata.display_hint != DisplayHint::None {
+                properties.push((format!("display_hint.{}", name), format!("{:?}", metadata.display_hint)));
+            }
+            for (key, value) in &metadata.properties {
+                properties.push((format!("{}.{}", key, name), value.clone()));
+            }
+        }
+        properties
+    }
 }
@@ -1,6 +1,7 @@
 pub mod csv_factory;
 pub mod csv_configs;
 pub mod csv_source;
+pub mod dc_configs;
 pub mod dc_factory;
 pub mod dc_source;
 pub mod decompress;
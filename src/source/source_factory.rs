@@ -3,8 +3,15 @@ use std::path::Path;
 
 use crate::chopper::chopper::Source;
 use crate::error::CliResult;
+use crate::transport::transport_factory::SeekableRead;
 
 pub trait SourceFactory {
     fn can_create_from(&self, path: &Path) -> bool;
     fn create_source(&mut self, reader: Box<dyn io::Read>) -> CliResult<Box<dyn Source>>;
+
+    /// Creates a source over an uncompressed random access input.
+    /// Factories whose sources cannot seek fall back to `create_source`.
+    fn create_seekable_source(&mut self, reader: Box<dyn SeekableRead>) -> CliResult<Box<dyn Source>> {
+        self.create_source(Box::new(reader))
+    }
 }
@@ -0,0 +1,143 @@
+use std::error;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Instant;
+
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Ends an input at a wall-clock deadline, even while a read is blocked waiting for data,
+/// e.g. on a stalled stream. Reads happen on a background thread; at the deadline reads
+/// fail with a deadline error, and the thread is left to finish its pending read.
+/// Unlike the end of the input, the deadline may cut a record short, so sources end
+/// cleanly on the error and drop the partly read record; see `is_deadline_passed`.
+pub struct DeadlineReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    deadline: Instant,
+    chunk: Vec<u8>,
+    // read position in chunk
+    position: usize,
+    done: bool,
+    deadline_passed: bool,
+}
+
+#[derive(Debug)]
+struct DeadlinePassed;
+
+impl fmt::Display for DeadlinePassed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "input ended at the deadline")
+    }
+}
+
+impl error::Error for DeadlinePassed {}
+
+/// Whether the error is a `DeadlineReader` reaching its deadline.
+pub fn is_deadline_passed(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<DeadlinePassed>())
+}
+
+impl DeadlineReader {
+    pub fn new(mut reader: Box<dyn Read + Send>, deadline: Instant) -> Self {
+        // one chunk ahead of the consumer
+        let (sender, chunks) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            loop {
+                let mut chunk = vec![0; CHUNK_BYTES];
+                let result = match reader.read(&mut chunk) {
+                    Ok(n) => {
+                        chunk.truncate(n);
+                        Ok(chunk)
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e)
+                };
+                let last = !matches!(&result, Ok(c) if !c.is_empty());
+                if sender.send(result).is_err() || last {
+                    return
+                }
+            }
+        });
+        DeadlineReader { chunks, deadline, chunk: Vec::new(), position: 0, done: false, deadline_passed: false }
+    }
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.chunk.len() {
+            if self.deadline_passed {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, DeadlinePassed))
+            }
+            if self.done {
+                return Ok(0)
+            }
+            let wait = self.deadline.saturating_duration_since(Instant::now());
+            match self.chunks.recv_timeout(wait) {
+                Ok(Ok(chunk)) if !chunk.is_empty() => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e)
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.deadline_passed = true;
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, DeadlinePassed))
+                }
+                // end of the input
+                Ok(Ok(_)) | Err(RecvTimeoutError::Disconnected) => {
+                    self.done = true;
+                    return Ok(0)
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    // some data, then no more data for a long time
+    struct Stalling {
+        sent: bool,
+    }
+
+    impl Read for Stalling {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.sent {
+                thread::sleep(Duration::from_secs(60));
+                return Ok(0)
+            }
+            self.sent = true;
+            buf[..4].copy_from_slice(b"a,b\n");
+            Ok(4)
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Instant::now();
+        let mut reader = DeadlineReader::new(Box::new(Stalling { sent: false }), start + Duration::from_millis(100));
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"a,b\n");
+        let error = reader.read(&mut buf).unwrap_err();
+        assert!(is_deadline_passed(&error), "{}", error);
+        assert!(is_deadline_passed(&reader.read(&mut buf).unwrap_err()));
+        assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+
+        let mut reader = DeadlineReader::new(Box::new(&b"x,y\n"[..]), start + Duration::from_secs(60));
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "x,y\n");
+    }
+}
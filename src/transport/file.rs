@@ -1,18 +1,61 @@
 use std::fs;
-use std::io;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::transport::transport_factory::TransportFactory;
+use crate::transport::transport_factory::{SeekableRead, TransportFactory};
+
+pub const FOLLOW_POLL_INTERVAL_DEFAULT: Duration = Duration::from_millis(200);
+
+/// How to keep reading a file that is still being written.
+#[derive(Clone, Copy, Debug)]
+pub struct FollowConfig {
+    // wait between checks for new data at the end of the file
+    pub poll_interval: Duration,
+    // end the input once the file has not grown for this long; follow forever if None
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for FollowConfig {
+    fn default() -> Self {
+        FollowConfig { poll_interval: FOLLOW_POLL_INTERVAL_DEFAULT, idle_timeout: None }
+    }
+}
 
 #[derive(Clone)]
-pub struct FileInput;
+pub struct FileInput {
+    follow: Option<FollowConfig>,
+}
+
+impl FileInput {
+    pub fn new() -> Self {
+        FileInput { follow: None }
+    }
+
+    /// Keeps reading files as they grow, like tail -f, until they are rotated or truncated.
+    pub fn new_follow(follow: FollowConfig) -> Self {
+        FileInput { follow: Some(follow) }
+    }
+}
+
+impl Default for FileInput {
+    fn default() -> Self {
+        FileInput::new()
+    }
+}
 
 impl TransportFactory for FileInput {
     fn can_open(&self, path: &Path) -> bool {
         path.exists()
     }
 
-    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + Send>> {
+        if let Some(follow) = self.follow {
+            return Ok(Box::new(FollowReader::new(path, follow)?))
+        }
         match fs::File::open(path) {
             Ok(r) => {
                 Ok(Box::new(r))
@@ -23,6 +66,14 @@ impl TransportFactory for FileInput {
         }
     }
 
+    fn open_seekable(&self, path: &Path) -> io::Result<Option<Box<dyn SeekableRead>>> {
+        // a followed file is read as a stream
+        if self.follow.is_some() {
+            return Ok(None)
+        }
+        Ok(Some(Box::new(fs::File::open(path)?)))
+    }
+
     fn box_clone(&self) -> Box<dyn TransportFactory> {
         Box::new((*self).clone())
     }
@@ -31,3 +82,75 @@ impl TransportFactory for FileInput {
         "file"
     }
 }
+
+/// Reads a growing file. At the end of the file it waits for more data instead of
+/// returning EOF, so sources see a partial trailing record as data still to come.
+/// A file that is replaced (rotated) or shrinks (truncated) ends the input, so that
+/// it can be opened again and read from the start, header included; see `FollowSource`.
+pub struct FollowReader {
+    path: PathBuf,
+    file: fs::File,
+    position: u64,
+    follow: FollowConfig,
+    reopen: Arc<AtomicBool>,
+}
+
+impl FollowReader {
+    pub fn new(path: &Path, follow: FollowConfig) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let reopen = Arc::new(AtomicBool::new(false));
+        Ok(FollowReader { path: path.to_path_buf(), file, position: 0, follow, reopen })
+    }
+
+    /// Set once the input has ended because the file was rotated or truncated.
+    pub fn reopen_flag(&self) -> Arc<AtomicBool> {
+        self.reopen.clone()
+    }
+
+    fn is_changed(&self) -> io::Result<bool> {
+        let current = match fs::metadata(&self.path) {
+            Ok(m) => m,
+            // rotated away and not created again yet
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e)
+        };
+        Ok(!same_file(&current, &self.file.metadata()?) || current.len() < self.position)
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.reopen.load(Ordering::SeqCst) {
+            return Ok(0)
+        }
+        let idle_since = Instant::now();
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.position += n as u64;
+                return Ok(n)
+            }
+            if self.is_changed()? {
+                self.reopen.store(true, Ordering::SeqCst);
+                return Ok(0)
+            }
+            if let Some(idle_timeout) = self.follow.idle_timeout {
+                if idle_since.elapsed() >= idle_timeout {
+                    return Ok(0)
+                }
+            }
+            thread::sleep(self.follow.poll_interval);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
+}
@@ -1,30 +1,319 @@
-use std::io;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
 
-use reqwest::{Client, Url};
+use flate2::read::GzDecoder;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
 
-use crate::transport::transport_factory::TransportFactory;
+use crate::error::{CliResult, Error};
+use crate::transport::transport_factory::{SeekableRead, TransportFactory};
 
+pub const CONNECT_TIMEOUT_DEFAULT: Duration = Duration::from_secs(10);
+pub const READ_TIMEOUT_DEFAULT: Duration = Duration::from_secs(30);
+pub const RETRIES_DEFAULT: u32 = 3;
+pub const RETRY_BACKOFF_DEFAULT: Duration = Duration::from_millis(500);
+// longest wait between two attempts
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Request headers, timeouts and retries for http(s) inputs.
+#[derive(Clone, Debug)]
+pub struct HttpConfig {
+    headers: Vec<(String, String)>,
+    connect_timeout: Duration,
+    // per read of the response, not the whole transfer
+    read_timeout: Duration,
+    retries: u32,
+    // doubles after each failed attempt
+    retry_backoff: Duration,
+}
+
+impl HttpConfig {
+    pub fn new_default() -> Self {
+        HttpConfig {
+            headers: Vec::new(),
+            connect_timeout: CONNECT_TIMEOUT_DEFAULT,
+            read_timeout: READ_TIMEOUT_DEFAULT,
+            retries: RETRIES_DEFAULT,
+            retry_backoff: RETRY_BACKOFF_DEFAULT
+        }
+    }
+
+    pub fn headers(&self) -> &Vec<(String, String)> {
+        &self.headers
+    }
+
+    pub fn add_header(&mut self, name: &str, value: &str) -> CliResult<()> {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(Error::from(format!("Http -- invalid header name - {}", name)))
+        }
+        if HeaderValue::from_str(value).is_err() {
+            return Err(Error::from(format!("Http -- invalid value for header {}", name)))
+        }
+        self.headers.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// Adds a header given as "Name: value".
+    pub fn add_header_line(&mut self, line: &str) -> CliResult<()> {
+        match line.split_once(':') {
+            Some((name, value)) => self.add_header(name.trim(), value.trim()),
+            None => Err(Error::from(format!("Http -- expected 'Name: value' header, got - {}", line)))
+        }
+    }
+
+    /// Adds the headers in a file, one "Name: value" per line; blank lines and lines
+    /// starting with # are skipped.
+    pub fn read_header_file(&mut self, path: &str) -> CliResult<()> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => return Err(Error::from(format!("Http -- cannot read header file {}: {}", path, e)))
+        };
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            self.add_header_line(line)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_bearer_token(&mut self, token: &str) -> CliResult<()> {
+        self.add_header(header::AUTHORIZATION.as_str(), &format!("Bearer {}", token))
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        self.retry_backoff
+    }
+
+    pub fn set_retry_backoff(&mut self, retry_backoff: Duration) {
+        self.retry_backoff = retry_backoff;
+    }
+
+    // wait before the attempt after `failures` failed ones
+    fn backoff(&self, failures: u32) -> Duration {
+        self.retry_backoff.saturating_mul(1 << failures.min(16)).min(RETRY_BACKOFF_MAX)
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig::new_default()
+    }
+}
+
+/// Reads http(s) urls. Failed requests are retried with backoff, a response cut off midway
+/// is resumed with a range request, and servers that accept ranges allow random access.
 #[derive(Clone)]
-pub struct Http;
+pub struct Http {
+    config: Arc<HttpConfig>,
+    // built on first use, shared by clones
+    client: Arc<OnceLock<Client>>,
+}
+
+impl Http {
+    pub fn new(config: HttpConfig) -> Self {
+        Http { config: Arc::new(config), client: Arc::new(OnceLock::new()) }
+    }
+
+    fn client(&self) -> io::Result<&Client> {
+        if let Some(client) = self.client.get() {
+            return Ok(client)
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.config.headers {
+            // checked when added
+            headers.append(HeaderName::from_bytes(name.as_bytes()).unwrap(), HeaderValue::from_str(value).unwrap());
+        }
+        // byte offsets for ranges must be of the file itself, so content encoding is not
+        // negotiated; a server that gzips anyway is decoded in open
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        let client = Client::builder()
+            .default_headers(headers)
+            .gzip(false)
+            .connect_timeout(self.config.connect_timeout)
+            .timeout(self.config.read_timeout)
+            .build()
+            .map_err(to_io_error)?;
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    // sends the request, retrying errors and 5xx or 429 responses; other responses
+    // are returned whatever their status
+    fn send(&self, request: impl Fn(&Client) -> RequestBuilder) -> io::Result<Response> {
+        let client = self.client()?;
+        let mut failures = 0;
+        loop {
+            let last = failures >= self.config.retries;
+            match request(client).send() {
+                Ok(r) if last || !is_transient(r.status()) => return Ok(r),
+                Err(e) if last => return Err(to_io_error(e)),
+                _ => {}
+            }
+            thread::sleep(self.config.backoff(failures));
+            failures += 1;
+        }
+    }
+
+    // gets the url from a byte offset on
+    fn get_from(&self, url: &Url, offset: u64) -> io::Result<Response> {
+        let mut response = self.send(|c| match offset {
+            0 => c.get(url.clone()),
+            _ => c.get(url.clone()).header(header::RANGE, format!("bytes={}-", offset))
+        })?;
+        if !response.status().is_success() {
+            return Err(io::Error::other(format!("Http -- {} responded {}", url, response.status())))
+        }
+        // a server that ignores the range sends the whole file
+        if offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            io::copy(&mut (&mut response).take(offset), &mut io::sink())?;
+        }
+        Ok(response)
+    }
+
+    // None if the server does not answer HEAD requests for the url
+    fn head(&self, url: &Url) -> io::Result<Option<Response>> {
+        let response = self.send(|c| c.head(url.clone()))?;
+        Ok(Some(response).filter(|r| r.status().is_success()))
+    }
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Http::new(HttpConfig::new_default())
+    }
+}
+
+fn to_io_error(e: reqwest::Error) -> io::Error {
+    let kind = match e.is_timeout() {
+        true => io::ErrorKind::TimedOut,
+        false => io::ErrorKind::Other
+    };
+    io::Error::new(kind, e)
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn parse_url(path: &Path) -> io::Result<Url> {
+    let text = match path.to_str() {
+        Some(t) => t,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Http -- invalid url {:?}", path)))
+    };
+    match text.parse() {
+        Ok(url) => Ok(url),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Http -- invalid url {}: {}", text, e)))
+    }
+}
+
+fn header_str<'a>(response: &'a Response, name: &HeaderName) -> Option<&'a str> {
+    response.headers().get(name)?.to_str().ok()
+}
+
+fn accepts_ranges(response: &Response) -> bool {
+    header_str(response, &header::ACCEPT_RANGES).is_some_and(|r| r.eq_ignore_ascii_case("bytes"))
+}
+
+fn is_identity_encoded(response: &Response) -> bool {
+    header_str(response, &header::CONTENT_ENCODING).is_none_or(|e| e.eq_ignore_ascii_case("identity"))
+}
+
+/// The file extension for a media type, e.g. ".csv" for "text/csv; charset=utf-8".
+pub fn media_type_extension(content_type: &str) -> Option<&'static str> {
+    let media_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match media_type.as_str() {
+        "text/csv" | "application/csv" | "text/comma-separated-values" => Some(".csv"),
+        "application/x-dc" | "application/vnd.chopper.dc" => Some(".dc"),
+        _ => None
+    }
+}
+
+// the extension of the file name in content-disposition, e.g. ".dc.gz"
+fn disposition_extension(disposition: &str) -> Option<String> {
+    let name = disposition.split(';')
+        .filter_map(|p| p.trim().strip_prefix("filename="))
+        .next()?
+        .trim_matches('"');
+    let dot = name.find('.')?;
+    Some(name[dot..].to_string())
+}
 
 impl TransportFactory for Http {
     fn can_open(&self, path: &Path) -> bool {
-        path.starts_with("http://") || path.starts_with("https://") || path.starts_with("ftp://")
+        path.to_str().is_some_and(|p| p.starts_with("http://") || p.starts_with("https://"))
     }
 
-    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
-        let url: Url = path.to_str().unwrap().parse().unwrap();
-        let client = Client::new();
-        let response = match client.get(url).send() {
-            Ok(r) => r,
-            Err(err) => {
-                return Err(io::Error::new(io::ErrorKind::Other, err))
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + Send>> {
+        let url = parse_url(path)?;
+        let response = self.get_from(&url, 0)?;
+        match header_str(&response, &header::CONTENT_ENCODING) {
+            None => {},
+            Some(e) if e.eq_ignore_ascii_case("identity") => {},
+            Some(e) if e.eq_ignore_ascii_case("gzip") => {
+                // offsets of the encoded body cannot be resumed
+                let reader = HttpReader::new(self.clone(), url, response, false, None);
+                return Ok(Box::new(GzDecoder::new(io::BufReader::new(reader))))
             }
+            Some(e) => return Err(io::Error::other(format!("Http -- unsupported content encoding {} for {}", e, url)))
+        }
+        let resumable = accepts_ranges(&response);
+        let reader = HttpReader::new(self.clone(), url, response, resumable, None);
+        Ok(Box::new(io::BufReader::new(reader)))
+    }
+
+    fn open_seekable(&self, path: &Path) -> io::Result<Option<Box<dyn SeekableRead>>> {
+        let url = parse_url(path)?;
+        // read as a stream if HEAD is not allowed; open reports other failures
+        let response = match self.head(&url)? {
+            Some(r) => r,
+            None => return Ok(None)
         };
+        match response.content_length() {
+            Some(length) if accepts_ranges(&response) && is_identity_encoded(&response) => {
+                let reader = HttpReader::new_seekable(self.clone(), url, length);
+                Ok(Some(Box::new(io::BufReader::new(reader))))
+            }
+            _ => Ok(None)
+        }
+    }
 
-        let reader = io::BufReader::new(response);
-        Ok(Box::new(reader))
+    fn file_type(&self, path: &Path) -> Option<String> {
+        let url = parse_url(path).ok()?;
+        let name = url.path_segments()?.next_back().unwrap_or("");
+        if let Some(dot) = name.find('.') {
+            return Some(name[dot..].to_string())
+        }
+        // no extension in the url, ask the server
+        let response = self.head(&url).ok()??;
+        if let Some(extension) = header_str(&response, &header::CONTENT_DISPOSITION).and_then(disposition_extension) {
+            return Some(extension)
+        }
+        media_type_extension(header_str(&response, &header::CONTENT_TYPE)?).map(|e| e.to_string())
     }
 
     fn box_clone(&self) -> Box<dyn TransportFactory> {
@@ -35,3 +324,121 @@ impl TransportFactory for Http {
         "http"
     }
 }
+
+/// The body of a url, read from the current position on. After a read error the rest is
+/// requested again if the server accepts ranges; seeking also starts a new range request.
+pub struct HttpReader {
+    http: Http,
+    url: Url,
+    // None after a seek, until the next read
+    response: Option<Response>,
+    position: u64,
+    resumable: bool,
+    // total size, for seekable readers
+    length: Option<u64>,
+}
+
+impl HttpReader {
+    fn new(http: Http, url: Url, response: Response, resumable: bool, length: Option<u64>) -> Self {
+        HttpReader { http, url, response: Some(response), position: 0, resumable, length }
+    }
+
+    fn new_seekable(http: Http, url: Url, length: u64) -> Self {
+        HttpReader { http, url, response: None, position: 0, resumable: true, length: Some(length) }
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.length.is_some_and(|l| self.position >= l) {
+            return Ok(0)
+        }
+        let mut failures = 0;
+        loop {
+            let result = match self.response.as_mut() {
+                Some(response) => response.read(buf),
+                None => match self.http.get_from(&self.url, self.position) {
+                    Ok(response) => {
+                        self.response = Some(response);
+                        continue;
+                    }
+                    Err(e) => return Err(e)
+                }
+            };
+            match result {
+                Ok(n) => {
+                    self.position += n as u64;
+                    return Ok(n)
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if !self.resumable || failures >= self.http.config.retries => return Err(e),
+                Err(_) => {
+                    thread::sleep(self.http.config.backoff(failures));
+                    failures += 1;
+                    self.response = None;
+                }
+            }
+        }
+    }
+}
+
+impl Seek for HttpReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match (pos, self.length) {
+            (SeekFrom::Start(p), _) => Some(p),
+            (SeekFrom::Current(d), _) => self.position.checked_add_signed(d),
+            (SeekFrom::End(d), Some(length)) => length.checked_add_signed(d),
+            (SeekFrom::End(_), None) => return Err(io::Error::new(
+                io::ErrorKind::Unsupported, format!("Http -- size of {} is unknown", self.url)))
+        };
+        let position = match position {
+            Some(p) => p,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Http -- seek before the start"))
+        };
+        if position != self.position {
+            self.response = None;
+            self.position = position;
+        }
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let mut config = HttpConfig::new_default();
+        config.add_header_line("X-Api-Key: abc:def").unwrap();
+        config.set_bearer_token("token").unwrap();
+        assert_eq!(config.headers(), &vec![("X-Api-Key".to_string(), "abc:def".to_string()),
+                                           ("authorization".to_string(), "Bearer token".to_string())]);
+        assert!(config.add_header_line("no colon").is_err());
+        assert!(config.add_header_line("bad name: x").is_err());
+
+        assert_eq!(config.backoff(0), RETRY_BACKOFF_DEFAULT);
+        assert_eq!(config.backoff(2), RETRY_BACKOFF_DEFAULT * 4);
+        assert_eq!(config.backoff(30), RETRY_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn test_file_type_headers() {
+        assert_eq!(media_type_extension("text/csv; charset=utf-8"), Some(".csv"));
+        assert_eq!(media_type_extension("Application/X-DC"), Some(".dc"));
+        assert_eq!(media_type_extension("application/octet-stream"), None);
+        assert_eq!(disposition_extension("attachment; filename=\"feed.dc.gz\""), Some(".dc.gz".to_string()));
+        assert_eq!(disposition_extension("attachment; filename=feed"), None);
+        assert_eq!(disposition_extension("inline"), None);
+    }
+
+    #[test]
+    fn test_can_open() {
+        let http = Http::default();
+        assert!(http.can_open(Path::new("https://example.com/a.csv")));
+        assert!(!http.can_open(Path::new("ftp://example.com/a.csv")));
+        assert!(!http.can_open(Path::new("data/a.csv")));
+        assert_eq!(http.file_type(Path::new("http://example.com/data/a.csv.gz?token=x")), Some(".csv.gz".to_string()));
+        assert!(http.open(Path::new("http://exa mple.com/a.csv")).is_err());
+    }
+}
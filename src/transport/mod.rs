@@ -1,3 +1,5 @@
+pub mod deadline;
 pub mod file;
 pub mod http;
+pub mod socket;
 pub mod transport_factory;
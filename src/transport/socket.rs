@@ -0,0 +1,199 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+use crate::transport::transport_factory::TransportFactory;
+
+pub const DEFAULT_FORMAT: &str = "csv";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+/// A local stream: tcp://host:port or unix:///path/to.sock, with optional query
+/// parameters: listen or listen=true|false, to wait for one peer to connect instead of
+/// connecting to it, and format=csv|dc|csv.gz|..., the data format as a file extension
+/// [default: csv].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocketAddress {
+    pub endpoint: Endpoint,
+    pub listen: bool,
+    pub format: String,
+}
+
+impl SocketAddress {
+    pub fn is_socket_url(url: &str) -> bool {
+        url.starts_with("tcp://") || url.starts_with("unix://")
+    }
+
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let (address, query) = match url.split_once('?') {
+            Some((a, q)) => (a, Some(q)),
+            None => (url, None)
+        };
+        let endpoint = if let Some(host_port) = address.strip_prefix("tcp://") {
+            if !host_port.contains(':') {
+                return Err(invalid(format!("Socket -- missing port in {}", url)))
+            }
+            Endpoint::Tcp(host_port.to_string())
+        } else if let Some(path) = address.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err(invalid(format!("Socket -- missing path in {}", url)))
+            }
+            Endpoint::Unix(PathBuf::from(path))
+        } else {
+            return Err(invalid(format!("Socket -- unknown scheme in {}, expected tcp:// or unix://", url)))
+        };
+
+        let mut listen = false;
+        let mut format = DEFAULT_FORMAT.to_string();
+        for param in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+            match param.split_once('=') {
+                None if param == "listen" => listen = true,
+                Some(("listen", "true")) => listen = true,
+                Some(("listen", "false")) => listen = false,
+                Some(("listen", value)) => return Err(invalid(format!(
+                    "Socket -- invalid listen value {} in {}, expected true or false", value, url))),
+                Some(("format", value)) => format = value.trim_start_matches('.').to_string(),
+                _ => return Err(invalid(format!("Socket -- unknown parameter {} in {}", param, url)))
+            }
+        }
+        Ok(SocketAddress { endpoint, listen, format })
+    }
+
+    /// Connects to the peer, or waits for one to connect when listening.
+    pub fn open(&self) -> io::Result<SocketStream> {
+        match (&self.endpoint, self.listen) {
+            (Endpoint::Tcp(address), false) => Ok(SocketStream::Tcp(TcpStream::connect(address)?)),
+            (Endpoint::Tcp(address), true) => {
+                let (stream, _) = TcpListener::bind(address)?.accept()?;
+                Ok(SocketStream::Tcp(stream))
+            }
+            (Endpoint::Unix(path), listen) => open_unix(path, listen),
+        }
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(unix)]
+fn open_unix(path: &Path, listen: bool) -> io::Result<SocketStream> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    if !listen {
+        return Ok(SocketStream::Unix(UnixStream::connect(path)?))
+    }
+    let listener = UnixListener::bind(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AddrInUse => io::Error::new(io::ErrorKind::AddrInUse, format!(
+            "Socket -- {} is in use; if no one is listening on it, remove the stale socket file", path.display())),
+        _ => e
+    })?;
+    let accepted = listener.accept();
+    // the socket file is not needed once connected
+    std::fs::remove_file(path)?;
+    Ok(SocketStream::Unix(accepted?.0))
+}
+
+#[cfg(not(unix))]
+fn open_unix(path: &Path, _listen: bool) -> io::Result<SocketStream> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       format!("Socket -- unix sockets are not supported on this platform: {:?}", path)))
+}
+
+pub enum SocketStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Read for SocketStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            SocketStream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            SocketStream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for SocketStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            SocketStream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            SocketStream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            SocketStream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            SocketStream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Socket;
+
+impl TransportFactory for Socket {
+    fn can_open(&self, path: &Path) -> bool {
+        path.to_str().is_some_and(SocketAddress::is_socket_url)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + Send>> {
+        let address = SocketAddress::parse(path.to_str().unwrap())?;
+        Ok(Box::new(address.open()?))
+    }
+
+    fn file_type(&self, path: &Path) -> Option<String> {
+        let address = SocketAddress::parse(path.to_str()?).ok()?;
+        Some(format!(".{}", address.format))
+    }
+
+    fn box_clone(&self) -> Box<dyn TransportFactory> {
+        Box::new((*self).clone())
+    }
+
+    fn factory_name(&self) -> &str {
+        "socket"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(SocketAddress::parse("tcp://127.0.0.1:9000").unwrap(),
+                   SocketAddress { endpoint: Endpoint::Tcp("127.0.0.1:9000".to_string()),
+                                   listen: false, format: "csv".to_string() });
+        assert_eq!(SocketAddress::parse("unix:///tmp/feed.sock?listen&format=dc").unwrap(),
+                   SocketAddress { endpoint: Endpoint::Unix(PathBuf::from("/tmp/feed.sock")),
+                                   listen: true, format: "dc".to_string() });
+        assert!(SocketAddress::parse("tcp://localhost").is_err());
+        assert!(SocketAddress::parse("unix://").is_err());
+        assert!(SocketAddress::parse("tcp://localhost:1?bogus").is_err());
+        assert!(!SocketAddress::parse("tcp://localhost:1?listen=false").unwrap().listen);
+        assert!(SocketAddress::parse("tcp://localhost:1?listen=yes").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_unix_socket() {
+        let path = std::env::temp_dir().join(format!("chopper_stale_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // the socket file stays behind once the listener is dropped
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let error = open_unix(&path, true).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert!(error.to_string().contains(&path.display().to_string()), "{}", error);
+        assert!(error.to_string().contains("remove the stale socket file"), "{}", error);
+    }
+}
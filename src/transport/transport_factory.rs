@@ -2,9 +2,25 @@ use std::fmt;
 use std::io;
 use std::path::Path;
 
+pub trait SeekableRead: io::Read + io::Seek {}
+
+impl <T: io::Read + io::Seek> SeekableRead for T {}
+
 pub trait TransportFactory {
     fn can_open(&self, path: &Path) -> bool;
-    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>>;
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + Send>>;
+
+    /// Opens the path for random access if the transport supports it.
+    fn open_seekable(&self, _path: &Path) -> io::Result<Option<Box<dyn SeekableRead>>> {
+        Ok(None)
+    }
+
+    /// The data format of the path as a file extension, e.g. ".csv", if the transport
+    /// knows it; otherwise it comes from the path itself.
+    fn file_type(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
     fn box_clone(&self) -> Box<dyn TransportFactory>;
     fn factory_name(&self) -> &str;
 }
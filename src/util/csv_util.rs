@@ -1,6 +1,11 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, SeekFrom};
+use std::rc::Rc;
+
 use crate::chopper::chopper::Source;
 use crate::error::{CliResult, Error};
-use crate::source::csv_configs::CSVOutputConfig;
+use crate::source::csv_configs::{self, CSVOutputConfig};
 
 pub fn parse_into_delimiter(str: &str) -> CliResult<u8> {
     /* Code in this function was adapted from public domain xsv project. */
@@ -22,6 +27,204 @@ pub fn parse_into_delimiter(str: &str) -> CliResult<u8> {
     }
 }
 
+// delimiter of one or more ASCII characters; \t and \xHH escapes are allowed, e.g. "||" or "\x01"
+pub fn parse_delimiter(str: &str) -> CliResult<Vec<u8>> {
+    let mut delimiter: Vec<u8> = Vec::new();
+    let mut chars = str.chars();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii() {
+            return Err(Error::from(
+                format!("Error: specified delimiter '{}' is not an ASCII character.", c)))
+        }
+        if c != '\\' {
+            delimiter.push(c as u8);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => delimiter.push(b'\t'),
+            Some('\\') => delimiter.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) if b.is_ascii() => delimiter.push(b),
+                    _ => return Err(Error::from(format!("Error: invalid escape in delimiter '{}'.", str)))
+                }
+            }
+            _ => return Err(Error::from(format!("Error: invalid escape in delimiter '{}'.", str)))
+        }
+    }
+    if delimiter.is_empty() {
+        return Err(Error::from("Error: delimiter cannot be empty."))
+    }
+    Ok(delimiter)
+}
+
+type SeekFn<R> = fn(&mut io::BufReader<R>, SeekFrom) -> io::Result<u64>;
+
+/// Prepares csv input for parsing: skips leading lines and translates a multi-byte
+/// delimiter into the single byte placeholder the csv parser splits on.
+pub struct DialectReader<R> {
+    reader: io::BufReader<R>,
+    // multi-byte delimiter to translate, if any
+    delimiter: Option<Vec<u8>>,
+    // bytes skipped at the start of the input
+    skipped: u64,
+    line: Vec<u8>,
+    line_pos: usize,
+    // bytes passed on from offset history_start, if kept
+    history: Option<Vec<u8>>,
+    history_start: u64,
+    // set for random access inputs
+    seek: Option<SeekFn<R>>,
+}
+
+impl <R: io::Read> DialectReader<R> {
+    pub fn new(reader: R, delimiter: &[u8], skip_lines: usize) -> io::Result<Self> {
+        let mut reader = io::BufReader::new(reader);
+        let mut skipped: u64 = 0;
+        let mut line: Vec<u8> = Vec::new();
+        for _i in 0..skip_lines {
+            line.clear();
+            skipped += reader.read_until(b'\n', &mut line)? as u64;
+        }
+        line.clear();
+        let delimiter = match delimiter.len() {
+            1 => None,
+            _ => Some(delimiter.to_vec())
+        };
+        Ok(DialectReader { reader, delimiter, skipped, line, line_pos: 0, history: None, history_start: 0, seek: None })
+    }
+
+    pub fn translates_delimiter(&self) -> bool {
+        self.delimiter.is_some()
+    }
+
+    /// Whether byte offsets can be sought to; not for streams, nor for a translated
+    /// multi-byte delimiter, whose offsets do not match the input.
+    pub fn is_seekable(&self) -> bool {
+        self.seek.is_some() && !self.translates_delimiter()
+    }
+
+    /// Keeps the bytes passed on, so that the text of a record can be looked up by its
+    /// byte span, as given by csv positions.
+    pub fn keep_history(&mut self) {
+        self.history = Some(Vec::new());
+    }
+
+    /// The bytes from offset `start` to `end`, if still kept.
+    pub fn history(&self, start: u64, end: u64) -> Option<&[u8]> {
+        let history = self.history.as_ref()?;
+        let from = start.checked_sub(self.history_start)? as usize;
+        let to = end.checked_sub(self.history_start)? as usize;
+        history.get(from..to)
+    }
+
+    /// Forgets the kept bytes before the offset.
+    pub fn discard_history(&mut self, offset: u64) {
+        if let Some(history) = self.history.as_mut() {
+            let n = (offset.saturating_sub(self.history_start) as usize).min(history.len());
+            history.drain(..n);
+            self.history_start += n as u64;
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    fn fill_line(&mut self, delimiter: &[u8]) -> io::Result<()> {
+        let mut raw: Vec<u8> = Vec::new();
+        self.reader.read_until(b'\n', &mut raw)?;
+        self.line.clear();
+        self.line_pos = 0;
+        let mut i = 0;
+        while i < raw.len() {
+            if raw[i..].starts_with(delimiter) {
+                self.line.push(csv_configs::MULTI_BYTE_DELIMITER_PLACEHOLDER);
+                i += delimiter.len();
+            } else {
+                self.line.push(raw[i]);
+                i += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl <R: io::Read> io::Read for DialectReader<R> {
+    fn read(&mut self, into: &mut [u8]) -> io::Result<usize> {
+        let n = match self.delimiter.clone() {
+            None => self.reader.read(into)?,
+            Some(delimiter) => {
+                if self.line_pos >= self.line.len() {
+                    self.fill_line(&delimiter)?;
+                }
+                let n = (&self.line[self.line_pos..]).read(into)?;
+                self.line_pos += n;
+                n
+            }
+        };
+        if let Some(history) = self.history.as_mut() {
+            history.extend_from_slice(&into[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl <R: io::Read + io::Seek> DialectReader<R> {
+    pub fn new_seekable(reader: R, delimiter: &[u8], skip_lines: usize) -> io::Result<Self> {
+        let mut reader = DialectReader::new(reader, delimiter, skip_lines)?;
+        reader.seek = Some(<io::BufReader<R> as io::Seek>::seek);
+        Ok(reader)
+    }
+}
+
+// positions are relative to the end of the skipped lines
+impl <R: io::Read> io::Seek for DialectReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if self.translates_delimiter() {
+            return Err(io::Error::other("cannot seek csv input with a multi-byte delimiter"))
+        }
+        let seek = match self.seek {
+            Some(seek) => seek,
+            None => return Err(io::Error::other("cannot seek a streamed csv input"))
+        };
+        let pos = match pos {
+            SeekFrom::Start(p) => SeekFrom::Start(p + self.skipped),
+            p => p
+        };
+        let position = seek(&mut self.reader, pos)?.saturating_sub(self.skipped);
+        if let Some(history) = self.history.as_mut() {
+            history.clear();
+            self.history_start = position;
+        }
+        Ok(position)
+    }
+}
+
+/// Side output of input rows that were rejected, as csv with columns line, reason and raw;
+/// clones share the same file so all csv inputs can report to it.
+#[derive(Clone)]
+pub struct RejectedRows {
+    writer: Rc<RefCell<csv::Writer<File>>>,
+}
+
+impl RejectedRows {
+    pub fn new(path: &str) -> CliResult<Self> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["line", "reason", "raw"])?;
+        writer.flush()?;
+        Ok(RejectedRows { writer: Rc::new(RefCell::new(writer)) })
+    }
+
+    pub fn write(&self, line: u64, reason: &str, raw: &str) -> CliResult<()> {
+        let mut writer = self.writer.borrow_mut();
+        writer.write_record([line.to_string().as_str(), reason, raw])?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
 pub fn create_csv_output_config_from_source(sources: &mut Vec<Box<dyn Source>>, delimiter: &str) -> CSVOutputConfig {
     let mut all_sources_have_native_timestamps = true;
     for source in sources {
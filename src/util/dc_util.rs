@@ -1,22 +1,161 @@
 use std::collections::HashMap;
-use std::io::{self, BufWriter, Write};
+use std::io::{self, Read};
 use std::string::String;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
+pub use crate::chopper::types::DisplayHint;
 use crate::chopper::types::FieldType;
 use crate::error::{CliResult, Error};
 
 pub const MAGIC_NUM: u64 = 0x44434154;
 pub const VERSION: u16 = 2;
+pub const VERSION_3: u16 = 3;
+pub const SUPPORTED_VERSIONS: [u16; 2] = [VERSION, VERSION_3];
+pub const FOOTER_MAGIC_NUM: u64 = 0x44434654;
+// rows per block written by v3
+pub const BLOCK_ROWS: u32 = 4096;
 
-pub enum DisplayHint {
-    Timestamp,
+// layout of v3, all integers big endian:
+//   magic u64, version u16, user header (u32 size + bytes)
+//   timestamp unit u8, block compression u8
+//   field count u32, field descriptors (name, type as sized strings)
+//   field metadata section: u32 size, then per field u32 entry count and
+//     key/value sized strings; unknown keys are kept as is
+//   blocks: u32 row count, u32 raw size, u32 stored size, stored bytes;
+//     rows encoded as in v2; a block with zero rows ends the data
+//   footer: row count u64, min timestamp u64, max timestamp u64, footer magic u64
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimestampUnit {
+    Nanos,
+    Micros,
+}
+
+impl TimestampUnit {
+    pub fn parse(unit: &str) -> CliResult<Self> {
+        match unit {
+            "ns" => Ok(TimestampUnit::Nanos),
+            "us" => Ok(TimestampUnit::Micros),
+            _ => Err(Error::from(format!("DC -- unknown timestamp unit - {}", unit)))
+        }
+    }
+
+    pub fn from_code(code: u8) -> CliResult<Self> {
+        match code {
+            0 => Ok(TimestampUnit::Nanos),
+            1 => Ok(TimestampUnit::Micros),
+            _ => Err(Error::from(format!("DCReader -- unknown timestamp unit code - {}", code)))
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            TimestampUnit::Nanos => 0,
+            TimestampUnit::Micros => 1,
+        }
+    }
+
+    pub fn nanos_per_unit(self) -> u64 {
+        match self {
+            TimestampUnit::Nanos => 1,
+            TimestampUnit::Micros => 1_000,
+        }
+    }
+
+    /// A timestamp read from a file in nanos; one too large to be in nanos is corruption.
+    pub fn to_nanos(self, timestamp: u64) -> CliResult<u64> {
+        match timestamp.checked_mul(self.nanos_per_unit()) {
+            Some(t) => Ok(t),
+            None => Err(Error::from(format!("DC -- timestamp {} {:?} is out of range", timestamp, self)))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockCompression {
     None,
+    Deflate,
+}
+
+impl BlockCompression {
+    pub fn parse(compression: &str) -> CliResult<Self> {
+        match compression {
+            "none" => Ok(BlockCompression::None),
+            "deflate" => Ok(BlockCompression::Deflate),
+            _ => Err(Error::from(format!("DC -- unknown block compression - {}", compression)))
+        }
+    }
+
+    pub fn from_code(code: u8) -> CliResult<Self> {
+        match code {
+            0 => Ok(BlockCompression::None),
+            1 => Ok(BlockCompression::Deflate),
+            _ => Err(Error::from(format!("DCReader -- unknown block compression code - {}", code)))
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            BlockCompression::None => 0,
+            BlockCompression::Deflate => 1,
+        }
+    }
+}
+
+/// Summary written at the end of a v3 file; timestamps are in nanos.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Footer {
+    pub row_count: u64,
+    pub min_timestamp: u64,
+    pub max_timestamp: u64,
+}
+
+// footer size including the zero row count that ends the blocks
+const FOOTER_BYTES: i64 = 4 + 8 * 4;
+
+/// Reads the footer of a v3 file from the end, without reading the rows.
+/// None for older versions or a file whose footer is not written (yet).
+pub fn read_footer_from_end<R: io::Read + io::Seek>(reader: &mut R) -> CliResult<Option<Footer>> {
+    reader.seek(io::SeekFrom::Start(0))?;
+    if reader.read_u64::<BigEndian>()? != MAGIC_NUM || reader.read_u16::<BigEndian>()? != VERSION_3 {
+        return Ok(None)
+    }
+    let user_header_size = reader.read_u32::<BigEndian>()?;
+    reader.seek(io::SeekFrom::Current(user_header_size as i64))?;
+    let timestamp_unit = TimestampUnit::from_code(reader.read_u8()?)?;
+    let header_end = reader.stream_position()?;
+    let file_end = reader.seek(io::SeekFrom::End(0))?;
+    if file_end < header_end + FOOTER_BYTES as u64 {
+        return Ok(None)
+    }
+    reader.seek(io::SeekFrom::End(-FOOTER_BYTES))?;
+    if reader.read_u32::<BigEndian>()? != 0 {
+        return Ok(None)
+    }
+    let row_count = reader.read_u64::<BigEndian>()?;
+    let min_timestamp = timestamp_unit.to_nanos(reader.read_u64::<BigEndian>()?)?;
+    let max_timestamp = timestamp_unit.to_nanos(reader.read_u64::<BigEndian>()?)?;
+    if reader.read_u64::<BigEndian>()? != FOOTER_MAGIC_NUM {
+        return Ok(None)
+    }
+    Ok(Some(Footer { row_count, min_timestamp, max_timestamp }))
+}
+
+// byte count followed by the bytes in hex, e.g. "3 bytes: 01ab02",
+// and the text if the bytes are printable utf-8, e.g. "2 bytes: 6869 (hi)"
+pub fn format_bytes(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) =>
+            format!("{} bytes: {} ({})", bytes.len(), hex.concat(), text),
+        _ => format!("{} bytes: {}", bytes.len(), hex.concat())
+    }
 }
 
+// one null bit per field
 pub fn get_bitset_bytes(field_count: usize) -> usize {
-    1+((field_count-1)/8)
+    field_count.div_ceil(8)
 }
 
 // map for field types
@@ -63,8 +202,8 @@ pub struct FieldDescriptor {
 
 impl FieldDescriptor {
     pub fn new<R: io::BufRead>(mut reader: R) -> CliResult<Self> {
-        let name = Self::get_sized_string(&mut reader)?;
-        let type_string = Self::get_sized_string(&mut reader)?;
+        let name = read_sized_string(&mut reader)?;
+        let type_string = read_sized_string(&mut reader)?;
 
         let display_hint = match reader.read_i32::<BigEndian>()? {
             -1 => DisplayHint::None,
@@ -75,6 +214,13 @@ impl FieldDescriptor {
         Ok(FieldDescriptor { name, type_string, display_hint })
     }
 
+    // v3 keeps the display hint in the field metadata section
+    pub fn new_v3<R: io::BufRead>(mut reader: R) -> CliResult<Self> {
+        let name = read_sized_string(&mut reader)?;
+        let type_string = read_sized_string(&mut reader)?;
+        Ok(FieldDescriptor { name, type_string, display_hint: DisplayHint::None })
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -86,25 +232,36 @@ impl FieldDescriptor {
     pub fn get_display_hint(&self) -> &DisplayHint {
         &self.display_hint
     }
+}
 
-    fn get_sized_string<R: io::BufRead>(mut rdr: R) -> CliResult<String> {
-        let size = rdr.read_u32::<BigEndian>()?;
-        let mut string_bytes: Vec<u8> = Vec::with_capacity(size as usize);
-        for _i in 0..size as usize {
-            string_bytes.push(rdr.read_u8()?);
-        }
-        Ok(String::from_utf8(string_bytes).unwrap().to_string())
+/// Reads `size` bytes, a size given by the file. Memory grows with the bytes actually read,
+/// so a corrupt size fails as a truncated file instead of allocating that much up front.
+pub fn read_bytes<R: io::Read + ?Sized>(reader: &mut R, size: u64) -> io::Result<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    reader.take(size).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"))
+    }
+    Ok(bytes)
+}
+
+pub fn read_sized_string<R: io::Read>(mut rdr: R) -> CliResult<String> {
+    let size = rdr.read_u32::<BigEndian>()?;
+    let string_bytes = read_bytes(&mut rdr, size as u64)?;
+    match String::from_utf8(string_bytes) {
+        Ok(s) => Ok(s),
+        Err(_) => Err(Error::from("DC -- invalid utf-8 in sized string"))
     }
 }
 
-pub fn write_sized_string<W: io::Write>(writer: &mut BufWriter<W>, string: &str) -> CliResult<()> {
+pub fn write_sized_string<W: io::Write>(writer: &mut W, string: &str) -> CliResult<()> {
     let bytes = string.as_bytes();
     writer.write_u32::<BigEndian>(bytes.len() as u32)?;
     writer.write_all(bytes)?;
     Ok(())
 }
 
-pub fn write_string_value<W: io::Write>(writer: &mut BufWriter<W>, value: &str) -> CliResult<()> {
+pub fn write_string_value<W: io::Write>(writer: &mut W, value: &str) -> CliResult<()> {
     let bytes = value.as_bytes();
     match bytes.len() {
         x if x <= std::i16::MAX as usize => writer.write_i16::<BigEndian>(bytes.len() as i16)?,
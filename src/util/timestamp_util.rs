@@ -1,4 +1,6 @@
-use chrono::{NaiveDateTime, TimeZone};
+use std::time::Duration;
+
+use chrono::{DateTime, Days, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use chrono_tz::{Tz, UTC};
 
 use crate::chopper::types::Nanos;
@@ -9,6 +11,7 @@ pub static DEFAULT_DAY: &str = "01";
 pub static DEFAULT_TIME: &str = "00:00:00";
 pub static DEFAULT_ZONE: Tz = UTC;
 pub static DEFAULT_ZONE_FORMAT: &str = "%z";
+pub static DEFAULT_OUTPUT_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.9f";
 
 // list of timestamp formats
 lazy_static! {
@@ -36,21 +39,92 @@ fn create_date_time_formats() -> Vec<String> {
             format.push(format!("{}{}{}", df, d, "%H:%M:%S"));
         }
     }
+    // ISO 8601 local times, then with an embedded offset; RFC 3339 (%+) also accepts Z
+    format.push("%Y-%m-%dT%H:%M:%S%.f".to_string());
+    format.push("%Y-%m-%d %H:%M:%S%.f".to_string());
+    format.push(format!("%Y-%m-%d %H:%M:%S%.f{}", DEFAULT_ZONE_FORMAT));
+    format.push(format!("%Y-%m-%d %H:%M:%S%.f {}", DEFAULT_ZONE_FORMAT));
+    format.push("%+".to_string());
     format
 }
 
-pub fn parse_timestamp_range(timestamp: String, timezone: Tz) -> CliResult<Nanos> {
+/// How a local time that occurs twice (clocks set back) or not at all
+/// (clocks set forward) during a DST transition is resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DstPolicy {
+    /// the earlier instant; a skipped time is read with the offset before the transition
+    Earliest,
+    /// the later instant; a skipped time is read with the offset after the transition
+    Latest,
+    Error,
+}
+
+impl DstPolicy {
+    pub fn parse(policy: &str) -> CliResult<Self> {
+        match policy {
+            "earliest" => Ok(DstPolicy::Earliest),
+            "latest" => Ok(DstPolicy::Latest),
+            "error" => Ok(DstPolicy::Error),
+            _ => Err(Error::from(format!("Cannot parse dst policy: {}", policy)))
+        }
+    }
+}
+
+/// Converts a local time in the time zone to seconds since epoch.
+pub fn localize(naive_dt: &NaiveDateTime, timezone: Tz, dst_policy: DstPolicy) -> CliResult<i64> {
+    let hour = chrono::Duration::hours(1);
+    let datetime = match (timezone.from_local_datetime(naive_dt), dst_policy) {
+        (LocalResult::Single(dt), _) => Some(dt),
+        (LocalResult::Ambiguous(earliest, _), DstPolicy::Earliest) => Some(earliest),
+        (LocalResult::Ambiguous(_, latest), DstPolicy::Latest) => Some(latest),
+        // skipped times are read with the offset of an hour before or after, which covers
+        // the usual one hour gap
+        (LocalResult::None, DstPolicy::Earliest) =>
+            timezone.from_local_datetime(&(*naive_dt - hour)).earliest().map(|dt| dt + hour),
+        (LocalResult::None, DstPolicy::Latest) =>
+            timezone.from_local_datetime(&(*naive_dt + hour)).latest().map(|dt| dt - hour),
+        _ => None
+    };
+    match datetime {
+        Some(dt) => Ok(dt.timestamp()),
+        None => Err(Error::from(format!("Local time {} is ambiguous or does not exist in {} \
+            because of a DST transition; use --dst to pick earliest or latest", naive_dt, timezone)))
+    }
+}
+
+fn has_offset(fmt: &str) -> bool {
+    ["%z", "%:z", "%::z", "%#z", "%+"].iter().any(|z| fmt.contains(z))
+}
+
+/// Parses a date time in the format to seconds since epoch; an offset in the text
+/// takes precedence, otherwise it is a local time in the time zone.
+pub fn parse_datetime(timestamp: &str, fmt: &str, timezone: Tz, dst_policy: DstPolicy) -> CliResult<i64> {
+    if has_offset(fmt) {
+        return Ok(DateTime::parse_from_str(timestamp, fmt)?.timestamp())
+    }
+    let naive_dt = NaiveDateTime::parse_from_str(timestamp, fmt)?;
+    localize(&naive_dt, timezone, dst_policy)
+}
+
+// whether the timestamp is a date time in the format, regardless of the time zone
+fn matches_format(timestamp: &str, fmt: &str) -> bool {
+    match has_offset(fmt) {
+        true => DateTime::parse_from_str(timestamp, fmt).is_ok(),
+        false => NaiveDateTime::parse_from_str(timestamp, fmt).is_ok()
+    }
+}
+
+/// The last of DATE_TIME_FORMATS the timestamp parses with.
+pub fn detect_format(timestamp: &str) -> Option<&'static String> {
+    DATE_TIME_FORMATS.iter().rev().find(|fmt| matches_format(timestamp, fmt))
+}
+
+pub fn parse_timestamp_range(timestamp: String, timezone: Tz, dst_policy: DstPolicy) -> CliResult<Nanos> {
     let timestamp = complete_timestamp(timestamp);
 
     // try available datetime formats
-    for fmt in DATE_TIME_FORMATS.iter() {
-        // try parsing to naive datetime first
-        let naive_dt
-            = NaiveDateTime::parse_from_str(timestamp.as_ref(), fmt.as_ref());
-        // if matching format is found, convert naive datetime to a timezone-aware datetime
-        if naive_dt.is_ok() {
-            return Ok(timezone.from_local_datetime(&naive_dt?).unwrap().timestamp() as Nanos)
-        }
+    if let Some(fmt) = DATE_TIME_FORMATS.iter().find(|fmt| matches_format(&timestamp, fmt)) {
+        return Ok(parse_datetime(&timestamp, fmt, timezone, dst_policy)? as Nanos)
     }
     match timestamp.parse::<Nanos>() {
         Ok(n) => Ok(n),
@@ -59,6 +133,105 @@ pub fn parse_timestamp_range(timestamp: String, timezone: Tz) -> CliResult<Nanos
     }
 }
 
+/// A --begin/--end bound, either a point in time or an offset from the first row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeBound {
+    Absolute(Nanos),
+    AfterFirstRow(Nanos),
+}
+
+/// Parses an absolute timestamp or a relative expression: now, -<duration> (before now),
+/// +<duration> (after the first row), or today/yesterday/tomorrow with an optional time
+/// of day such as "today 09:30".
+pub fn parse_time_bound(expression: &str, timezone: Tz, dst_policy: DstPolicy,
+                        now: DateTime<Utc>) -> CliResult<TimeBound> {
+    let expression = expression.trim();
+    if expression == "now" {
+        return Ok(TimeBound::Absolute(now.timestamp() as Nanos))
+    }
+    if let Some(duration) = expression.strip_prefix('-') {
+        let before = parse_duration(duration)?.as_secs() as i64;
+        return Ok(TimeBound::Absolute((now.timestamp() - before) as Nanos))
+    }
+    if let Some(duration) = expression.strip_prefix('+') {
+        return Ok(TimeBound::AfterFirstRow(parse_duration(duration)?.as_secs() as Nanos))
+    }
+
+    let mut words = expression.splitn(2, ' ');
+    let today = now.with_timezone(&timezone).date_naive();
+    let date = match words.next() {
+        Some("today") => Some(today),
+        Some("yesterday") => today.checked_sub_days(Days::new(1)),
+        Some("tomorrow") => today.checked_add_days(Days::new(1)),
+        _ => None
+    };
+    match date {
+        Some(date) => {
+            let time = match words.next() {
+                Some(t) => parse_time_of_day(t)?,
+                None => NaiveTime::MIN
+            };
+            let seconds = localize(&date.and_time(time), timezone, dst_policy)?;
+            Ok(TimeBound::Absolute(seconds as Nanos))
+        }
+        None => Ok(TimeBound::Absolute(parse_timestamp_range(expression.to_string(), timezone, dst_policy)?))
+    }
+}
+
+// time of day as hh:mm or hh:mm:ss
+pub fn parse_time_of_day(time: &str) -> CliResult<NaiveTime> {
+    let time = time.trim();
+    match NaiveTime::parse_from_str(time, "%H:%M:%S").or_else(|_| NaiveTime::parse_from_str(time, "%H:%M")) {
+        Ok(t) => Ok(t),
+        Err(_) => Err(Error::from(format!("Cannot parse time of day: {}. Use hh:mm or hh:mm:ss.", time)))
+    }
+}
+
+/// A time of day window such as 09:30-16:00, repeated every day in the time zone;
+/// a window that ends before it starts wraps around midnight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DailyWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    timezone: Tz,
+}
+
+impl DailyWindow {
+    pub fn new(start: NaiveTime, end: NaiveTime, timezone: Tz) -> Self {
+        DailyWindow { start, end, timezone }
+    }
+
+    pub fn parse(window: &str, timezone: Tz) -> CliResult<Self> {
+        match window.split_once('-') {
+            Some((start, end)) =>
+                Ok(DailyWindow::new(parse_time_of_day(start)?, parse_time_of_day(end)?, timezone)),
+            None => Err(Error::from(format!("Cannot parse time window: {}. Use e.g. 09:30-16:00.", window)))
+        }
+    }
+
+    /// Whether the timestamp, in seconds, falls in the window; the end is exclusive.
+    pub fn contains(&self, timestamp: Nanos) -> bool {
+        let time = match self.timezone.timestamp_opt(timestamp as i64, 0).single() {
+            Some(dt) => dt.time(),
+            None => return false
+        };
+        match self.start <= self.end {
+            true => time >= self.start && time < self.end,
+            false => time >= self.start || time < self.end
+        }
+    }
+
+    /// The local date the window containing the timestamp, in seconds, starts on:
+    /// the day before for the part of a window past midnight.
+    pub fn start_date(&self, timestamp: Nanos) -> Option<NaiveDate> {
+        let dt = self.timezone.timestamp_opt(timestamp as i64, 0).single()?;
+        match self.start > self.end && dt.time() < self.end {
+            true => dt.date_naive().pred_opt(),
+            false => Some(dt.date_naive())
+        }
+    }
+}
+
 pub fn complete_timestamp(mut timestamp: String) -> String {
     // if time is not specified
     if timestamp.len() <= 8 {
@@ -74,12 +247,45 @@ pub fn complete_timestamp(mut timestamp: String) -> String {
     timestamp
 }
 
-pub fn parse_time_zone(timezone: Option<&str>) -> Tz {
-    let tz: Tz = match timezone {
-        Some(z) => z.parse().unwrap(),
-        None => DEFAULT_ZONE,
+pub fn parse_time_zone(timezone: Option<&str>) -> CliResult<Tz> {
+    match timezone {
+        Some(z) => match z.parse::<Tz>() {
+            Ok(tz) => Ok(tz),
+            Err(_) => Err(Error::from(format!("Cannot parse time zone: {}. \
+                Use an IANA name such as America/New_York or UTC.", z)))
+        },
+        None => Ok(DEFAULT_ZONE),
+    }
+}
+
+// parse durations like 500ms, 30s, 5m, 2h, 1d; plain numbers are seconds
+pub fn parse_duration(duration: &str) -> CliResult<Duration> {
+    let duration = duration.trim();
+    let split = duration.find(|c: char| !c.is_ascii_digit()).unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(split);
+    let value = match value.parse::<u64>() {
+        Ok(v) => v,
+        Err(_) => return Err(Error::from(format!("Cannot parse duration: {}", duration)))
     };
-    tz
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        "d" => Ok(Duration::from_secs(value * 60 * 60 * 24)),
+        _ => Err(Error::from(format!("Cannot parse duration: {}. \
+            Supported units are ms, s, m, h and d.", duration)))
+    }
+}
+
+// format nanos since epoch as a UTC datetime
+pub fn format_nanos(nanos: i64, format: &str) -> String {
+    let secs = nanos.div_euclid(1_000_000_000);
+    let nsecs = nanos.rem_euclid(1_000_000_000) as u32;
+    match chrono::DateTime::from_timestamp(secs, nsecs) {
+        Some(datetime) => datetime.format(format).to_string(),
+        None => nanos.to_string()
+    }
 }
 
 #[cfg(test)]
@@ -90,10 +296,88 @@ mod tests {
     #[test]
     fn test_parse_timestamp_range() {
         let timestamp_year = parse_timestamp_range
-            ("2019".to_string(), New_York).unwrap();
+            ("2019".to_string(), New_York, DstPolicy::Error).unwrap();
         let timestamp_datetime = parse_timestamp_range(
-            "20190101-00:00:00".to_string(), New_York).unwrap();
+            "20190101-00:00:00".to_string(), New_York, DstPolicy::Error).unwrap();
         assert_eq!(timestamp_year, 1546318800);
         assert_eq!(timestamp_datetime, 1546318800);
+
+        // embedded offsets win over the time zone
+        let rfc3339 = parse_timestamp_range(
+            "2019-01-01T05:00:00Z".to_string(), New_York, DstPolicy::Error).unwrap();
+        let offset = parse_timestamp_range(
+            "2019-01-01 07:00:00+0200".to_string(), New_York, DstPolicy::Error).unwrap();
+        let iso_local = parse_timestamp_range(
+            "2019-01-01T00:00:00".to_string(), New_York, DstPolicy::Error).unwrap();
+        assert_eq!(rfc3339, 1546318800);
+        assert_eq!(offset, 1546318800);
+        assert_eq!(iso_local, 1546318800);
+    }
+
+    #[test]
+    fn test_dst() {
+        // 2019-11-03 01:30 happens twice in New York
+        let ambiguous = NaiveDateTime::parse_from_str("20191103-01:30:00", "%Y%m%d-%H:%M:%S").unwrap();
+        assert_eq!(localize(&ambiguous, New_York, DstPolicy::Earliest).unwrap(), 1572759000);
+        assert_eq!(localize(&ambiguous, New_York, DstPolicy::Latest).unwrap(), 1572762600);
+        assert!(localize(&ambiguous, New_York, DstPolicy::Error).is_err());
+
+        // 2019-03-10 02:30 does not exist in New York
+        let skipped = NaiveDateTime::parse_from_str("20190310-02:30:00", "%Y%m%d-%H:%M:%S").unwrap();
+        assert_eq!(localize(&skipped, New_York, DstPolicy::Earliest).unwrap(), 1552203000);
+        assert_eq!(localize(&skipped, New_York, DstPolicy::Latest).unwrap(), 1552199400);
+        assert!(localize(&skipped, New_York, DstPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_parse_time_zone() {
+        assert_eq!(parse_time_zone(Some("America/New_York")).unwrap(), New_York);
+        assert_eq!(parse_time_zone(None).unwrap(), DEFAULT_ZONE);
+        assert!(parse_time_zone(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        // 2019-06-03 14:00:00 in New York
+        let now = DateTime::from_timestamp(1559584800, 0).unwrap();
+        let parse = |e: &str| parse_time_bound(e, New_York, DstPolicy::Error, now).unwrap();
+        assert_eq!(parse("now"), TimeBound::Absolute(1559584800));
+        assert_eq!(parse("-1h"), TimeBound::Absolute(1559581200));
+        assert_eq!(parse("+30m"), TimeBound::AfterFirstRow(1800));
+        assert_eq!(parse("today"), TimeBound::Absolute(1559534400));
+        assert_eq!(parse("today 09:30"), TimeBound::Absolute(1559568600));
+        assert_eq!(parse("yesterday 16:00:00"), TimeBound::Absolute(1559505600));
+        assert_eq!(parse("2019-03-01T14:00:00.123456789Z"), TimeBound::Absolute(1551448800));
+        assert!(parse_time_bound("today 25:00", New_York, DstPolicy::Error, now).is_err());
+        assert!(parse_time_bound("-1w", New_York, DstPolicy::Error, now).is_err());
+    }
+
+    #[test]
+    fn test_daily_window() {
+        let window = DailyWindow::parse("09:30-16:00", New_York).unwrap();
+        assert!(window.contains(1559568600)); // 09:30
+        assert!(window.contains(1559584800)); // 14:00
+        assert!(!window.contains(1559592000)); // 16:00
+        assert!(!window.contains(1559534400)); // 00:00
+        let overnight = DailyWindow::parse("18:00-02:00", New_York).unwrap();
+        assert!(overnight.contains(1559534400));
+        assert!(!overnight.contains(1559584800));
+        assert!(DailyWindow::parse("09:30", New_York).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert!(parse_duration("2w").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn test_format_nanos() {
+        assert_eq!(format_nanos(1546318800_000000001, DEFAULT_OUTPUT_FORMAT), "2019-01-01 05:00:00.000000001");
+        assert_eq!(format_nanos(-1, "%Y-%m-%d %H:%M:%S%.9f"), "1969-12-31 23:59:59.999999999");
     }
 }
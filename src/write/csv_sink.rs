@@ -1,22 +1,27 @@
 use std::fs::File;
-use std::io::{self, BufWriter, Write};
+use std::io;
 use std::path::PathBuf;
 
 use crate::chopper::chopper::{DataSink, HeaderSink};
 use crate::chopper::header_graph::PinId;
-use crate::chopper::types::{FieldValue, Header, Row};
+use crate::chopper::types::{DisplayHint, FieldValue, Header, Row};
 use crate::error::{CliResult, Error};
-use crate::source::csv_configs::CSVOutputConfig;
+use crate::source::csv_configs::{CSVOutputConfig, LineTerminator, QuoteStyle};
+use crate::transport::socket::SocketAddress;
+use crate::util::{csv_util, timestamp_util};
 
 pub struct CSVSink {
-    writer: BufWriter<Box<dyn io::Write+'static>>,
-    csv_output_config: CSVOutputConfig
+    writer: csv::Writer<Box<dyn io::Write+'static>>,
+    csv_output_config: CSVOutputConfig,
+    // per field, whether it holds timestamps to format
+    timestamp_fields: Vec<bool>,
 }
 
 impl CSVSink {
     pub fn new(path: &Option<String>, csv_output_config: CSVOutputConfig) -> CliResult<Self> {
-        let writer = BufWriter::new(CSVSink::into_writer(path)?);
-        Ok(CSVSink { writer, csv_output_config })
+        let writer = CSVSink::writer_builder(&csv_output_config)?
+            .from_writer(CSVSink::into_writer(path)?);
+        Ok(CSVSink { writer, csv_output_config, timestamp_fields: Vec::new() })
     }
 
     fn into_writer(path: &Option<String>) -> io::Result<Box<dyn io::Write>> {
@@ -24,6 +29,10 @@ impl CSVSink {
             None => {
                 Ok(Box::new(io::stdout()))
             }
+            // a local stream, see SocketAddress
+            Some(p) if SocketAddress::is_socket_url(p) => {
+                Ok(Box::new(SocketAddress::parse(p)?.open()?))
+            }
             Some(p) => {
                 let path = PathBuf::from(p);
                 let file = File::create(path)?;
@@ -32,21 +41,77 @@ impl CSVSink {
         }
     }
 
+    fn writer_builder(config: &CSVOutputConfig) -> CliResult<csv::WriterBuilder> {
+        let delimiter = csv_util::parse_delimiter(config.delimiter())?;
+        if delimiter.len() != 1 {
+            return Err(Error::from(format!(
+                "CSVSink -- output delimiter must be a single character - {}", config.delimiter())))
+        }
+        let mut builder = csv::WriterBuilder::new();
+        builder.delimiter(delimiter[0])
+            .quote(config.quote())
+            .quote_style(match config.quote_style() {
+                QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                QuoteStyle::Always => csv::QuoteStyle::Always,
+                QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+                QuoteStyle::Never => csv::QuoteStyle::Never,
+            })
+            .terminator(match config.line_terminator() {
+                LineTerminator::Lf => csv::Terminator::Any(b'\n'),
+                LineTerminator::CrLf => csv::Terminator::CRLF,
+                LineTerminator::Cr => csv::Terminator::Any(b'\r'),
+            })
+            // rows may differ in length from the header, e.g. with merged inputs
+            .flexible(true);
+        Ok(builder)
+    }
+
     fn write_csv_header(&mut self, header: &mut Header) -> CliResult<()> {
-        let writer = &mut self.writer;
-        let field_name = header.field_names().clone();
-        let mut first = true;
+        if self.csv_output_config.print_timestamp() {
+            self.writer.write_field("timestamp")?;
+        }
+        for name in header.field_names() {
+            self.writer.write_field(name)?;
+        }
+        self.writer.write_record(None::<&[u8]>)?;
+        Ok(())
+    }
 
-        if self.csv_output_config.print_timestamp() { write!(writer, "timestamp,")?; }
-        for name in field_name {
-            if first {
-                write!(writer, "{}", name)?;
-                first = false;
-            } else {
-                write!(writer, ",{}", name)?;
+    fn write_value(&mut self, i: usize, value: &FieldValue) -> CliResult<()> {
+        if self.timestamp_fields.get(i) == Some(&true) {
+            let nanos = match value {
+                FieldValue::Int(x) => Some(*x as i64),
+                FieldValue::Long(x) => Some(*x),
+                _ => None
+            };
+            if let (Some(nanos), Some(format)) = (nanos, self.csv_output_config.hint_timestamp_format()) {
+                self.writer.write_field(timestamp_util::format_nanos(nanos, format))?;
+                return Ok(())
             }
         }
-        write!(writer, "\n")?;
+        match value {
+            FieldValue::Boolean(_x) =>
+                return Err(Error::from("CSVSink -- boolean field type is not supported")),
+            FieldValue::Byte(x) => self.writer.write_field(x.to_string())?,
+            FieldValue::ByteBuf(_x) =>
+                return Err(Error::from("CSVSink -- ByteBuffer field type is not supported")),
+            FieldValue::Char(x) => self.writer.write_field(x.to_string())?,
+            FieldValue::Double(x) => {
+                let mut buf: Vec<u8> = Vec::new();
+                dtoa::write(&mut buf, *x)?;
+                self.writer.write_field(buf)?
+            },
+            FieldValue::Float(x) => {
+                let mut buf: Vec<u8> = Vec::new();
+                dtoa::write(&mut buf, *x)?;
+                self.writer.write_field(buf)?
+            },
+            FieldValue::Int(x) => self.writer.write_field(x.to_string())?,
+            FieldValue::Long(x) => self.writer.write_field(x.to_string())?,
+            FieldValue::Short(x) => self.writer.write_field(x.to_string())?,
+            FieldValue::String(x) => self.writer.write_field(x)?,
+            FieldValue::None => self.writer.write_field(self.csv_output_config.null_value())?,
+        };
         Ok(())
     }
 }
@@ -54,40 +119,23 @@ impl CSVSink {
 impl HeaderSink for CSVSink {
     fn process_header(mut self: Box<Self>, header: &mut Header) -> CliResult<Box<dyn DataSink>> {
         self.write_csv_header(header)?;
+        let format_hints = self.csv_output_config.hint_timestamp_format().is_some();
+        self.timestamp_fields = header.field_metadata().iter()
+            .map(|m| format_hints && m.display_hint == DisplayHint::Timestamp)
+            .collect();
         Ok(self.boxed())
     }
 }
 
 impl DataSink for CSVSink {
     fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
-        let mut first_col = true;
         if self.csv_output_config.print_timestamp() {
-                write!(self.writer, "{}", row.timestamp)?;
-                first_col = false;
+            self.writer.write_field(row.timestamp.to_string())?;
         }
-        let field_values = &row.field_values;
-        let delimiter = self.csv_output_config.delimiter();
-        for value in field_values {
-            if first_col { first_col = false; }
-            else { write!(self.writer, "{}", delimiter)?; }
-
-            match value {
-                FieldValue::Boolean(_x) =>
-                    return Err(Error::from("CSVSink -- boolean field type is not supported")),
-                FieldValue::Byte(x) => write!(self.writer, "{}", x)?,
-                FieldValue::ByteBuf(_x) =>
-                    return Err(Error::from("CSVSink -- ByteBuffer field type is not supported")),
-                FieldValue::Char(x) => write!(self.writer, "{}", x)?,
-                FieldValue::Double(x) => { dtoa::write(&mut self.writer, *x)?; },
-                FieldValue::Float(x) => { dtoa::write(&mut self.writer, *x)?; },
-                FieldValue::Int(x) => write!(self.writer, "{}", x)?,
-                FieldValue::Long(x) => write!(self.writer, "{}", x)?,
-                FieldValue::Short(x) => write!(self.writer, "{}", x)?,
-                FieldValue::String(x) => write!(self.writer, "{}", x)?,
-                FieldValue::None => (),
-            };
+        for (i, value) in row.field_values.iter().enumerate() {
+            self.write_value(i, value)?;
         }
-        write!(self.writer, "\n")?;
+        self.writer.write_record(None::<&[u8]>)?;
         Ok(None)
     }
 
@@ -100,6 +148,11 @@ impl DataSink for CSVSink {
         Ok(())
     }
 
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        self.flush()?;
+        Ok(Vec::new())
+    }
+
     fn boxed(self) -> Box<dyn DataSink> {
         Box::new(self)
     }
@@ -3,22 +3,44 @@ use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
 
 use byteorder::{BigEndian, WriteBytesExt};
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
 
 use crate::chopper::chopper::{DataSink, HeaderSink};
 use crate::chopper::header_graph::PinId;
-use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+use crate::chopper::types::{DisplayHint, FieldType, FieldValue, Header, Row};
 use crate::error::{CliResult, Error};
-use crate::util::dc_util;
+use crate::source::dc_configs::DCOutputConfig;
+use crate::transport::socket::SocketAddress;
+use crate::util::dc_util::{self, BlockCompression, Footer};
 
 pub struct DCSink {
     writer: BufWriter<Box<dyn io::Write+'static>>,
     bitset_bytes: usize,
+    dc_output_config: DCOutputConfig,
+    // v3 rows are buffered into blocks
+    block: Vec<u8>,
+    block_rows: u32,
+    footer: Option<Footer>,
+    finished: bool,
 }
 
 impl DCSink {
-    pub fn new(path: &Option<String>) -> CliResult<Self> {
+    pub fn new(path: &Option<String>, dc_output_config: DCOutputConfig) -> CliResult<Self> {
         let writer = BufWriter::new(DCSink::into_writer(path)?);
-        Ok(DCSink { writer, bitset_bytes: 0 })
+        Ok(DCSink {
+            writer,
+            bitset_bytes: 0,
+            dc_output_config,
+            block: Vec::new(),
+            block_rows: 0,
+            footer: None,
+            finished: false
+        })
+    }
+
+    fn is_v3(&self) -> bool {
+        self.dc_output_config.version() == dc_util::VERSION_3
     }
 
     fn into_writer(path: &Option<String>) -> io::Result<Box<dyn io::Write>> {
@@ -26,6 +48,10 @@ impl DCSink {
             None => {
                 Ok(Box::new(io::stdout()))
             }
+            // a local stream, see SocketAddress
+            Some(p) if SocketAddress::is_socket_url(p) => {
+                Ok(Box::new(SocketAddress::parse(p)?.open()?))
+            }
             Some(p) => {
                 let path = PathBuf::from(p);
                 let file = File::create(path)?;
@@ -37,8 +63,15 @@ impl DCSink {
     fn write_header(mut dc_sink: &mut DCSink, header: &mut Header) -> CliResult<()> {
         DCSink::write_magic(&mut dc_sink)?;
         DCSink::write_version(&mut dc_sink)?;
-        DCSink::write_empty_user_data(&mut dc_sink)?;
+        DCSink::write_user_header(&mut dc_sink, header)?;
+        if dc_sink.is_v3() {
+            dc_sink.writer.write_u8(dc_sink.dc_output_config.timestamp_unit().code())?;
+            dc_sink.writer.write_u8(dc_sink.dc_output_config.compression().code())?;
+        }
         DCSink::write_field_descriptors(&mut dc_sink, header)?;
+        if dc_sink.is_v3() {
+            DCSink::write_field_metadata(dc_sink, header)?;
+        }
         Ok(())
     }
 
@@ -48,18 +81,25 @@ impl DCSink {
     }
 
     fn write_version(dc_sink: &mut DCSink) -> CliResult<()> {
-        dc_sink.writer.write_u16::<BigEndian>(dc_util::VERSION)?;
+        dc_sink.writer.write_u16::<BigEndian>(dc_sink.dc_output_config.version())?;
         Ok(())
     }
 
-    fn write_empty_user_data(dc_sink: &mut DCSink) -> CliResult<()> {
-        dc_sink.writer.write_u32::<BigEndian>(0)?;
+    // the configured user header takes precedence over the one carried by the input
+    fn write_user_header(dc_sink: &mut DCSink, header: &Header) -> CliResult<()> {
+        let user_header = match dc_sink.dc_output_config.user_header() {
+            Some(u) => u,
+            None => header.user_header().unwrap_or(&[])
+        };
+        dc_sink.writer.write_u32::<BigEndian>(user_header.len() as u32)?;
+        dc_sink.writer.write_all(user_header)?;
         Ok(())
     }
 
     fn write_field_descriptors(dc_sink: &mut DCSink, header: &mut Header) -> CliResult<()> {
         let field_types = header.field_types();
         let field_names = header.field_names();
+        let field_metadata = header.field_metadata();
         let field_count = field_types.len();
 
         // write field count
@@ -70,10 +110,142 @@ impl DCSink {
             return Err(Error::from(
                 "DCSink -- number of field name and number of field types does not match"));
         }
+        if field_metadata.len() != field_types.len() {
+            return Err(Error::from(
+                "DCSink -- number of field metadata and number of field types does not match"));
+        }
         for i in 0..field_types.len() {
             dc_util::write_sized_string(&mut dc_sink.writer, &field_names[i])?;
             DCSink::write_field_type(dc_sink, &field_types[i])?;
-            DCSink::write_display_hint(dc_sink, dc_util::DisplayHint::None)?;
+            // v3 keeps the display hint in the field metadata section
+            if !dc_sink.is_v3() {
+                DCSink::write_display_hint(dc_sink, field_metadata[i].display_hint)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_field_metadata(dc_sink: &mut DCSink, header: &Header) -> CliResult<()> {
+        let mut section: Vec<u8> = Vec::new();
+        for metadata in header.field_metadata() {
+            let mut entries: Vec<(&str, &str)> = Vec::new();
+            if metadata.display_hint == DisplayHint::Timestamp {
+                entries.push(("display_hint", "timestamp"));
+            }
+            for (key, value) in &metadata.properties {
+                entries.push((key, value));
+            }
+            section.write_u32::<BigEndian>(entries.len() as u32)?;
+            for (key, value) in entries {
+                dc_util::write_sized_string(&mut section, key)?;
+                dc_util::write_sized_string(&mut section, value)?;
+            }
+        }
+        dc_sink.writer.write_u32::<BigEndian>(section.len() as u32)?;
+        dc_sink.writer.write_all(&section)?;
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> CliResult<()> {
+        if self.block_rows == 0 {
+            return Ok(())
+        }
+        let stored = match self.dc_output_config.compression() {
+            BlockCompression::None => self.block.clone(),
+            BlockCompression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&self.block)?;
+                encoder.finish()?
+            }
+        };
+        self.writer.write_u32::<BigEndian>(self.block_rows)?;
+        self.writer.write_u32::<BigEndian>(self.block.len() as u32)?;
+        self.writer.write_u32::<BigEndian>(stored.len() as u32)?;
+        self.writer.write_all(&stored)?;
+        self.block.clear();
+        self.block_rows = 0;
+        Ok(())
+    }
+
+    // ends the blocks and writes the footer, with timestamps in file units
+    fn write_footer(&mut self) -> CliResult<()> {
+        self.write_block()?;
+        let footer = self.footer.unwrap_or(Footer { row_count: 0, min_timestamp: 0, max_timestamp: 0 });
+        self.writer.write_u32::<BigEndian>(0)?;
+        self.writer.write_u64::<BigEndian>(footer.row_count)?;
+        self.writer.write_u64::<BigEndian>(footer.min_timestamp)?;
+        self.writer.write_u64::<BigEndian>(footer.max_timestamp)?;
+        self.writer.write_u64::<BigEndian>(dc_util::FOOTER_MAGIC_NUM)?;
+        Ok(())
+    }
+
+    fn write_row_v3(&mut self, row: Row) -> CliResult<()> {
+        let timestamp = row.timestamp / self.dc_output_config.timestamp_unit().nanos_per_unit();
+        self.footer = Some(match self.footer {
+            None => Footer { row_count: 1, min_timestamp: timestamp, max_timestamp: timestamp },
+            Some(f) => Footer {
+                row_count: f.row_count + 1,
+                min_timestamp: f.min_timestamp.min(timestamp),
+                max_timestamp: f.max_timestamp.max(timestamp)
+            }
+        });
+        let mut block = std::mem::take(&mut self.block);
+        DCSink::encode_row(&mut block, timestamp, &row.field_values, self.bitset_bytes)?;
+        self.block = block;
+        self.block_rows += 1;
+        if self.block_rows >= dc_util::BLOCK_ROWS {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    fn encode_row<W: io::Write>(writer: &mut W,
+                                timestamp: u64,
+                                field_values: &[FieldValue],
+                                bitset_byte_count: usize) -> CliResult<()>
+    {
+        // write timestamp
+        writer.write_u64::<BigEndian>(timestamp)?;
+
+        // write bitset
+        let mut bitset_bytes: Vec<u8> = Vec::with_capacity(bitset_byte_count);
+        let mut field_count = 0;
+
+        for _i in 0..bitset_byte_count {
+            let mut current_bitset: u8 = 0;
+            let mut current_bit = 0;
+            for _j in 0..8 {
+                if field_count >= field_values.len() {
+                    break;
+                }
+                match &field_values.get(field_count).unwrap() {
+                    FieldValue::None => current_bitset += 2_u8.pow(current_bit),
+                    _ => current_bitset = current_bitset,
+                }
+                current_bit += 1;
+                field_count += 1;
+            }
+            bitset_bytes.push(current_bitset);
+        }
+        writer.write_all(&bitset_bytes)?;
+
+        // write row values
+        for value in field_values {
+            match value {
+                FieldValue::Boolean(_x) =>
+                    return Err(Error::from("DCSink -- boolean field type is not supported")),
+                FieldValue::Byte(x) => writer.write_u8(*x)?,
+                FieldValue::ByteBuf(_x) =>
+                    return Err(Error::from("DCSink -- ByteBuffer field type is not supported")),
+                FieldValue::Char(x) => writer.write_u16::<BigEndian>(*x)?,
+                FieldValue::Double(x) => writer.write_f64::<BigEndian>(*x)?,
+                FieldValue::Float(x) => writer.write_f32::<BigEndian>(*x)?,
+                FieldValue::Int(x) => writer.write_i32::<BigEndian>(*x)?,
+                FieldValue::Long(x) => writer.write_i64::<BigEndian>(*x)?,
+                FieldValue::Short(x) => writer.write_i16::<BigEndian>(*x)?,
+                FieldValue::String(x) => dc_util::write_string_value(writer, &x)?,
+                FieldValue::None => continue,
+            };
         }
         Ok(())
     }
@@ -115,7 +287,7 @@ impl DCSink {
 impl HeaderSink for DCSink {
     fn process_header(mut self: Box<Self>, header: &mut Header) -> CliResult<Box<dyn DataSink>> {
         Self::write_header(&mut self, header)?;
-        let bitset_bytes = dc_util::get_bitset_bytes(header.field_types().len()-1);
+        let bitset_bytes = dc_util::get_bitset_bytes(header.field_types().len());
         self.bitset_bytes = bitset_bytes;
         Ok(self.boxed())
     }
@@ -123,49 +295,10 @@ impl HeaderSink for DCSink {
 
 impl DataSink for DCSink {
     fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
-        // write timestamp
-        self.writer.write_u64::<BigEndian>(row.timestamp)?;
-
-        // write bitset
-        let field_values = &row.field_values;
-        let mut bitset_bytes: Vec<u8> = Vec::with_capacity(self.bitset_bytes);
-        let mut field_count = 0;
-
-        for _i in 0..self.bitset_bytes {
-            let mut current_bitset: u8 = 0;
-            let mut current_bit = 0;
-            for _j in 0..8 {
-                if field_count >= field_values.len() {
-                    break;
-                }
-                match &field_values.get(field_count).unwrap() {
-                    FieldValue::None => current_bitset += 2_u8.pow(current_bit),
-                    _ => current_bitset = current_bitset,
-                }
-                current_bit += 1;
-                field_count += 1;
-            }
-            bitset_bytes.push(current_bitset);
-        }
-        self.writer.write_all(&bitset_bytes)?;
-
-        // write row values
-        for value in field_values {
-            match value {
-                FieldValue::Boolean(_x) =>
-                    return Err(Error::from("DCSink -- boolean field type is not supported")),
-                FieldValue::Byte(x) => self.writer.write_u8(*x)?,
-                FieldValue::ByteBuf(_x) =>
-                    return Err(Error::from("DCSink -- ByteBuffer field type is not supported")),
-                FieldValue::Char(x) => self.writer.write_u16::<BigEndian>(*x)?,
-                FieldValue::Double(x) => self.writer.write_f64::<BigEndian>(*x)?,
-                FieldValue::Float(x) => self.writer.write_f32::<BigEndian>(*x)?,
-                FieldValue::Int(x) => self.writer.write_i32::<BigEndian>(*x)?,
-                FieldValue::Long(x) => self.writer.write_i64::<BigEndian>(*x)?,
-                FieldValue::Short(x) => self.writer.write_i16::<BigEndian>(*x)?,
-                FieldValue::String(x) => dc_util::write_string_value(&mut self.writer, &x)?,
-                FieldValue::None => continue,
-            };
+        if self.is_v3() {
+            self.write_row_v3(row)?;
+        } else {
+            DCSink::encode_row(&mut self.writer, row.timestamp, &row.field_values, self.bitset_bytes)?;
         }
         Ok(None)
     }
@@ -179,6 +312,15 @@ impl DataSink for DCSink {
         Ok(())
     }
 
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        if self.is_v3() && !self.finished {
+            self.write_footer()?;
+        }
+        self.finished = true;
+        self.flush()?;
+        Ok(Vec::new())
+    }
+
     fn boxed(self) -> Box<dyn DataSink> {
         Box::new(self)
     }
@@ -1,24 +1,36 @@
 use crate::chopper::chopper::HeaderSink;
 use crate::error::{CliResult, Error};
 use crate::source::csv_configs::CSVOutputConfig;
+use crate::source::dc_configs::DCOutputConfig;
+use crate::transport::socket::SocketAddress;
 use crate::write::csv_sink;
 use crate::write::dc_sink;
 
 pub fn new_header_sink(output: Option<&str>,
-                       csv_output_config: Option<CSVOutputConfig>) -> CliResult<Box<dyn HeaderSink +'static>>
+                       csv_output_config: Option<CSVOutputConfig>,
+                       dc_output_config: Option<DCOutputConfig>) -> CliResult<Box<dyn HeaderSink +'static>>
 {
     let csv_output_config = match csv_output_config {
         Some(c) => c,
         None => CSVOutputConfig::new_default()
     };
+    let dc_output_config = match dc_output_config {
+        Some(c) => c,
+        None => DCOutputConfig::new_default()
+    };
     let writer: Box<dyn HeaderSink +'static>;
     match output {
         Some(p) => {
             let p = p.to_string();
-            if p.ends_with("csv") {
+            // streams carry the format in the address
+            let format = match SocketAddress::is_socket_url(&p) {
+                true => SocketAddress::parse(&p)?.format,
+                false => p.clone()
+            };
+            if format.ends_with("csv") {
                 writer = Box::new(csv_sink::CSVSink::new(&Some(p), csv_output_config)?);
-            } else if p.ends_with("dc") {
-                writer = Box::new(dc_sink::DCSink::new(&Some(p))?);
+            } else if format.ends_with("dc") {
+                writer = Box::new(dc_sink::DCSink::new(&Some(p), dc_output_config)?);
             } else {
                 return Err(Error::from(format!("file type -- {} is not supported", p)))
             }
@@ -0,0 +1,19 @@
+use chrono::DateTime;
+use chrono_tz::America::New_York;
+
+use chopper_lib::cli_app::CliApp;
+use chopper_lib::util::timestamp_util::{self, DstPolicy, TimeBound};
+
+#[test]
+fn test_relative_begin_end_args() {
+    // values starting with '-' are read as values, not flags
+    let matches = CliApp.create_cli_app()
+        .get_matches_from_safe(vec!["chop", "--begin", "-1h", "-e", "-30m", "in.csv"])
+        .unwrap();
+    let now = DateTime::from_timestamp(1559584800, 0).unwrap();
+    let parse = |arg| timestamp_util::parse_time_bound(
+        matches.value_of(arg).unwrap(), New_York, DstPolicy::Error, now).unwrap();
+    assert_eq!(parse("begin"), TimeBound::Absolute(1559584800 - 3600));
+    assert_eq!(parse("end"), TimeBound::Absolute(1559584800 - 1800));
+    assert_eq!(matches.values_of("input").unwrap().collect::<Vec<_>>(), vec!["in.csv"]);
+}
@@ -0,0 +1,295 @@
+use std::fs;
+use std::io::{self, Read};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chopper_lib::chopper::chopper::{HeaderSink, Source};
+use chopper_lib::chopper::types::{FieldType, FieldValue, Header, Row};
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::source::csv_configs::{BadRowPolicy, CSVInputConfig, CSVOutputConfig, EscapeStyle,
+                                       LineTerminator, QuoteStyle, TimestampCol, TimestampConfig};
+use chopper_lib::source::csv_source::CSVSource;
+use chopper_lib::transport::deadline::DeadlineReader;
+use chopper_lib::util::csv_util;
+use chopper_lib::util::timestamp_util::DstPolicy;
+use chopper_lib::write::csv_sink::CSVSink;
+
+fn read_csv(data: &str, config: &CSVInputConfig) -> (Header, Vec<Row>) {
+    let mut source = CSVSource::new(io::Cursor::new(data.as_bytes().to_vec()), config).unwrap();
+    let mut rows = Vec::new();
+    while let Some(row) = Source::next_row(&mut source).unwrap() {
+        rows.push(row);
+    }
+    (source.header().clone(), rows)
+}
+
+fn string(s: &str) -> FieldValue {
+    FieldValue::String(s.to_string())
+}
+
+#[test]
+fn test_parse_delimiter() {
+    assert_eq!(csv_util::parse_delimiter(",").unwrap(), b",".to_vec());
+    assert_eq!(csv_util::parse_delimiter("||").unwrap(), b"||".to_vec());
+    assert_eq!(csv_util::parse_delimiter(r"\t").unwrap(), b"\t".to_vec());
+    assert_eq!(csv_util::parse_delimiter(r"\x01").unwrap(), vec![1u8]);
+    assert!(csv_util::parse_delimiter("").is_err());
+    assert!(csv_util::parse_delimiter(r"\q").is_err());
+    assert!(csv_util::parse_delimiter("é").is_err());
+}
+
+#[test]
+fn test_multi_byte_delimiter() {
+    let data = "time||name||note\n\
+                1500000000||a||\"x||y\"\n\
+                1500000001||b||z\n";
+    let config = CSVInputConfig::new("||", true, TimestampConfig::default()).unwrap();
+    let (header, rows) = read_csv(data, &config);
+    assert_eq!(header.field_names(), &vec!["time".to_string(), "name".to_string(), "note".to_string()]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].timestamp, 1500000001);
+    assert!(rows[0].field_values[2] == string("x||y"));
+    assert!(rows[1].field_values[2] == string("z"));
+}
+
+#[test]
+fn test_dialect() {
+    let data = "generated by a logger\n\
+                version 2\n\
+                time,name,note\n\
+                # a comment\n\
+                1500000000,  a ,\"say \\\"hi\\\"\"\n\
+                1500000001,NA,\n\
+                1500000002,\\N\n";
+    let mut config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+    config.set_skip_lines(2);
+    config.set_comment(Some(b'#'));
+    config.set_escape(EscapeStyle::Backslash);
+    config.set_flexible(true);
+    config.set_null_values(vec!["NA".to_string(), "\\N".to_string(), "".to_string()]);
+    let (header, rows) = read_csv(data, &config);
+    assert_eq!(header.field_names().len(), 3);
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].field_values[1] == string("a"));
+    assert!(rows[0].field_values[2] == string("say \"hi\""));
+    assert!(rows[1].field_values[1] == FieldValue::None);
+    assert!(rows[1].field_values[2] == FieldValue::None);
+    // short row padded with nulls
+    assert!(rows[2].field_values[1] == FieldValue::None);
+    assert!(rows[2].field_values[2] == FieldValue::None);
+
+    // no quoting keeps quotes in values
+    let mut config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+    config.set_quote(None);
+    let (_, rows) = read_csv("time,note\n1500000000,\"a\"\n", &config);
+    assert!(rows[0].field_values[1] == string("\"a\""));
+}
+
+#[test]
+fn test_output_quoting() {
+    let path = "tests/output/test_csv_quoting.csv";
+    let write = |quote_style: QuoteStyle| {
+        let mut config = CSVOutputConfig::new(",", false);
+        config.set_quote_style(quote_style);
+        let sink = Box::new(CSVSink::new(&Some(path.to_string()), config).unwrap());
+        let mut header = Header::new(vec!["name".to_string(), "value".to_string()],
+                                     vec![FieldType::String, FieldType::Long]);
+        let mut data_sink = sink.process_header(&mut header).unwrap();
+        data_sink.write_row(Row { timestamp: 0, field_values: vec![string("a,\"b\""), FieldValue::Long(1)] }).unwrap();
+        data_sink.write_row(Row { timestamp: 0, field_values: vec![string("2.5"), FieldValue::Long(2)] }).unwrap();
+        data_sink.finish().unwrap();
+        fs::read_to_string(path).unwrap()
+    };
+    assert_eq!(write(QuoteStyle::Necessary), "name,value\n\"a,\"\"b\"\"\",1\n2.5,2\n");
+    assert_eq!(write(QuoteStyle::Always), "\"name\",\"value\"\n\"a,\"\"b\"\"\",\"1\"\n\"2.5\",\"2\"\n");
+    assert_eq!(write(QuoteStyle::NonNumeric), "\"name\",\"value\"\n\"a,\"\"b\"\"\",1\n2.5,2\n");
+    assert_eq!(write(QuoteStyle::Never), "name,value\na,\"b\",1\n2.5,2\n");
+}
+
+#[test]
+fn test_output_escaping() {
+    let path = "tests/output/test_csv_escaping.csv";
+    let mut config = CSVOutputConfig::new(r"\t", true);
+    config.set_line_terminator(LineTerminator::CrLf);
+    config.set_null_value("\\N".to_string());
+    let sink = Box::new(CSVSink::new(&Some(path.to_string()), config).unwrap());
+    let mut header = Header::new(vec!["a\tb".to_string(), "c".to_string()],
+                                 vec![FieldType::String, FieldType::String]);
+    let mut data_sink = sink.process_header(&mut header).unwrap();
+    data_sink.write_row(Row { timestamp: 1500000000, field_values: vec![string("x\ny"), FieldValue::None] }).unwrap();
+    data_sink.finish().unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "timestamp\t\"a\tb\"\tc\r\n1500000000\t\"x\ny\"\t\\N\r\n");
+
+    // read back
+    let mut config = CSVInputConfig::new(r"\t", true, TimestampConfig::default()).unwrap();
+    config.set_null_values(vec!["\\N".to_string()]);
+    let (header, rows) = read_csv(&fs::read_to_string(path).unwrap(), &config);
+    assert_eq!(header.field_names()[1], "a\tb");
+    assert!(rows[0].field_values[1] == string("x\ny"));
+    assert!(rows[0].field_values[2] == FieldValue::None);
+
+    assert!(CSVSink::new(&Some(path.to_string()), CSVOutputConfig::new("||", true)).is_err());
+}
+
+#[test]
+fn test_bad_rows() {
+    let data = "time,name\n\
+                1500000000,a\n\
+                1500000001,b,extra\n\
+                1500000002\n\
+                1500000003,d\n";
+    let read_with = |policy: BadRowPolicy| {
+        let mut config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+        config.set_bad_row_policy(policy);
+        let mut source = CSVSource::new(io::Cursor::new(data.as_bytes().to_vec()), &config).unwrap();
+        let mut rows = Vec::new();
+        loop {
+            match Source::next_row(&mut source) {
+                Ok(Some(row)) => rows.push(row),
+                Ok(None) => return Ok((rows, source.warnings())),
+                Err(e) => return Err(e.to_string())
+            }
+        }
+    };
+
+    let error = read_with(BadRowPolicy::Error).unwrap_err();
+    assert!(error.contains("line 3"), "{}", error);
+
+    let (rows, skipped) = read_with(BadRowPolicy::Skip).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].contains("skipped 2 bad rows"), "{:?}", skipped);
+    assert_eq!(rows[1].timestamp, 1500000003);
+
+    // a local file, opened for random access
+    let path = "tests/output/test_csv_bad_rows.csv";
+    fs::write(path, data).unwrap();
+    let mut config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+    config.set_bad_row_policy(BadRowPolicy::Skip);
+    let mut source = InputFactory::new(Some(config), None, None, None).unwrap()
+        .create_source_from_path(path).unwrap();
+    while source.next_row().unwrap().is_some() {}
+    assert_eq!(source.warnings(), vec!["CSVReader -- skipped 2 bad rows".to_string()]);
+
+    let error = read_with(BadRowPolicy::Pad).unwrap_err();
+    assert!(error.contains("line 3"), "{}", error);
+
+    let error = read_with(BadRowPolicy::Truncate).unwrap_err();
+    assert!(error.contains("line 4"), "{}", error);
+
+    // rejected rows side output, with the rows as they were read
+    let path = "tests/output/test_csv_rejected.csv";
+    let mut config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+    config.set_bad_row_policy(BadRowPolicy::Skip);
+    config.set_rejected_rows(Some(csv_util::RejectedRows::new(path).unwrap()));
+    let (_, rows) = read_csv(&format!("{}1500000004,\"e,f\",g\r\n1500000005,h\n", data), &config);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(fs::read_to_string(path).unwrap(),
+               "line,reason,raw\n\
+                3,\"found 3 fields, expected 2\",\"1500000001,b,extra\"\n\
+                4,\"found 1 fields, expected 2\",1500000002\n\
+                6,\"found 3 fields, expected 2\",\"1500000004,\"\"e,f\"\",g\"\n");
+
+    // multi-byte delimiter
+    let mut config = CSVInputConfig::new("||", true, TimestampConfig::default()).unwrap();
+    config.set_bad_row_policy(BadRowPolicy::Skip);
+    config.set_rejected_rows(Some(csv_util::RejectedRows::new(path).unwrap()));
+    let (_, rows) = read_csv("time||name\n1500000000||a||b\n1500000001||c\n", &config);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(fs::read_to_string(path).unwrap(),
+               "line,reason,raw\n2,\"found 3 fields, expected 2\",1500000000||a||b\n");
+}
+
+#[test]
+fn test_timestamp_column_by_name() {
+    let data = "id,Time,value\n\
+                1,1500000000,a\n\
+                2,1500000001,b\n";
+    let read_with = |timestamp_col: TimestampCol, drop_columns: bool| {
+        let mut ts_config = TimestampConfig::new(timestamp_col, None, chrono_tz::UTC);
+        ts_config.set_drop_columns(drop_columns);
+        let config = CSVInputConfig::new(",", true, ts_config).unwrap();
+        read_csv(data, &config)
+    };
+
+    let (header, rows) = read_with(TimestampCol::TimestampName("Time".to_string()), false);
+    assert_eq!(header.field_names().len(), 3);
+    assert_eq!(rows[1].timestamp, 1500000001);
+
+    // auto-detected by name, and dropped from the fields
+    let (header, rows) = read_with(TimestampCol::Auto, true);
+    assert_eq!(header.field_names(), &vec!["id".to_string(), "value".to_string()]);
+    assert_eq!(rows[0].timestamp, 1500000000);
+    assert!(rows[0].field_values == vec![string("1"), string("a")]);
+
+    // auto-detected by a date time value
+    let data = "id,when\n1,20190102-03:04:05\n";
+    let config = CSVInputConfig::new(",", true,
+                                     TimestampConfig::new(TimestampCol::Auto, None, chrono_tz::UTC)).unwrap();
+    let (_, rows) = read_csv(data, &config);
+    assert_eq!(rows[0].timestamp, 1546398245);
+
+    let config = CSVInputConfig::new(",", true, TimestampConfig::new(
+        TimestampCol::TimestampName("missing".to_string()), None, chrono_tz::UTC)).unwrap();
+    assert!(CSVSource::new(io::Cursor::new(data.as_bytes().to_vec()), &config).is_err());
+}
+
+#[test]
+fn test_timestamp_offsets_and_dst() {
+    // offsets in the text win over the configured time zone
+    let data = "time,value\n\
+                2019-01-01T05:00:00Z,a\n\
+                2019-01-01T01:00:00-04:00,b\n";
+    let ts_config = TimestampConfig::new(TimestampCol::Timestamp(0), None, chrono_tz::America::New_York);
+    let config = CSVInputConfig::new(",", true, ts_config).unwrap();
+    let (_, rows) = read_csv(data, &config);
+    assert_eq!(rows[0].timestamp, 1546318800);
+    assert_eq!(rows[1].timestamp, 1546318800);
+
+    // 01:30 happens twice in New York on 2019-11-03
+    let data = "time,value\n20191103-01:30:00,a\n";
+    let read_with = |dst_policy: DstPolicy| {
+        let mut ts_config = TimestampConfig::new(TimestampCol::Timestamp(0), None, chrono_tz::America::New_York);
+        ts_config.set_dst_policy(dst_policy);
+        let config = CSVInputConfig::new(",", true, ts_config).unwrap();
+        CSVSource::new(io::Cursor::new(data.as_bytes().to_vec()), &config)
+            .map(|mut s| Source::next_row(&mut s).unwrap().unwrap().timestamp)
+    };
+    assert_eq!(read_with(DstPolicy::Earliest).unwrap(), 1572759000);
+    assert_eq!(read_with(DstPolicy::Latest).unwrap(), 1572762600);
+    assert!(read_with(DstPolicy::Error).is_err());
+}
+
+// sends the data, then no more data for a long time
+struct Stalling {
+    data: Option<&'static [u8]>,
+}
+
+impl Read for Stalling {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.data.take() {
+            Some(data) => {
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+            None => {
+                thread::sleep(Duration::from_secs(60));
+                Ok(0)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_deadline_within_record() {
+    let stalling = Stalling { data: Some(b"ts,a\n1500000000,x\n1500000001,y\n15000") };
+    let reader = DeadlineReader::new(Box::new(stalling), Instant::now() + Duration::from_millis(200));
+    let config = CSVInputConfig::new(",", true, TimestampConfig::default()).unwrap();
+    let mut source = CSVSource::new(reader, &config).unwrap();
+    let mut timestamps = Vec::new();
+    while let Some(row) = Source::next_row(&mut source).unwrap() {
+        timestamps.push(row.timestamp);
+    }
+    // the partial trailing record is dropped
+    assert_eq!(timestamps, vec![1500000000, 1500000001]);
+}
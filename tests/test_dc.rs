@@ -0,0 +1,247 @@
+use std::fs::File;
+
+use chopper_lib::chopper::chopper::{HeaderSink, Source};
+use chopper_lib::chopper::types::{DisplayHint, FieldType, FieldValue, Header, Row};
+use chopper_lib::error::CliResult;
+use chopper_lib::filter::column_filter_delete_col::ColumnFilterDelete;
+use chopper_lib::source::csv_configs::CSVOutputConfig;
+use chopper_lib::source::dc_configs::{DCInputConfig, DCOutputConfig, InvalidUtf8};
+use chopper_lib::source::dc_source::DCSource;
+use chopper_lib::util::dc_util::{self, BlockCompression, TimestampUnit};
+use chopper_lib::write::csv_sink::CSVSink;
+use chopper_lib::write::dc_sink::DCSink;
+
+fn write_dc(path: &str, mut header: Header, rows: Vec<Row>, config: DCOutputConfig) -> CliResult<()> {
+    let sink = Box::new(DCSink::new(&Some(path.to_string()), config)?);
+    let mut data_sink = sink.process_header(&mut header)?;
+    for row in rows {
+        data_sink.write_row(row)?;
+    }
+    data_sink.finish()?;
+    Ok(())
+}
+
+fn read_dc(path: &str) -> CliResult<(Header, Vec<Row>)> {
+    let mut source = DCSource::new(File::open(path)?)?;
+    let mut rows = Vec::new();
+    while let Some(row) = Source::next_row(&mut source)? {
+        rows.push(row);
+    }
+    Ok((source.header().clone(), rows))
+}
+
+fn rows() -> Vec<Row> {
+    vec![Row { timestamp: 1, field_values: vec![FieldValue::Long(10), FieldValue::String("a".to_string())] },
+         Row { timestamp: 2, field_values: vec![FieldValue::None, FieldValue::String("b".to_string())] }]
+}
+
+#[test]
+fn test_user_header() {
+    let header = Header::new(vec!["l".to_string(), "s".to_string()], vec![FieldType::Long, FieldType::String]);
+
+    // written from config
+    let user_header = b"instrument=ESZ9".to_vec();
+    write_dc("tests/output/test_dc_user_header.dc", header,
+             rows(), DCOutputConfig::new(Some(user_header.clone()))).unwrap();
+    let (header, read_rows) = read_dc("tests/output/test_dc_user_header.dc").unwrap();
+    assert_eq!(header.user_header(), Some(user_header.as_slice()));
+    assert_eq!(read_rows.len(), 2);
+
+    // preserved dc to dc
+    write_dc("tests/output/test_dc_user_header_copy.dc", header.clone(),
+             read_rows.clone(), DCOutputConfig::new_default()).unwrap();
+    let (copy_header, copy_rows) = read_dc("tests/output/test_dc_user_header_copy.dc").unwrap();
+    assert_eq!(copy_header.user_header(), Some(user_header.as_slice()));
+    assert!(copy_rows[1].field_values[1] == FieldValue::String("b".to_string()));
+
+    // overridden
+    write_dc("tests/output/test_dc_user_header_copy.dc", header,
+             read_rows, DCOutputConfig::new(Some(Vec::new()))).unwrap();
+    let (copy_header, _) = read_dc("tests/output/test_dc_user_header_copy.dc").unwrap();
+    assert_eq!(copy_header.user_header(), Some(&[][..]));
+}
+
+#[test]
+fn test_display_hints() {
+    let mut header = Header::new(
+        vec!["s".to_string(), "t".to_string()], vec![FieldType::String, FieldType::Long]);
+    header.field_metadata_mut()[1].display_hint = DisplayHint::Timestamp;
+    let rows = vec![Row { timestamp: 1, field_values: vec![
+        FieldValue::String("a".to_string()), FieldValue::Long(1546318800_000000001)] }];
+
+    // preserved dc to dc
+    write_dc("tests/output/test_dc_hints.dc", header, rows, DCOutputConfig::new_default()).unwrap();
+    let (header, rows) = read_dc("tests/output/test_dc_hints.dc").unwrap();
+    assert_eq!(header.field_metadata()[0].display_hint, DisplayHint::None);
+    assert_eq!(header.field_metadata()[1].display_hint, DisplayHint::Timestamp);
+
+    // moved along by a column filter
+    let mut filtered_header = header.clone();
+    ColumnFilterDelete::new("s").process_header(&mut filtered_header).unwrap();
+    assert_eq!(filtered_header.field_metadata().len(), 1);
+    assert_eq!(filtered_header.field_metadata()[0].display_hint, DisplayHint::Timestamp);
+
+    // formatted in csv
+    let path = "tests/output/test_dc_hints.csv";
+    let sink = Box::new(CSVSink::new(&Some(path.to_string()), CSVOutputConfig::new(",", false)).unwrap());
+    let mut data_sink = sink.process_header(&mut header.clone()).unwrap();
+    for row in rows {
+        data_sink.write_row(row).unwrap();
+    }
+    data_sink.finish().unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "s,t\na,2019-01-01 05:00:00.000000001\n");
+}
+
+#[test]
+fn test_v3() {
+    let mut header = Header::new(vec!["l".to_string(), "s".to_string()], vec![FieldType::Long, FieldType::String]);
+    header.field_metadata_mut()[0].display_hint = DisplayHint::Timestamp;
+    header.field_metadata_mut()[1].properties.push(("unit".to_string(), "usd".to_string()));
+    header.set_user_header(Some(b"v3".to_vec()));
+    let row_count = dc_util::BLOCK_ROWS as u64 * 2 + 3;
+    let rows: Vec<Row> = (0..row_count).map(|i| Row {
+        timestamp: 1_000_000 + i * 1_000,
+        field_values: vec![FieldValue::Long(i as i64),
+                           if i % 3 == 0 { FieldValue::None } else { FieldValue::String(i.to_string()) }]
+    }).collect();
+
+    for (unit, compression) in vec![(TimestampUnit::Nanos, BlockCompression::Deflate),
+                                    (TimestampUnit::Micros, BlockCompression::None)] {
+        let path = "tests/output/test_dc_v3.dc";
+        let mut config = DCOutputConfig::new_default();
+        config.set_version(dc_util::VERSION_3).unwrap();
+        config.set_timestamp_unit(unit);
+        config.set_compression(compression);
+        write_dc(path, header.clone(), rows.clone(), config).unwrap();
+
+        let mut source = DCSource::new(File::open(path).unwrap()).unwrap();
+        let read_header = source.header().clone();
+        assert_eq!(read_header.user_header(), Some(&b"v3"[..]));
+        assert!(read_header.field_metadata() == header.field_metadata());
+        let mut count = 0;
+        while let Some(row) = Source::next_row(&mut source).unwrap() {
+            let expected = &rows[count];
+            assert_eq!(row.timestamp, expected.timestamp);
+            assert!(row.field_values[0] == expected.field_values[0]);
+            assert!(row.field_values[1] == expected.field_values[1]);
+            count += 1;
+        }
+        assert_eq!(count as u64, row_count);
+        let footer = source.footer().unwrap();
+        assert_eq!(footer.row_count, row_count);
+        assert_eq!(footer.min_timestamp, 1_000_000);
+        assert_eq!(footer.max_timestamp, 1_000_000 + (row_count - 1) * 1_000);
+    }
+}
+
+#[test]
+fn test_timestamp_out_of_range() {
+    let path = "tests/output/test_dc_v3_range.dc";
+    let header = Header::new(vec!["l".to_string()], vec![FieldType::Long]);
+    let micros: u64 = 0x1122334455;
+    let rows = vec![Row { timestamp: micros * 1_000, field_values: vec![FieldValue::Long(1)] }];
+    let mut config = DCOutputConfig::new_default();
+    config.set_version(dc_util::VERSION_3).unwrap();
+    config.set_timestamp_unit(TimestampUnit::Micros);
+    write_dc(path, header, rows, config).unwrap();
+    let mut bytes = std::fs::read(path).unwrap();
+    // the first is the row, then the footer's min and max
+    let offset = bytes.windows(8).position(|w| w == micros.to_be_bytes()).unwrap();
+    bytes[offset..offset + 8].copy_from_slice(&u64::MAX.to_be_bytes());
+    match read_all(&bytes, &DCInputConfig::new_default()) {
+        Err(e) => assert!(e.to_string().contains("is out of range"), "{}", e),
+        Ok(_) => panic!("timestamp out of range should not be read")
+    }
+}
+
+#[test]
+fn test_unsupported_version() {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(&dc_util::MAGIC_NUM.to_be_bytes());
+    bytes.extend_from_slice(&9u16.to_be_bytes());
+    match DCSource::new(bytes.as_slice()) {
+        Err(e) => assert!(e.to_string().contains("unsupported version - 9")),
+        Ok(_) => panic!("version 9 should not be readable")
+    }
+}
+
+fn read_all(bytes: &[u8], config: &DCInputConfig) -> CliResult<Vec<Row>> {
+    let mut source = DCSource::new_with_config(bytes, config)?;
+    let mut rows = Vec::new();
+    while let Some(row) = Source::next_row(&mut source)? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[test]
+fn test_corruption() {
+    let header = Header::new(vec!["l".to_string(), "s".to_string()], vec![FieldType::Long, FieldType::String]);
+    write_dc("tests/output/test_dc_corruption.dc", header, rows(), DCOutputConfig::new_default()).unwrap();
+    let bytes = std::fs::read("tests/output/test_dc_corruption.dc").unwrap();
+    let strict = DCInputConfig::new_default();
+    let lenient = DCInputConfig::new(true, InvalidUtf8::Error);
+
+    // clean end of file
+    assert_eq!(read_all(&bytes, &strict).unwrap().len(), 2);
+
+    // the last row is 12 bytes: timestamp, bitset and a one byte string
+    let last_row_offset = bytes.len() - 12;
+    let truncated = &bytes[..bytes.len() - 1];
+    match read_all(truncated, &strict) {
+        Err(e) => assert!(e.to_string().contains(&format!("truncated row at byte offset {}", last_row_offset))),
+        Ok(_) => panic!("truncated file should not be read")
+    }
+    assert_eq!(read_all(truncated, &lenient).unwrap().len(), 1);
+
+    // negative string length in the last row
+    let mut negative = bytes.clone();
+    let length_offset = bytes.len() - 3;
+    negative[length_offset] = 0x80;
+    match read_all(&negative, &strict) {
+        Err(e) => assert!(e.to_string().contains(
+            &format!("negative string length -32767 at byte offset {}", last_row_offset)), "{}", e),
+        Ok(_) => panic!("negative string length should not be read")
+    }
+    assert_eq!(read_all(&negative, &lenient).unwrap().len(), 1);
+    let mut source = DCSource::new_with_config(&negative[..], &lenient).unwrap();
+    while Source::next_row(&mut source).unwrap().is_some() {}
+    assert_eq!(source.warnings(), vec![format!(
+        "DCReader -- negative string length -32767 at byte offset {}; stopped reading", last_row_offset)]);
+
+    // a string length far beyond the end of the file
+    let mut oversized = bytes[..bytes.len() - 3].to_vec();
+    oversized.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xf0, b'a']);
+    match read_all(&oversized, &strict) {
+        Err(e) => assert!(e.to_string().contains(&format!("truncated row at byte offset {}", last_row_offset))),
+        Ok(_) => panic!("oversized string should not be read")
+    }
+
+    // a user header size far beyond the end of the file
+    let mut oversized = bytes.clone();
+    oversized[10..14].copy_from_slice(&[0xff, 0xff, 0xff, 0xf0]);
+    assert!(read_all(&oversized, &strict).is_err());
+
+    // invalid utf-8 in the last string value
+    let mut invalid = bytes.clone();
+    let last = invalid.len() - 1;
+    invalid[last] = 0xff;
+    assert!(read_all(&invalid, &strict).is_err());
+    let lossy = read_all(&invalid, &DCInputConfig::new(false, InvalidUtf8::Lossy)).unwrap();
+    assert!(lossy[1].field_values[1] == FieldValue::String("\u{fffd}".to_string()));
+}
+
+#[test]
+fn test_unknown_field_type() {
+    let header = Header::new(vec!["l".to_string(), "m".to_string()], vec![FieldType::Long, FieldType::Long]);
+    write_dc("tests/output/test_dc_unknown_type.dc", header, Vec::new(), DCOutputConfig::new_default()).unwrap();
+    let mut bytes = std::fs::read("tests/output/test_dc_unknown_type.dc").unwrap();
+    // type string "J" right after magic, version, user header size, field count and the name "l"
+    let type_offset = 8 + 2 + 4 + 4 + 4 + 1 + 4;
+    assert_eq!(bytes[type_offset], b'J');
+    bytes[type_offset] = b'X';
+    match DCSource::new(bytes.as_slice()) {
+        Err(e) => assert!(e.to_string().contains("unknown field type - X")),
+        Ok(_) => panic!("unknown field type should not be read")
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::File;
+
+use proptest::prelude::*;
+
+use chopper_lib::chopper::chopper::{HeaderSink, Source};
+use chopper_lib::chopper::types::{FieldType, FieldValue, Header, Row};
+use chopper_lib::error::CliResult;
+use chopper_lib::source::dc_configs::DCOutputConfig;
+use chopper_lib::source::dc_source::DCSource;
+use chopper_lib::util::dc_util;
+use chopper_lib::write::dc_sink::DCSink;
+
+// field types the dc format supports
+fn field_type() -> impl Strategy<Value = FieldType> {
+    prop_oneof![
+        Just(FieldType::Byte),
+        Just(FieldType::Char),
+        Just(FieldType::Double),
+        Just(FieldType::Float),
+        Just(FieldType::Int),
+        Just(FieldType::Long),
+        Just(FieldType::Short),
+        Just(FieldType::String),
+    ]
+}
+
+fn string_value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        9 => ".{0,20}",
+        // longer than i16::MAX bytes, written with a u32 size
+        1 => (0..3usize).prop_map(|extra| "x".repeat(std::i16::MAX as usize + extra)),
+    ]
+}
+
+fn field_value(field_type: FieldType) -> BoxedStrategy<FieldValue> {
+    let value = match field_type {
+        FieldType::Byte => any::<u8>().prop_map(FieldValue::Byte).boxed(),
+        FieldType::Char => any::<u16>().prop_map(FieldValue::Char).boxed(),
+        FieldType::Double => any::<f64>().prop_map(FieldValue::Double).boxed(),
+        FieldType::Float => any::<f32>().prop_map(FieldValue::Float).boxed(),
+        FieldType::Int => any::<i32>().prop_map(FieldValue::Int).boxed(),
+        FieldType::Long => any::<i64>().prop_map(FieldValue::Long).boxed(),
+        FieldType::Short => any::<i16>().prop_map(FieldValue::Short).boxed(),
+        FieldType::String => string_value().prop_map(FieldValue::String).boxed(),
+        _ => unreachable!(),
+    };
+    prop_oneof![1 => Just(FieldValue::None), 4 => value].boxed()
+}
+
+fn header_and_rows() -> impl Strategy<Value = (Header, Vec<Row>)> {
+    prop::collection::vec(field_type(), 0..20).prop_flat_map(|field_types| {
+        let row = (any::<u64>(), field_types.iter().cloned().map(field_value).collect::<Vec<_>>())
+            .prop_map(|(timestamp, field_values)| Row { timestamp, field_values });
+        let field_names = (0..field_types.len()).map(|i| format!("field_{}", i)).collect();
+        (Just(Header::new(field_names, field_types)), prop::collection::vec(row, 0..10))
+    })
+}
+
+// compares floats by bits so NaN round-trips count as equal
+fn same_value(a: &FieldValue, b: &FieldValue) -> bool {
+    match (a, b) {
+        (FieldValue::Double(x), FieldValue::Double(y)) => x.to_bits() == y.to_bits(),
+        (FieldValue::Float(x), FieldValue::Float(y)) => x.to_bits() == y.to_bits(),
+        _ => a == b,
+    }
+}
+
+fn round_trip(path: &str, mut header: Header, rows: &[Row], config: DCOutputConfig) -> CliResult<(Header, Vec<Row>)> {
+    let sink = Box::new(DCSink::new(&Some(path.to_string()), config)?);
+    let mut data_sink = sink.process_header(&mut header)?;
+    for row in rows {
+        data_sink.write_row(row.clone())?;
+    }
+    data_sink.finish()?;
+
+    let mut source = DCSource::new(File::open(path)?)?;
+    let mut read_rows = Vec::new();
+    while let Some(row) = Source::next_row(&mut source)? {
+        read_rows.push(row);
+    }
+    Ok((source.header().clone(), read_rows))
+}
+
+fn assert_round_trip(path: &str, header: Header, rows: Vec<Row>, config: DCOutputConfig) {
+    let (read_header, read_rows) = round_trip(path, header.clone(), &rows, config).unwrap();
+    assert!(read_header == header);
+    assert_eq!(read_rows.len(), rows.len());
+    for (read_row, row) in read_rows.iter().zip(&rows) {
+        assert_eq!(read_row.timestamp, row.timestamp);
+        assert_eq!(read_row.field_values.len(), row.field_values.len());
+        for (read_value, value) in read_row.field_values.iter().zip(&row.field_values) {
+            assert!(same_value(read_value, value), "{} != {}", read_value, value);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_round_trip_v2((header, rows) in header_and_rows()) {
+        assert_round_trip("tests/output/test_dc_roundtrip_v2.dc", header, rows, DCOutputConfig::new_default());
+    }
+
+    #[test]
+    fn test_round_trip_v3((header, rows) in header_and_rows()) {
+        let mut config = DCOutputConfig::new_default();
+        config.set_version(dc_util::VERSION_3).unwrap();
+        assert_round_trip("tests/output/test_dc_roundtrip_v3.dc", header, rows, config);
+    }
+}
+
+#[test]
+fn test_bitset_sizes() {
+    // bitset byte boundaries, incl. no fields at all
+    for field_count in vec![0, 1, 7, 8, 9, 16, 17] {
+        let field_names = (0..field_count).map(|i| format!("field_{}", i)).collect();
+        let header = Header::new(field_names, vec![FieldType::Int; field_count]);
+        let rows = vec![
+            Row { timestamp: 1, field_values: (0..field_count).map(|i| FieldValue::Int(i as i32)).collect() },
+            Row { timestamp: 2, field_values: vec![FieldValue::None; field_count] }];
+        assert_round_trip("tests/output/test_dc_bitset_sizes.dc", header, rows, DCOutputConfig::new_default());
+    }
+}
@@ -0,0 +1,440 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use chrono_tz::UTC;
+
+use chopper_lib::chopper::chopper::{ChopperDriver, DataSink, HeaderSink, Source};
+use chopper_lib::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper_lib::chopper::types::{self, FieldType, FieldValue, Header, Row, TimestampRange};
+use chopper_lib::driver::driver::{DriveLimit, Driver, Playback};
+use chopper_lib::driver::merge_join::MergeJoin;
+use chopper_lib::driver::split::Split;
+use chopper_lib::error::{CliResult, Error};
+use chopper_lib::filter::{row_filter_head::RowFilterHead, row_filter_tail::RowFilterTail};
+use chopper_lib::input::lazy_source::LazySource;
+use chopper_lib::util::timestamp_util::{DailyWindow, DstPolicy};
+
+struct VecSource {
+    header: Header,
+    rows: Vec<Row>,
+}
+
+impl VecSource {
+    fn new(timestamps: Vec<u64>) -> Box<dyn Source> {
+        let header = Header::new(vec!["value".to_string()], vec![FieldType::Long]);
+        let rows = timestamps.into_iter().rev()
+            .map(|t| Row { timestamp: t, field_values: vec![FieldValue::Long(t as i64)] })
+            .collect();
+        Box::new(VecSource { header, rows })
+    }
+}
+
+impl Source for VecSource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        Ok(self.rows.pop())
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        true
+    }
+}
+
+// rows at the timestamps, each after a wall-clock delay, then an error if read further
+struct SlowSource {
+    source: Box<dyn Source>,
+    delay: Duration,
+    error_at_end: bool,
+}
+
+impl Source for SlowSource {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        std::thread::sleep(self.delay);
+        match self.source.next_row()? {
+            None if self.error_at_end => Err(Error::from("SlowSource -- read past the last row")),
+            row => Ok(row)
+        }
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+struct Recorded {
+    timestamps: Vec<u64>,
+    finish_count: usize,
+}
+
+struct RecordingSink {
+    recorded: Rc<RefCell<Recorded>>,
+}
+
+impl RecordingSink {
+    fn new(recorded: &Rc<RefCell<Recorded>>) -> Box<dyn HeaderSink> {
+        Box::new(RecordingSink { recorded: recorded.clone() })
+    }
+}
+
+impl HeaderSink for RecordingSink {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> CliResult<Box<dyn DataSink>> {
+        Ok(self.boxed())
+    }
+}
+
+impl DataSink for RecordingSink {
+    fn write_row(&mut self, row: Row) -> CliResult<Option<Row>> {
+        self.recorded.borrow_mut().timestamps.push(row.timestamp);
+        Ok(None)
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> CliResult<Vec<Row>> {
+        self.recorded.borrow_mut().finish_count += 1;
+        Ok(Vec::new())
+    }
+
+    fn boxed(self) -> Box<dyn DataSink> {
+        Box::new(self)
+    }
+}
+
+#[test]
+fn test_merge_finish() {
+    let sources = vec![VecSource::new(vec![1, 3, 5]), VecSource::new(vec![2, 4, 6, 8, 10])];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+
+    let chain_1 = HeaderChain::new(vec![HeaderNode::Merge(2, 0)]);
+    let chain_2 = HeaderChain::new(vec![HeaderNode::Merge(2, 1)]);
+    let merge = MergeJoin::new(2).unwrap();
+    let num_of_header_to_process = merge.num_of_header_to_process();
+    let chain_3 = HeaderChain::new(vec![
+        HeaderNode::MergeHeaderSink(merge, num_of_header_to_process),
+        HeaderNode::HeaderSink(RowFilterTail::new(3)),
+        HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let graph = HeaderGraph::new(vec![chain_1, chain_2, chain_3]);
+
+    Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap().drive().unwrap();
+
+    // the merged chain only finishes once both sources are done
+    assert_eq!(recorded.borrow().timestamps, vec![6, 8, 10]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+}
+
+#[test]
+fn test_split_finish() {
+    let sources = vec![VecSource::new(vec![1, 2, 3, 4])];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded_1 = Rc::new(RefCell::new(Recorded::default()));
+    let recorded_2 = Rc::new(RefCell::new(Recorded::default()));
+
+    let split = Split::new(vec![1, 2]);
+    let header_to_process = split.num_of_header_to_process();
+    let chain_1 = HeaderChain::new(vec![HeaderNode::SplitHeaderSink(split, header_to_process)]);
+    let chain_2 = HeaderChain::new(vec![
+        HeaderNode::HeaderSink(RowFilterTail::new(1)),
+        HeaderNode::HeaderSink(RecordingSink::new(&recorded_1))]);
+    let chain_3 = HeaderChain::new(vec![HeaderNode::HeaderSink(RecordingSink::new(&recorded_2))]);
+    let graph = HeaderGraph::new(vec![chain_1, chain_2, chain_3]);
+
+    Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap().drive().unwrap();
+
+    assert_eq!(recorded_1.borrow().timestamps, vec![4]);
+    assert_eq!(recorded_1.borrow().finish_count, 1);
+    assert_eq!(recorded_2.borrow().timestamps, vec![1, 2, 3, 4]);
+    assert_eq!(recorded_2.borrow().finish_count, 1);
+}
+
+fn drive_merged(sources: Vec<Box<dyn Source>>, timestamp_range: TimestampRange) -> Vec<u64> {
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let chain_1 = HeaderChain::new(vec![HeaderNode::Merge(2, 0)]);
+    let chain_2 = HeaderChain::new(vec![HeaderNode::Merge(2, 1)]);
+    let merge = MergeJoin::new(2).unwrap();
+    let num_of_header_to_process = merge.num_of_header_to_process();
+    let chain_3 = HeaderChain::new(vec![
+        HeaderNode::MergeHeaderSink(merge, num_of_header_to_process),
+        HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let graph = HeaderGraph::new(vec![chain_1, chain_2, chain_3]);
+    Driver::new(sources, graph, timestamp_range, headers).unwrap().drive().unwrap();
+    assert_eq!(recorded.borrow().finish_count, 1);
+    let timestamps = recorded.borrow().timestamps.clone();
+    timestamps
+}
+
+#[test]
+fn test_range_after_first_row() {
+    // begin +2, end +6 from the first row of both sources
+    let timestamp_range = TimestampRange::new(Some("+2s"), Some("+6s"), UTC, DstPolicy::Error).unwrap();
+    let sources = vec![VecSource::new(vec![11, 13, 15, 17]), VecSource::new(vec![10, 12, 14, 16, 18])];
+    assert_eq!(drive_merged(sources, timestamp_range), vec![12, 13, 14, 15]);
+
+    // one source entirely past the end
+    let timestamp_range = TimestampRange::new(None, Some("+5s"), UTC, DstPolicy::Error).unwrap();
+    let sources = vec![VecSource::new(vec![10, 11, 12]), VecSource::new(vec![20, 21])];
+    assert_eq!(drive_merged(sources, timestamp_range), vec![10, 11, 12]);
+}
+
+#[test]
+fn test_daily_window() {
+    let mut timestamp_range = types::TIMESTAMP_RANGE_DEFAULT;
+    timestamp_range.set_daily_window(Some(DailyWindow::parse("00:01-00:02", UTC).unwrap()));
+    let day = 86_400;
+    let sources = vec![VecSource::new(vec![30, 60, 90, 120]), VecSource::new(vec![day + 59, day + 61])];
+    assert_eq!(drive_merged(sources, timestamp_range), vec![60, 90, day + 61]);
+}
+
+#[test]
+fn test_playback() {
+    let playback = Playback::new(10.0, Some(Duration::from_millis(50)), 1_000_000).unwrap();
+    assert_eq!(playback.delay(1000, 1100), Duration::from_millis(10));
+    assert_eq!(playback.delay(1000, 5000), Duration::from_millis(50));
+    assert_eq!(playback.delay(1000, 900), Duration::from_millis(0));
+    assert!(Playback::new(0.0, None, 1).is_err());
+
+    // timestamps in ms: 300ms of gaps at double speed, plus a long gap clamped to 100ms
+    let sources = vec![VecSource::new(vec![0, 100, 200, 300, 60_000])];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let chain = HeaderChain::new(vec![HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let graph = HeaderGraph::new(vec![chain]);
+    let mut driver = Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap();
+    driver.set_playback(Some(Playback::new(2.0, Some(Duration::from_millis(100)), 1_000_000).unwrap()));
+    let start = Instant::now();
+    driver.drive().unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(recorded.borrow().timestamps, vec![0, 100, 200, 300, 60_000]);
+    assert!(elapsed >= Duration::from_millis(250), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
+
+// counts the sources open at once
+struct OpenCount {
+    open: Rc<RefCell<(usize, usize)>>,
+    source: Box<dyn Source>,
+}
+
+impl Drop for OpenCount {
+    fn drop(&mut self) {
+        self.open.borrow_mut().0 -= 1;
+    }
+}
+
+impl Source for OpenCount {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        self.source.next_row()
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_lazy_sources() {
+    // (open now, most open at once)
+    let open = Rc::new(RefCell::new((0, 0)));
+    let lazy_source = |timestamps: Vec<u64>| {
+        let open = open.clone();
+        let hint = timestamps[0] / 100 * 100;
+        let open_source = move || {
+            let mut count = open.borrow_mut();
+            count.0 += 1;
+            count.1 = count.1.max(count.0);
+            let source: Box<dyn Source> = Box::new(OpenCount { open: open.clone(), source: VecSource::new(timestamps.clone()) });
+            Ok(source)
+        };
+        let source: Box<dyn Source> = Box::new(LazySource::new(Box::new(open_source), Some(hint)).unwrap());
+        source
+    };
+    let sources = vec![lazy_source(vec![200, 250]), lazy_source(vec![100, 150])];
+    assert_eq!(open.borrow().0, 0);
+    assert_eq!(drive_merged(sources, types::TIMESTAMP_RANGE_DEFAULT), vec![100, 150, 200, 250]);
+    assert_eq!(*open.borrow(), (0, 1));
+
+    // overlapping sources are both open
+    *open.borrow_mut() = (0, 0);
+    let sources = vec![lazy_source(vec![100, 150]), lazy_source(vec![120, 130])];
+    assert_eq!(drive_merged(sources, types::TIMESTAMP_RANGE_DEFAULT), vec![100, 120, 130, 150]);
+    assert_eq!(*open.borrow(), (0, 2));
+}
+
+fn drive_limited(sources: Vec<Box<dyn Source>>,
+                 timestamp_range: TimestampRange,
+                 limit: DriveLimit) -> Rc<RefCell<Recorded>> {
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let chain = HeaderChain::new(vec![HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let mut driver = Driver::new(sources, HeaderGraph::new(vec![chain]), timestamp_range, headers).unwrap();
+    driver.set_limit(limit);
+    driver.drive().unwrap();
+    recorded
+}
+
+#[test]
+fn test_limit() {
+    let limit = DriveLimit { rows: Some(2), duration: None };
+    let recorded = drive_limited(vec![VecSource::new(vec![1, 2, 3, 4])], types::TIMESTAMP_RANGE_DEFAULT, limit);
+    assert_eq!(recorded.borrow().timestamps, vec![1, 2]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+
+    // rows outside the range are not counted
+    let timestamp_range = TimestampRange { begin: Some(2), ..types::TIMESTAMP_RANGE_DEFAULT };
+    let recorded = drive_limited(vec![VecSource::new(vec![1, 2, 3, 4])], timestamp_range, limit);
+    assert_eq!(recorded.borrow().timestamps, vec![2, 3]);
+
+    // rows a tail passes on at the end are counted too
+    let sources = vec![VecSource::new(vec![1, 2, 3, 4, 5])];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let chain = HeaderChain::new(vec![
+        HeaderNode::HeaderSink(RowFilterTail::new(3)),
+        HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let mut driver = Driver::new(sources, HeaderGraph::new(vec![chain]), types::TIMESTAMP_RANGE_DEFAULT, headers)
+        .unwrap();
+    driver.set_limit(limit);
+    driver.drive().unwrap();
+    assert_eq!(recorded.borrow().timestamps, vec![3, 4]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+}
+
+#[test]
+fn test_duration() {
+    let source: Box<dyn Source> = Box::new(SlowSource {
+        source: VecSource::new((1..=100).collect()), delay: Duration::from_millis(20), error_at_end: false });
+    let limit = DriveLimit { rows: None, duration: Some(Duration::from_millis(200)) };
+    let start = Instant::now();
+    let recorded = drive_limited(vec![source], types::TIMESTAMP_RANGE_DEFAULT, limit);
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+    let count = recorded.borrow().timestamps.len();
+    assert!(count > 0 && count < 100, "{}", count);
+    assert_eq!(recorded.borrow().finish_count, 1);
+}
+
+#[test]
+fn test_drop_source_past_end() {
+    // the source is not read after its first row past the end
+    let source: Box<dyn Source> = Box::new(SlowSource {
+        source: VecSource::new(vec![1, 2, 3, 10]), delay: Duration::from_millis(0), error_at_end: true });
+    let timestamp_range = TimestampRange { end: Some(5), ..types::TIMESTAMP_RANGE_DEFAULT };
+    let recorded = drive_limited(vec![source], timestamp_range, DriveLimit::default());
+    assert_eq!(recorded.borrow().timestamps, vec![1, 2, 3]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+}
+
+// rows from the first timestamp on, one per second, forever
+struct EndlessSource {
+    header: Header,
+    next: u64,
+}
+
+impl EndlessSource {
+    fn new(first: u64) -> Box<dyn Source> {
+        Box::new(EndlessSource { header: Header::new(vec![], vec![]), next: first })
+    }
+}
+
+impl Source for EndlessSource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        self.next += 1;
+        Ok(Some(Row { timestamp: self.next - 1, field_values: vec![] }))
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_head_stops_driver() {
+    let sources = vec![EndlessSource::new(1)];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let chain = HeaderChain::new(vec![
+        HeaderNode::HeaderSink(RowFilterHead::new(2)),
+        HeaderNode::HeaderSink(RecordingSink::new(&recorded))]);
+    let graph = HeaderGraph::new(vec![chain]);
+    Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap().drive().unwrap();
+    assert_eq!(recorded.borrow().timestamps, vec![1, 2]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+
+    // merged endless sources stop once the merged chain is done
+    let sources = vec![EndlessSource::new(10), EndlessSource::new(1)];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let merge = MergeJoin::new(2).unwrap();
+    let num_of_header_to_process = merge.num_of_header_to_process();
+    let graph = HeaderGraph::new(vec![
+        HeaderChain::new(vec![HeaderNode::Merge(2, 0)]),
+        HeaderChain::new(vec![HeaderNode::Merge(2, 1)]),
+        HeaderChain::new(vec![
+            HeaderNode::MergeHeaderSink(merge, num_of_header_to_process),
+            HeaderNode::HeaderSink(RowFilterHead::new(12)),
+            HeaderNode::HeaderSink(RecordingSink::new(&recorded))])]);
+    Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap().drive().unwrap();
+    assert_eq!(recorded.borrow().timestamps, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 10, 11]);
+    assert_eq!(recorded.borrow().finish_count, 1);
+}
+
+struct WarningSource {
+    source: Box<dyn Source>,
+}
+
+impl Source for WarningSource {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> CliResult<Option<Row>> {
+        self.source.next_row()
+    }
+
+    fn has_native_timestamp_column(&self) -> bool {
+        true
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        vec!["skipped a row".to_string()]
+    }
+}
+
+#[test]
+fn test_warnings() {
+    // kept once the sources are done and dropped
+    let sources: Vec<Box<dyn Source>> = vec![
+        Box::new(WarningSource { source: VecSource::new(vec![1, 2]) }), VecSource::new(vec![3])];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+    let merge = MergeJoin::new(2).unwrap();
+    let num_of_header_to_process = merge.num_of_header_to_process();
+    let graph = HeaderGraph::new(vec![
+        HeaderChain::new(vec![HeaderNode::Merge(2, 0)]),
+        HeaderChain::new(vec![HeaderNode::Merge(2, 1)]),
+        HeaderChain::new(vec![
+            HeaderNode::MergeHeaderSink(merge, num_of_header_to_process),
+            HeaderNode::HeaderSink(RecordingSink::new(&recorded))])]);
+    let mut driver = Driver::new(sources, graph, types::TIMESTAMP_RANGE_DEFAULT, headers).unwrap();
+    driver.drive().unwrap();
+    assert_eq!(ChopperDriver::warnings(&driver), vec!["skipped a row".to_string()]);
+}
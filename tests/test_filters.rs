@@ -3,7 +3,7 @@ use same_file::is_same_file;
 
 use chopper_lib::chopper::chopper::{ChopperDriver, Source};
 use chopper_lib::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
-use chopper_lib::chopper::types::{FieldValue, Header, TimestampRange};
+use chopper_lib::chopper::types::{FieldValue, Header, TimestampRange, TIMESTAMP_RANGE_DEFAULT};
 use chopper_lib::driver::driver::Driver;
 use chopper_lib::error::{self, CliResult};
 use chopper_lib::filter::column_filter_delete_col::ColumnFilterDelete;
@@ -34,8 +34,8 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     let output = "./tests/reference/filters.csv";
 
     let begin = timestamp_util::parse_timestamp_range
-        ("2018".to_string(), New_York)?;
-    let timestamp_range = TimestampRange { begin: Some(begin), end: None };
+        ("2018".to_string(), New_York, timestamp_util::DstPolicy::Error)?;
+    let timestamp_range = TimestampRange { begin: Some(begin), ..TIMESTAMP_RANGE_DEFAULT };
 
     // source reader and headers
     let ts_config = TimestampConfig::new
@@ -43,7 +43,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     let input_config = CSVInputConfig::new
         (csv_configs::DELIMITER_DEFAULT, true, ts_config)?;
     let mut input_factory = InputFactory::new
-        (Some(input_config), None, None)?;
+        (Some(input_config), None, None, None)?;
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
     for i in inputs {
@@ -69,7 +69,7 @@ fn setup_graph() -> CliResult<Box<dyn ChopperDriver>> {
     // header sink
     let csv_output_config = CSVOutputConfig::new(DELIMITER_DEFAULT, true);
     let header_sink = factory::new_header_sink
-        (Some(output), Some(csv_output_config))?;
+        (Some(output), Some(csv_output_config), None)?;
     let node_output = HeaderNode::HeaderSink(header_sink);
     let chain = HeaderChain::new(vec![node_1, node_2, node_3, node_output]);
 
@@ -0,0 +1,95 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+use chopper_lib::chopper::chopper::{HeaderSink, Source};
+use chopper_lib::chopper::types::{FieldType, FieldValue, Header, Row};
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::source::csv_configs::{self, CSVInputConfig, TimestampConfig};
+use chopper_lib::source::dc_configs::DCOutputConfig;
+use chopper_lib::transport::file::FollowConfig;
+use chopper_lib::write::dc_sink::DCSink;
+
+fn follow_config() -> FollowConfig {
+    FollowConfig { poll_interval: Duration::from_millis(10), idle_timeout: Some(Duration::from_millis(500)) }
+}
+
+fn read_all(mut source: Box<dyn Source>) -> Vec<Row> {
+    let mut rows = Vec::new();
+    while let Some(row) = source.next_row().unwrap() {
+        rows.push(row);
+    }
+    rows
+}
+
+fn append(path: &str, data: &[u8]) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(data).unwrap();
+}
+
+fn timestamps(rows: &[Row]) -> Vec<u64> {
+    rows.iter().map(|r| r.timestamp).collect()
+}
+
+#[test]
+fn test_follow_csv() {
+    let path = "tests/output/test_follow.csv";
+    // a partial trailing row, completed later
+    fs::write(path, "time,value\n1500000000,a\n15000").unwrap();
+    let config = CSVInputConfig::new(csv_configs::DELIMITER_DEFAULT, true, TimestampConfig::default()).unwrap();
+    let mut input_factory = InputFactory::new(Some(config), None, None, None).unwrap();
+    input_factory.set_follow(Some(follow_config()));
+    let source = input_factory.create_source_from_path(path).unwrap();
+
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        append(path, b"00001,b\n");
+        thread::sleep(Duration::from_millis(100));
+        // truncated and written again from the start
+        fs::write(path, "time,value\n1500000002,c\n").unwrap();
+        thread::sleep(Duration::from_millis(100));
+        // rotated: replaced by a new file
+        let rotated = "tests/output/test_follow_rotated.csv";
+        fs::write(rotated, "time,value\n1500000003,d\n").unwrap();
+        fs::rename(rotated, path).unwrap();
+    });
+    let rows = read_all(source);
+    writer.join().unwrap();
+    assert_eq!(timestamps(&rows), vec![1500000000, 1500000001, 1500000002, 1500000003]);
+    assert!(rows[1].field_values[1] == FieldValue::String("b".to_string()));
+}
+
+#[test]
+fn test_follow_dc() {
+    let full_path = "tests/output/test_follow_full.dc";
+    let mut header = Header::new(vec!["value".to_string()], vec![FieldType::Long]);
+    let sink = Box::new(DCSink::new(&Some(full_path.to_string()), DCOutputConfig::new_default()).unwrap());
+    let mut data_sink = sink.process_header(&mut header).unwrap();
+    for t in 1..=3 {
+        data_sink.write_row(Row { timestamp: t, field_values: vec![FieldValue::Long(t as i64)] }).unwrap();
+    }
+    data_sink.finish().unwrap();
+    let data = fs::read(full_path).unwrap();
+
+    // the file is written in two parts, splitting a record
+    let path = "tests/output/test_follow.dc";
+    let split = data.len() - 5;
+    File::create(path).unwrap().write_all(&data[..split]).unwrap();
+    let mut input_factory = InputFactory::new(None, None, None, None).unwrap();
+    input_factory.set_follow(Some(follow_config()));
+    let source = input_factory.create_source_from_path(path).unwrap();
+    let rest = data[split..].to_vec();
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        append(path, &rest);
+        thread::sleep(Duration::from_millis(100));
+        // rotated: replaced by a new file, header included
+        let rotated = "tests/output/test_follow_rotated.dc";
+        fs::write(rotated, &data).unwrap();
+        fs::rename(rotated, path).unwrap();
+    });
+    let rows = read_all(source);
+    writer.join().unwrap();
+    assert_eq!(timestamps(&rows), vec![1, 2, 3, 1, 2, 3]);
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::source::csv_configs::{self, CSVInputConfig, TimestampConfig};
+use chopper_lib::transport::http::{Http, HttpConfig};
+use chopper_lib::transport::transport_factory::TransportFactory;
+
+const FEED: &str = "time,value\n1500000000,a\n1500000001,b\n1500000002,c\n";
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+}
+
+fn read_request(stream: &mut TcpStream) -> Request {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap().to_string();
+    let path = parts.next().unwrap().to_string();
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        match line.trim_end().split_once(':') {
+            Some((name, value)) => headers.insert(name.to_ascii_lowercase(), value.trim().to_string()),
+            None => break
+        };
+    }
+    Request { method, path, headers }
+}
+
+fn respond(stream: &mut TcpStream, request: &Request, status: &str, headers: &[(&str, String)], body: &[u8]) {
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).unwrap();
+    if request.method != "HEAD" {
+        stream.write_all(body).unwrap();
+    }
+}
+
+// serves the body whole, or from the start of a "bytes=N-" range
+fn respond_ranged(stream: &mut TcpStream, request: &Request, body: &[u8], content_type: &str) {
+    match request.headers.get("range").and_then(|r| r.strip_prefix("bytes=")) {
+        Some(range) => {
+            let start: usize = range.trim_end_matches('-').parse().unwrap();
+            let headers = [("Content-Type", content_type.to_string()),
+                           ("Accept-Ranges", "bytes".to_string()),
+                           ("Content-Length", (body.len() - start).to_string()),
+                           ("Content-Range", format!("bytes {}-{}/{}", start, body.len() - 1, body.len()))];
+            respond(stream, request, "206 Partial Content", &headers, &body[start..]);
+        }
+        None => {
+            let headers = [("Content-Type", content_type.to_string()),
+                           ("Accept-Ranges", "bytes".to_string()),
+                           ("Content-Length", body.len().to_string())];
+            respond(stream, request, "200 OK", &headers, body);
+        }
+    }
+}
+
+// a local server standing in for a remote one; returns the base url and the requests it got
+fn start_server() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let server_log = log.clone();
+    thread::spawn(move || {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&mut stream);
+            server_log.lock().unwrap().push((request.method.clone(), request.path.clone()));
+            let count = counts.entry(request.path.clone()).or_insert(0);
+            *count += 1;
+            match request.path.as_str() {
+                "/feed.csv" => respond_ranged(&mut stream, &request, FEED.as_bytes(), "text/csv"),
+                // no extension, the type comes from the headers
+                "/feed" => respond_ranged(&mut stream, &request, FEED.as_bytes(), "text/csv; charset=utf-8"),
+                "/auth.csv" => match request.headers.get("authorization").map(|a| a.as_str()) {
+                    Some("Bearer secret") => respond_ranged(&mut stream, &request, FEED.as_bytes(), "text/csv"),
+                    _ => respond(&mut stream, &request, "401 Unauthorized", &[("Content-Length", "0".to_string())], b"")
+                },
+                // fails twice before answering
+                "/flaky.csv" if *count <= 2 =>
+                    respond(&mut stream, &request, "503 Service Unavailable", &[("Content-Length", "0".to_string())], b""),
+                "/flaky.csv" => respond_ranged(&mut stream, &request, FEED.as_bytes(), "text/csv"),
+                // the first response is cut off midway
+                "/cut.csv" if request.method == "GET" && !request.headers.contains_key("range") => {
+                    let headers = [("Accept-Ranges", "bytes".to_string()),
+                                   ("Content-Length", FEED.len().to_string())];
+                    respond(&mut stream, &request, "200 OK", &headers, &FEED.as_bytes()[..20]);
+                }
+                "/cut.csv" => respond_ranged(&mut stream, &request, FEED.as_bytes(), "text/csv"),
+                "/gzipped.csv" => {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(FEED.as_bytes()).unwrap();
+                    let body = encoder.finish().unwrap();
+                    let headers = [("Content-Encoding", "gzip".to_string()),
+                                   ("Content-Length", body.len().to_string())];
+                    respond(&mut stream, &request, "200 OK", &headers, &body);
+                }
+                _ => respond(&mut stream, &request, "404 Not Found", &[("Content-Length", "0".to_string())], b"")
+            }
+        }
+    });
+    (base, log)
+}
+
+fn http_config() -> HttpConfig {
+    let mut config = HttpConfig::new_default();
+    config.set_retry_backoff(Duration::from_millis(10));
+    config.set_read_timeout(Duration::from_secs(5));
+    config
+}
+
+fn input_factory(config: HttpConfig) -> InputFactory {
+    let csv_config = CSVInputConfig::new(csv_configs::DELIMITER_DEFAULT, true, TimestampConfig::default()).unwrap();
+    let mut input_factory = InputFactory::new(Some(csv_config), None, None, None).unwrap();
+    input_factory.set_http(config);
+    input_factory
+}
+
+fn read_timestamps(config: HttpConfig, url: &str) -> Result<Vec<u64>, String> {
+    let mut source = input_factory(config).create_source_from_path(url).map_err(|e| e.to_string())?;
+    let mut timestamps = Vec::new();
+    while let Some(row) = source.next_row().map_err(|e| e.to_string())? {
+        timestamps.push(row.timestamp);
+    }
+    Ok(timestamps)
+}
+
+fn read_all(mut reader: impl Read) -> String {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).unwrap();
+    text
+}
+
+#[test]
+fn test_http_sources() {
+    let (base, log) = start_server();
+    let all = vec![1500000000, 1500000001, 1500000002];
+    assert_eq!(read_timestamps(http_config(), &format!("{}/feed.csv", base)).unwrap(), all);
+    // csv from the content type
+    assert_eq!(read_timestamps(http_config(), &format!("{}/feed", base)).unwrap(), all);
+    assert_eq!(read_timestamps(http_config(), &format!("{}/gzipped.csv", base)).unwrap(), all);
+
+    let error = read_timestamps(http_config(), &format!("{}/auth.csv", base)).unwrap_err();
+    assert!(error.contains("401"), "{}", error);
+    let mut config = http_config();
+    config.set_bearer_token("secret").unwrap();
+    assert_eq!(read_timestamps(config, &format!("{}/auth.csv", base)).unwrap(), all);
+
+    let error = read_timestamps(http_config(), &format!("{}/missing.csv", base)).unwrap_err();
+    assert!(error.contains("404"), "{}", error);
+    // 404 is not retried
+    assert_eq!(log.lock().unwrap().iter().filter(|(_, p)| p == "/missing.csv").count(), 2);
+}
+
+#[test]
+fn test_http_retries() {
+    let (base, log) = start_server();
+    let http = Http::new(http_config());
+    assert_eq!(read_all(http.open(Path::new(&format!("{}/flaky.csv", base))).unwrap()), FEED);
+    assert_eq!(log.lock().unwrap().len(), 3);
+
+    let (base, _) = start_server();
+    let mut config = http_config();
+    config.set_retries(1);
+    let error = Http::new(config).open(Path::new(&format!("{}/flaky.csv", base))).err().unwrap();
+    assert!(error.to_string().contains("503"), "{}", error);
+
+    // resumed from where the first response was cut off
+    let (base, log) = start_server();
+    assert_eq!(read_all(http.open(Path::new(&format!("{}/cut.csv", base))).unwrap()), FEED);
+    assert_eq!(log.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_http_seek() {
+    let (base, log) = start_server();
+    let http = Http::new(http_config());
+    let path = format!("{}/feed.csv", base);
+    let mut reader = http.open_seekable(Path::new(&path)).unwrap().unwrap();
+    assert_eq!(reader.seek(SeekFrom::End(-13)).unwrap(), FEED.len() as u64 - 13);
+    assert_eq!(read_all(&mut reader), "1500000002,c\n");
+    reader.seek(SeekFrom::Start(11)).unwrap();
+    let mut buf = [0; 10];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"1500000000");
+    let requests: Vec<String> = log.lock().unwrap().iter().map(|(m, _)| m.clone()).collect();
+    assert_eq!(requests, vec!["HEAD", "GET", "GET"]);
+
+    // the format from the url, or from the headers if it has no extension
+    assert_eq!(http.file_type(Path::new(&path)), Some(".csv".to_string()));
+    assert_eq!(http.file_type(Path::new(&format!("{}/feed", base))), Some(".csv".to_string()));
+    assert_eq!(http.file_type(Path::new(&format!("{}/other", base))), None);
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::Path;
+
+use chrono_tz::UTC;
+
+use chopper_lib::chopper::chopper::{HeaderSink, Source};
+use chopper_lib::chopper::types::{self, FieldType, FieldValue, Header, Row, TimestampRange};
+use chopper_lib::input::concat_source::ConcatSource;
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::input::time_span::TimeSpan;
+use chopper_lib::source::csv_configs::{self, CSVInputConfig, TimestampConfig};
+use chopper_lib::source::dc_configs::DCOutputConfig;
+use chopper_lib::util::dc_util;
+use chopper_lib::write::dc_sink::DCSink;
+
+const DIR: &str = "tests/output/test_inputs";
+
+// one csv file per day, with a row at 01:00 and 02:00
+fn write_days() {
+    let _ = fs::remove_dir_all(DIR);
+    for (day, timestamp) in vec![("03", 1559523600), ("04", 1559610000), ("05", 1559696400)] {
+        let dir = format!("{}/2019/06/{}", DIR, day);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(format!("{}/feed.csv", dir),
+                  format!("time,value\n{},a\n{},b\n", timestamp, timestamp + 3600)).unwrap();
+        fs::write(format!("{}/notes.txt", dir), "not an input").unwrap();
+    }
+}
+
+fn input_factory() -> InputFactory {
+    let config = CSVInputConfig::new(csv_configs::DELIMITER_DEFAULT, true, TimestampConfig::default()).unwrap();
+    InputFactory::new(Some(config), None, None, None).unwrap()
+}
+
+fn paths(input: &str, timestamp_range: &TimestampRange) -> Vec<String> {
+    input_factory().expand_input(input, timestamp_range, UTC).unwrap().unwrap()
+        .into_iter().map(|e| e.path).collect()
+}
+
+#[test]
+fn test_expand_input() {
+    write_days();
+    let all = vec![format!("{}/2019/06/03/feed.csv", DIR),
+                   format!("{}/2019/06/04/feed.csv", DIR),
+                   format!("{}/2019/06/05/feed.csv", DIR)];
+    let no_range = types::TIMESTAMP_RANGE_DEFAULT;
+    assert_eq!(paths(DIR, &no_range), all);
+    assert_eq!(paths(&format!("{}/2019/06/*/*.csv", DIR), &no_range), all);
+    assert!(input_factory().expand_input(&all[0], &no_range, UTC).unwrap().is_none());
+    assert!(input_factory().expand_input(&format!("{}/*.dc", DIR), &no_range, UTC).is_err());
+
+    // only 2019-06-04
+    let range = TimestampRange { begin: Some(1559606400), end: Some(1559692800), ..no_range };
+    assert_eq!(paths(DIR, &range), vec![all[1].clone()]);
+
+    // read lazily
+    let mut input_factory = input_factory();
+    let mut source = input_factory.create_lazy_source_from_path(&all[1], Some(1559606400)).unwrap();
+    assert_eq!(source.first_timestamp_hint(), Some(1559606400));
+    assert_eq!(source.header().field_names().len(), 2);
+    assert_eq!(source.next_row().unwrap().unwrap().timestamp, 1559610000);
+    assert_eq!(source.first_timestamp_hint(), None);
+}
+
+#[test]
+fn test_dc_footer_time_span() {
+    let path = "tests/output/test_inputs_footer.dc";
+    let mut header = Header::new(vec!["value".to_string()], vec![FieldType::Long]);
+    let mut config = DCOutputConfig::new_default();
+    config.set_version(dc_util::VERSION_3).unwrap();
+    let sink = Box::new(DCSink::new(&Some(path.to_string()), config).unwrap());
+    let mut data_sink = sink.process_header(&mut header).unwrap();
+    for t in vec![1000, 2000, 3000] {
+        data_sink.write_row(Row { timestamp: t, field_values: vec![FieldValue::Long(1)] }).unwrap();
+    }
+    data_sink.finish().unwrap();
+    assert_eq!(TimeSpan::from_dc_footer(Path::new(path)).unwrap(), Some(TimeSpan { begin: 1000, end: 3001 }));
+
+    // v2 has no footer
+    let path = "tests/output/test_inputs_no_footer.dc";
+    let sink = Box::new(DCSink::new(&Some(path.to_string()), DCOutputConfig::new_default()).unwrap());
+    sink.process_header(&mut header).unwrap().finish().unwrap();
+    assert_eq!(TimeSpan::from_dc_footer(Path::new(path)).unwrap(), None);
+
+    // shorter than the header
+    let path = "tests/output/test_inputs_empty.dc";
+    std::fs::write(path, b"").unwrap();
+    assert_eq!(TimeSpan::from_dc_footer(Path::new(path)).unwrap(), None);
+    std::fs::write(path, &dc_util::MAGIC_NUM.to_be_bytes()).unwrap();
+    assert_eq!(TimeSpan::from_dc_footer(Path::new(path)).unwrap(), None);
+
+    let error = TimeSpan::from_dc_footer(Path::new("tests/output/test_inputs_missing.dc")).unwrap_err();
+    assert!(error.to_string().contains("tests/output/test_inputs_missing.dc"), "{}", error);
+}
+
+fn concat(files: Vec<&str>) -> Result<Vec<u64>, String> {
+    let mut input_factory = input_factory();
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    for (i, data) in files.into_iter().enumerate() {
+        let path = format!("tests/output/test_inputs_concat_{}.csv", i);
+        fs::write(&path, data).unwrap();
+        sources.push(input_factory.create_lazy_source_from_path(&path, None).unwrap());
+    }
+    let mut source = ConcatSource::new(sources).map_err(|e| e.to_string())?;
+    let mut timestamps = Vec::new();
+    loop {
+        match source.next_row() {
+            Ok(Some(row)) => timestamps.push(row.timestamp),
+            Ok(None) => return Ok(timestamps),
+            Err(e) => return Err(e.to_string())
+        }
+    }
+}
+
+#[test]
+fn test_concat() {
+    assert_eq!(concat(vec!["time,value\n1500000000,a\n1500000001,b\n",
+                           "time,value\n1500000001,c\n1500000002,d\n"]).unwrap(),
+               vec![1500000000, 1500000001, 1500000001, 1500000002]);
+
+    let error = concat(vec!["time,value\n1500000000,a\n1500000005,b\n",
+                            "time,value\n1500000003,c\n"]).unwrap_err();
+    assert!(error.contains("input 2 starts at 1500000003"), "{}", error);
+
+    let error = concat(vec!["time,value\n1500000000,a\n", "time,other\n1500000001,b\n"]).unwrap_err();
+    assert!(error.contains("input 2"), "{}", error);
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use chopper_lib::chopper::chopper::Source;
+use chopper_lib::chopper::types::{FieldType, FieldValue, Header, Row};
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::source::csv_configs::{self, CSVInputConfig, CSVOutputConfig, TimestampConfig};
+use chopper_lib::write::factory;
+
+const DATA: &str = "time,value\n1500000000,a\n1500000001,b\n";
+
+fn input_factory() -> InputFactory {
+    let config = CSVInputConfig::new(csv_configs::DELIMITER_DEFAULT, true, TimestampConfig::default()).unwrap();
+    InputFactory::new(Some(config), None, None, None).unwrap()
+}
+
+fn read_timestamps(mut source: Box<dyn Source>) -> Vec<u64> {
+    let mut timestamps = Vec::new();
+    while let Some(row) = source.next_row().unwrap() {
+        timestamps.push(row.timestamp);
+    }
+    timestamps
+}
+
+// connects, retrying until the listener is up
+fn connect_tcp(address: &str) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(address) {
+            return stream
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("cannot connect to {}", address)
+}
+
+#[test]
+fn test_tcp_input() {
+    // chopper connects to a producer
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let producer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(DATA.as_bytes()).unwrap();
+    });
+    let source = input_factory().create_source_from_path(&format!("tcp://{}", address)).unwrap();
+    assert_eq!(read_timestamps(source), vec![1500000000, 1500000001]);
+    producer.join().unwrap();
+
+    // chopper listens for a producer
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let producer_address = address.clone();
+    let producer = thread::spawn(move || {
+        connect_tcp(&producer_address).write_all(DATA.as_bytes()).unwrap();
+    });
+    let source = input_factory().create_source_from_path(&format!("tcp://{}?listen&format=csv", address)).unwrap();
+    assert_eq!(read_timestamps(source), vec![1500000000, 1500000001]);
+    producer.join().unwrap();
+}
+
+#[test]
+fn test_tcp_output() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let consumer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        received
+    });
+    let output = format!("tcp://{}", address);
+    let sink = factory::new_header_sink(Some(&output), Some(CSVOutputConfig::new(",", false)), None).unwrap();
+    let mut header = Header::new(vec!["value".to_string()], vec![FieldType::String]);
+    let mut data_sink = sink.process_header(&mut header).unwrap();
+    data_sink.write_row(Row { timestamp: 0, field_values: vec![FieldValue::String("a".to_string())] }).unwrap();
+    data_sink.finish().unwrap();
+    drop(data_sink);
+    assert_eq!(consumer.join().unwrap(), "value\na\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unix_input() {
+    use std::os::unix::net::UnixStream;
+
+    let path = "tests/output/test_socket.sock";
+    let _ = fs::remove_file(path);
+    let producer = thread::spawn(move || {
+        for _ in 0..100 {
+            if let Ok(mut stream) = UnixStream::connect(path) {
+                stream.write_all(DATA.as_bytes()).unwrap();
+                return
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("cannot connect to {}", path)
+    });
+    let source = input_factory().create_source_from_path(&format!("unix://{}?listen", path)).unwrap();
+    assert_eq!(read_timestamps(source), vec![1500000000, 1500000001]);
+    producer.join().unwrap();
+    // removed once connected
+    assert!(fs::metadata(path).is_err());
+}
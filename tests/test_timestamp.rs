@@ -51,7 +51,7 @@ fn setup_graph(inputs: Vec<&str>, output: &str, ts_config: TimestampConfig) -> C
     let input_config = CSVInputConfig::new
         (csv_configs::DELIMITER_DEFAULT, true, ts_config)?;
     let mut input_factory
-        = InputFactory::new(Some(input_config), None, None)?;
+        = InputFactory::new(Some(input_config), None, None, None)?;
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
     for i in inputs {
@@ -62,7 +62,7 @@ fn setup_graph(inputs: Vec<&str>, output: &str, ts_config: TimestampConfig) -> C
 
     let csv_output_config = CSVOutputConfig::new(DELIMITER_DEFAULT, true);
     let header_sink = factory::new_header_sink
-        (Some(output), Some(csv_output_config))?;
+        (Some(output), Some(csv_output_config), None)?;
     let node_output = HeaderNode::HeaderSink(header_sink);
     let chain = HeaderChain::new(vec![node_output]);
 
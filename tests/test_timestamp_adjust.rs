@@ -0,0 +1,70 @@
+use std::fs::File;
+
+use chrono_tz::America::New_York;
+
+use chopper_lib::chopper::chopper::{ChopperDriver, HeaderSink, Source};
+use chopper_lib::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper_lib::chopper::types::{FieldType, FieldValue, Header, Row, TIMESTAMP_RANGE_DEFAULT};
+use chopper_lib::driver::driver::Driver;
+use chopper_lib::filter::timestamp_adjust::TimestampAdjustConfig;
+use chopper_lib::input::input_factory::InputFactory;
+use chopper_lib::source::csv_configs::{CSVInputConfig, CSVOutputConfig, DELIMITER_DEFAULT};
+use chopper_lib::source::csv_configs::{TimestampCol, TimestampConfig};
+use chopper_lib::source::dc_configs::DCOutputConfig;
+use chopper_lib::source::dc_source::DCSource;
+use chopper_lib::write::dc_sink::DCSink;
+use chopper_lib::write::factory;
+
+#[test]
+fn test_ts_keep_original() {
+    let output = "./tests/output/test_ts_keep_original.csv";
+    let ts_config = TimestampConfig::new
+        (TimestampCol::Timestamp(1), Some("%Y/%m/%d-%H:%M:%S".to_string()), New_York);
+    let input_config = CSVInputConfig::new(DELIMITER_DEFAULT, true, ts_config).unwrap();
+    let mut input_factory = InputFactory::new(Some(input_config), None, None, None).unwrap();
+    let source = input_factory.create_source_from_path("./tests/input/time_city.csv").unwrap();
+    let headers = vec![source.header().clone()];
+
+    let mut adjust = TimestampAdjustConfig::new();
+    adjust.set_shift(-60);
+    adjust.set_keep_original(Some("original".to_string()));
+    let header_sink = factory::new_header_sink
+        (Some(output), Some(CSVOutputConfig::new(DELIMITER_DEFAULT, true)), None).unwrap();
+    let chain = HeaderChain::new(vec![
+        HeaderNode::HeaderSink(adjust.boxed()), HeaderNode::HeaderSink(header_sink)]);
+    let mut driver = Driver::new(vec![source], HeaderGraph::new(vec![chain]), TIMESTAMP_RANGE_DEFAULT, headers)
+        .unwrap();
+    driver.drive().unwrap();
+
+    // the original timestamp is written as is, in seconds like the input
+    let written = std::fs::read_to_string(output).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert!(lines[0].ends_with(",original"), "{}", written);
+    assert!(lines[1].starts_with("1451624340,") && lines[1].ends_with(",1451624400"), "{}", written);
+}
+
+#[test]
+fn test_ts_shift_dc() {
+    let path = "./tests/output/test_ts_shift.dc";
+    let mut header = Header::new(vec!["l".to_string()], vec![FieldType::Long]);
+    let mut data_sink = Box::new(DCSink::new(&Some(path.to_string()), DCOutputConfig::new_default()).unwrap())
+        .process_header(&mut header).unwrap();
+    for (i, timestamp) in [1546318800_000000001, 1546318800_500000000].iter().enumerate() {
+        data_sink.write_row(Row { timestamp: *timestamp, field_values: vec![FieldValue::Long(i as i64)] })
+            .unwrap();
+    }
+    data_sink.finish().unwrap();
+
+    // the shift is given in seconds and dc timestamps are nanos
+    let mut source = DCSource::new(File::open(path).unwrap()).unwrap();
+    let mut adjust = TimestampAdjustConfig::new();
+    adjust.set_shift(-60);
+    adjust.set_nanos(true);
+    let mut header = source.header().clone();
+    let mut data_sink = adjust.boxed().process_header(&mut header).unwrap();
+    let mut timestamps = Vec::new();
+    while let Some(row) = Source::next_row(&mut source).unwrap() {
+        timestamps.push(data_sink.write_row(row).unwrap().unwrap().timestamp);
+    }
+    assert_eq!(timestamps, vec![1546318740_000000001, 1546318740_500000000]);
+}